@@ -0,0 +1,86 @@
+//! Criterion benchmarks for the homomorphic SQL functions over large tables.
+//!
+//! Run with `cargo bench -p fhesql`. Each benchmark evaluates one query over
+//! a table of `FHESQL_BENCH_ROWS` rows (100 000 by default) and reports rows
+//! per second; ciphertexts are under a key with a modulus of
+//! `FHESQL_BENCH_BITS` bits (1024 by default). Compare commits with
+//! `--save-baseline` and `--baseline` as described in the `paillier_rs`
+//! benchmarks.
+//!
+//! Tables of fewer than [`CACHE_SIZE`](fhesql::functions::CACHE_SIZE) rows
+//! stay in the functions' cache from one iteration to the next, so they
+//! measure cached reads rather than a scan of new ciphertexts.
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use fhesql::codec::CiphertextCodec;
+use fhesql::functions::register_functions;
+use num_bigint::BigUint;
+use paillier_rs::encrypt::paillier_encrypt;
+use paillier_rs::keygen::paillier_keygen;
+use rusqlite::types::Value;
+use rusqlite::{params, Connection};
+use std::time::Duration;
+
+fn env_or(name: &str, default: usize) -> usize {
+    std::env::var(name).map_or(default, |v| v.parse().unwrap_or_else(|_| panic!("{} must be an integer", name)))
+}
+
+/// A table `emp (salary, bonus, salary_text)` of encryptions of 1, 2, ...,
+/// with `salary_text` in the TEXT format of unmigrated databases. Row i is
+/// derived from row i - 1 with one multiplication, which keeps setup fast.
+fn database(rows: usize, bits: usize) -> (Connection, Vec<u8>) {
+    let (pubkey, _) = paillier_keygen(bits / 2);
+    let codec = CiphertextCodec::new(&pubkey);
+    let n_sq = &pubkey.0 * &pubkey.0;
+    let one = paillier_encrypt(&pubkey, &BigUint::from(1u32));
+    let mut ciphertexts = Vec::with_capacity(rows);
+    let mut c = one.clone();
+    for _ in 0..rows {
+        ciphertexts.push(c.clone());
+        c = (&c * &one) % &n_sq;
+    }
+
+    let mut conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("CREATE TABLE emp (salary BLOB, bonus BLOB, salary_text TEXT)").unwrap();
+    let tx = conn.transaction().unwrap();
+    {
+        let mut insert = tx.prepare("INSERT INTO emp VALUES (?1, ?2, ?3)").unwrap();
+        for (i, salary) in ciphertexts.iter().enumerate() {
+            let bonus = &ciphertexts[(7 * i + 3) % rows];
+            insert.execute(params![codec.encode(salary), codec.encode(bonus), salary.to_str_radix(10)]).unwrap();
+        }
+    }
+    tx.commit().unwrap();
+    register_functions(&conn, &pubkey).unwrap();
+    (conn, codec.encode(&one))
+}
+
+fn functions(c: &mut Criterion) {
+    let rows = env_or("FHESQL_BENCH_ROWS", 100_000);
+    let (conn, one) = database(rows, env_or("FHESQL_BENCH_BITS", 1024));
+    let mut group = c.benchmark_group(format!("functions/{}_rows", rows));
+    group.throughput(Throughput::Elements(rows as u64));
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(20));
+
+    let queries = [
+        ("sum", "SELECT FHESUM(salary) FROM emp"),
+        ("sum_text", "SELECT FHESUM(salary_text) FROM emp"),
+        ("sum_and_avg", "SELECT FHESUM(salary), FHEAVG(salary) FROM emp"),
+        ("add_columns", "SELECT count(FHEADD(salary, bonus)) FROM emp"),
+        ("add_parameter", "SELECT count(FHEADD(salary, ?1)) FROM emp"),
+        ("sub_columns", "SELECT count(FHESUB(salary, bonus)) FROM emp"),
+        ("mul_negative_constant", "SELECT count(FHEMULCONST(salary, -3)) FROM emp"),
+        ("add_negative_constant", "SELECT count(FHEADDCONST(salary, -3)) FROM emp"),
+    ];
+    for (name, sql) in queries {
+        let mut stmt = conn.prepare(sql).unwrap();
+        let params: &[Value] = if stmt.parameter_count() > 0 { &[Value::Blob(one.clone())] } else { &[] };
+        group.bench_function(name, |b| {
+            b.iter(|| stmt.query_row(rusqlite::params_from_iter(params), |row| row.get::<_, Value>(0)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, functions);
+criterion_main!(benches);
@@ -0,0 +1,145 @@
+use fhesql::cli::{option, required};
+use fhesql::client::{Client, Rows};
+use paillier_rs::encoding::decode_private_key;
+use rusqlite::types::Value;
+use std::error::Error;
+use std::fs;
+
+const USAGE: &str = "usage:
+  fhesql-client --addr <host:port> --key <private key file> execute <sql> [param...]
+  fhesql-client --addr <host:port> --key <private key file> [--decrypt <column,...>]
+                query <sql> [param...]
+  fhesql-client --addr <host:port> --key <private key file> [--batch-size <n>]
+                sql <sql> [param...]
+  fhesql-client --addr <host:port> --key <private key file> [--tag <tag column>]
+                declare <table> <column>
+
+Parameters of the form enc:<integer> are encrypted before they are sent;
+NULL, integers and other text are sent as they are. Query results are
+printed tab-separated, with the --decrypt columns decrypted.
+
+sql rewrites a statement written against plaintext values for the columns
+declared encrypted with declare: literals and integer parameters bound to
+those columns are encrypted, SUM and AVG run on ciphertexts, and encrypted
+result columns are decrypted. Columns declared with --tag also get a
+deterministic tag column, so equality filters, GROUP BY and joins on them
+work too. Range filters on encrypted values take extra round trips, in
+which the client tells the server the signs of masked values, at most
+--batch-size (default 256) per round trip.";
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Arguments that are neither `--name` options nor their values.
+fn positional(args: &[String]) -> Vec<&str> {
+    let mut rest = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        if args[i].starts_with("--") {
+            i += 2;
+        } else {
+            rest.push(args[i].as_str());
+            i += 1;
+        }
+    }
+    rest
+}
+
+fn parameter<S: std::io::Read + std::io::Write>(client: &Client<S>, arg: &str) -> Result<Value, Box<dyn Error>> {
+    if let Some(m) = arg.strip_prefix("enc:") {
+        return Ok(client.encrypt(m.parse::<i64>()?));
+    }
+    if arg == "NULL" {
+        return Ok(Value::Null);
+    }
+    Ok(match arg.parse::<i64>() {
+        Ok(i) => Value::Integer(i),
+        Err(_) => Value::Text(arg.to_string()),
+    })
+}
+
+fn print_rows(rows: &Rows) {
+    println!("{}", rows.columns.join("\t"));
+    for row in &rows.rows {
+        println!("{}", row.iter().map(display).collect::<Vec<_>>().join("\t"));
+    }
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(f) => f.to_string(),
+        Value::Text(s) => s.clone(),
+        Value::Blob(b) => format!("x'{}'", b.iter().map(|byte| format!("{:02x}", byte)).collect::<String>()),
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let key_path = required(&args, "key", USAGE)?;
+    let privkey = decode_private_key(&fs::read_to_string(key_path)?)
+        .ok_or_else(|| format!("Failed to parse private key {}", key_path))?;
+    let positional = positional(&args);
+    let (command, sql, params) = match positional.as_slice() {
+        [command, sql, params @ ..] => (*command, *sql, params),
+        _ => return Err(USAGE.into()),
+    };
+
+    let mut client = Client::connect(required(&args, "addr", USAGE)?, privkey)?;
+    if command == "declare" {
+        return match params {
+            [column] => match option(&args, "tag") {
+                Some(tag_column) => client.declare_tagged(sql, column, Some(tag_column)),
+                None => client.declare_encrypted(sql, column),
+            },
+            _ => Err(USAGE.into()),
+        };
+    }
+    let params = params.iter().map(|p| parameter(&client, p)).collect::<Result<Vec<_>, _>>()?;
+    match command {
+        "sql" => {
+            if let Some(batch_size) = option(&args, "batch-size") {
+                client.set_batch_size(batch_size.parse()?);
+            }
+            let rewritten = client.rewrite(sql)?;
+            if rewritten.returns_rows {
+                print_rows(&client.query_rewritten(&rewritten, &params)?);
+            } else {
+                println!("{} rows changed", client.execute_rewritten(&rewritten, &params)?);
+            }
+        }
+        "execute" => println!("{} rows changed", client.execute(sql, &params)?),
+        "query" => {
+            let rows = client.query(sql, &params)?;
+            let decrypt = option(&args, "decrypt")
+                .map(|columns| {
+                    columns
+                        .split(',')
+                        .map(|c| rows.column(c).ok_or_else(|| format!("No column {} in the result", c)))
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .transpose()?
+                .unwrap_or_default();
+            println!("{}", rows.columns.join("\t"));
+            for row in &rows.rows {
+                let mut cells = Vec::with_capacity(row.len());
+                for (i, value) in row.iter().enumerate() {
+                    if !decrypt.contains(&i) {
+                        cells.push(display(value));
+                        continue;
+                    }
+                    let m = client.decrypt(value).map_err(|e| format!("column {}: {}", rows.columns[i], e))?;
+                    cells.push(m.map_or_else(|| "NULL".to_string(), |m| m.to_string()));
+                }
+                println!("{}", cells.join("\t"));
+            }
+        }
+        _ => return Err(USAGE.into()),
+    }
+    Ok(())
+}
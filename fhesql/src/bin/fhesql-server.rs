@@ -0,0 +1,34 @@
+use fhesql::cli::{option, required};
+use fhesql::server::Server;
+use std::error::Error;
+use std::net::TcpListener;
+
+const USAGE: &str = "usage:
+  fhesql-server --db <path> [--addr <host:port>]
+
+Serves the database under its stored public key; the private key stays with
+the clients. Listens on 127.0.0.1:7878 by default.";
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let db = required(&args, "db", USAGE)?;
+    let addr = option(&args, "addr").unwrap_or("127.0.0.1:7878");
+    let server = Server::open(db)?;
+    let listener = TcpListener::bind(addr)?;
+    println!(
+        "Serving {} with key {} (version {}) on {}",
+        db,
+        server.key_id(),
+        server.version(),
+        listener.local_addr()?
+    );
+    server.serve(&listener)?;
+    Ok(())
+}
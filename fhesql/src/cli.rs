@@ -0,0 +1,28 @@
+use std::error::Error;
+
+/// Returns the value following `--name` in `args`.
+pub fn option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a.strip_prefix("--") == Some(name))
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+/// Like [`option`], failing with `usage` if `--name` is missing.
+pub fn required<'a>(args: &'a [String], name: &str, usage: &str) -> Result<&'a str, Box<dyn Error>> {
+    option(args, name).ok_or_else(|| format!("missing --{}\n{}", name, usage).into())
+}
+
+/// Whether the flag `--name`, which takes no value, is in `args`.
+pub fn flag(args: &[String], name: &str) -> bool {
+    args.iter().any(|a| a.strip_prefix("--") == Some(name))
+}
+
+/// The last argument, if it is not an option or the value of one.
+pub fn last_positional(args: &[String]) -> Option<&str> {
+    match args {
+        [.., before, last] if !before.starts_with("--") && !last.starts_with("--") => Some(last),
+        [last] if !last.starts_with("--") => Some(last),
+        _ => None,
+    }
+}
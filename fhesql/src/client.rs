@@ -0,0 +1,351 @@
+use crate::codec::CiphertextCodec;
+use crate::compare::{is_positive, DEFAULT_BATCH_SIZE};
+use crate::functions::parse_average;
+use crate::keys::DatabaseKeys;
+use crate::protocol::{read_frame, write_frame, Request, Response, Statement};
+use crate::rewrite::{rewrite, ColumnKind, Rewritten};
+use crate::schema::{quote, Schema, DECLARE_SQL, SCHEMA_QUERY};
+use crate::tag::TagKey;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use paillier_rs::ciphertext::Ciphertext;
+use paillier_rs::decrypt::paillier_decrypt;
+use paillier_rs::encrypt::paillier_encrypt;
+use paillier_rs::fixed_point::{decode_signed, encode_signed};
+use paillier_rs::keygen::{check_key_pair, PrivateKey};
+use paillier_rs::keyid::KeyId;
+use paillier_rs::keyproof::import_public_key;
+use paillier_rs::stats::decrypt_mean;
+use rusqlite::types::Value;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+
+/// Result set of [`Client::query`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Rows {
+    /// Index of the column called `name`.
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c == name)
+    }
+}
+
+/// The key holder's side of a connection to a [`Server`](crate::server::Server).
+/// It encrypts parameters and decrypts results locally; the private key
+/// never leaves the client.
+pub struct Client<S> {
+    stream: S,
+    keys: DatabaseKeys,
+    codec: CiphertextCodec,
+    tag_key: TagKey,
+    schema: Schema,
+    batch_size: usize,
+    round_trips: u64,
+}
+
+impl Client<TcpStream> {
+    pub fn connect(addr: impl ToSocketAddrs, privkey: PrivateKey) -> Result<Self, Box<dyn Error>> {
+        Client::new(TcpStream::connect(addr)?, privkey)
+    }
+}
+
+impl<S: Read + Write> Client<S> {
+    /// Fetches the server's public key, verifies its proof and checks that
+    /// `privkey` belongs to it, then loads the registry of encrypted columns.
+    pub fn new(mut stream: S, privkey: PrivateKey) -> Result<Self, Box<dyn Error>> {
+        let (version, key) = match call(&mut stream, &Request::PublicKey)? {
+            Response::PublicKey { version, key } => (version, key),
+            _ => return Err("Unexpected response to a public key request".into()),
+        };
+        let pubkey = import_public_key(&key).ok_or("Server sent a malformed key or one whose proof does not verify")?;
+        if check_key_pair(&pubkey, &privkey).is_err() {
+            return Err(format!(
+                "Private key does not match key {} (version {}) of the server",
+                KeyId::of(&pubkey),
+                version
+            )
+            .into());
+        }
+        let codec = CiphertextCodec::new(&pubkey);
+        let tag_key = TagKey::derive(&privkey);
+        let keys = DatabaseKeys { version, pubkey, privkey };
+        let schema = Schema::new();
+        // The public key request was the first round trip.
+        let mut client =
+            Client { stream, keys, codec, tag_key, schema, batch_size: DEFAULT_BATCH_SIZE, round_trips: 1 };
+        client.reload_schema()?;
+        Ok(client)
+    }
+
+    pub fn keys(&self) -> &DatabaseKeys {
+        &self.keys
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Sets how many masked values the server sends per round trip when it
+    /// resolves comparisons of encrypted values.
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+
+    /// Messages sent to the server so far, each answered by one response.
+    pub fn round_trips(&self) -> u64 {
+        self.round_trips
+    }
+
+    /// Re-reads the registry of encrypted columns from the server.
+    pub fn reload_schema(&mut self) -> Result<(), Box<dyn Error>> {
+        let rows = self.query(SCHEMA_QUERY, &[])?;
+        let mut schema = Schema::new();
+        for row in &rows.rows {
+            match row.as_slice() {
+                [Value::Text(table), Value::Text(column), Value::Null] => schema.declare(table, column),
+                [Value::Text(table), Value::Text(column), Value::Text(tag_column)] => {
+                    schema.declare_tagged(table, column, tag_column)
+                }
+                _ => return Err("Malformed registry of encrypted columns".into()),
+            }
+        }
+        self.schema = schema;
+        Ok(())
+    }
+
+    /// Declares `table.column` encrypted on the server.
+    pub fn declare_encrypted(&mut self, table: &str, column: &str) -> Result<(), Box<dyn Error>> {
+        let names = [Value::Text(table.to_lowercase()), Value::Text(column.to_lowercase()), Value::Null];
+        self.execute(DECLARE_SQL, &names)?;
+        self.schema.declare(table, column);
+        Ok(())
+    }
+
+    /// Declares `table.column` encrypted with its tags in `tag_column`,
+    /// which defaults to `<column>_tag`. The tag column is added to the
+    /// table and indexed if it does not exist, and the tags of the rows
+    /// already stored are filled in, all in one transaction on the server
+    /// (see [`Request::Transaction`]).
+    pub fn declare_tagged(
+        &mut self,
+        table: &str,
+        column: &str,
+        tag_column: Option<&str>,
+    ) -> Result<(), Box<dyn Error>> {
+        let tag_column = tag_column.map_or_else(|| format!("{}_tag", column), str::to_string).to_lowercase();
+        let statements = self.tag_statements(table, column, &tag_column)?;
+        self.executed(&Request::Transaction { statements })?;
+        self.schema.declare_tagged(table, column, &tag_column);
+        Ok(())
+    }
+
+    /// The statements that add and fill the tag column and declare it.
+    fn tag_statements(
+        &mut self,
+        table: &str,
+        column: &str,
+        tag_column: &str,
+    ) -> Result<Vec<Statement>, Box<dyn Error>> {
+        let (t, c, tag) = (quote(table), quote(column), quote(tag_column));
+        let existing = self.query("SELECT name FROM pragma_table_info(?1)", &[Value::Text(table.to_string())])?;
+        let has_column = |name: &str| {
+            existing.rows.iter().any(|row| matches!(&row[0], Value::Text(c) if c.eq_ignore_ascii_case(name)))
+        };
+        if existing.rows.is_empty() {
+            return Err(format!("No table {}", table).into());
+        }
+        if !has_column(column) {
+            return Err(format!("No column {} in table {}", column, table).into());
+        }
+        let mut statements = Vec::new();
+        if !has_column(tag_column) {
+            statements.push((format!("ALTER TABLE {} ADD COLUMN {} TEXT", t, tag), Vec::new()));
+        }
+        let index = quote(&format!("{}_{}_idx", table, tag_column));
+        statements.push((format!("CREATE INDEX IF NOT EXISTS {} ON {} ({})", index, t, tag), Vec::new()));
+
+        let rows = self.query(&format!("SELECT rowid, {} FROM {} WHERE {} IS NOT NULL", c, t, c), &[])?;
+        let update = format!("UPDATE {} SET {} = ?1 WHERE rowid = ?2", t, tag);
+        for row in &rows.rows {
+            let m = self.decrypt(&row[1]).map_err(|e| format!("{}.{}: {}", table, column, e))?;
+            let value = m.map_or(Value::Null, |m| self.tag(m));
+            statements.push((update.clone(), vec![value, row[0].clone()]));
+        }
+        let names = [table, column, tag_column].map(|name| Value::Text(name.to_lowercase()));
+        statements.push((DECLARE_SQL.to_string(), names.to_vec()));
+        Ok(statements)
+    }
+
+    /// Encrypts a signed integer as a BLOB parameter.
+    pub fn encrypt(&self, m: impl Into<BigInt>) -> Value {
+        Value::Blob(self.encrypt_to_blob(&m.into()))
+    }
+
+    /// The deterministic tag of a signed integer, as stored in tag columns.
+    pub fn tag(&self, m: impl Into<BigInt>) -> Value {
+        Value::Text(self.tag_key.tag(&m.into()))
+    }
+
+    fn encrypt_to_blob(&self, m: &BigInt) -> Vec<u8> {
+        let pubkey = &self.keys.pubkey;
+        self.codec.encode(&paillier_encrypt(pubkey, &encode_signed(m, &pubkey.0)))
+    }
+
+    /// Decrypts a ciphertext returned by the server, BLOB or TEXT, or
+    /// `None` for NULL.
+    pub fn decrypt(&self, value: &Value) -> Result<Option<BigInt>, Box<dyn Error>> {
+        let DatabaseKeys { pubkey, privkey, .. } = &self.keys;
+        let Some(c) = self.codec.decode(value.into())? else { return Ok(None) };
+        Ok(Some(decode_signed(&paillier_decrypt(privkey, pubkey, c.as_biguint()), &pubkey.0)))
+    }
+
+    /// Runs a statement and returns the number of changed rows.
+    pub fn execute(&mut self, sql: &str, params: &[Value]) -> Result<u64, Box<dyn Error>> {
+        self.executed(&Request::Execute { sql: sql.to_string(), params: params.to_vec() })
+    }
+
+    pub fn query(&mut self, sql: &str, params: &[Value]) -> Result<Rows, Box<dyn Error>> {
+        self.rows(&Request::Query { sql: sql.to_string(), params: params.to_vec() })
+    }
+
+    fn executed(&mut self, request: &Request) -> Result<u64, Box<dyn Error>> {
+        match self.exchange(request)? {
+            Response::Executed { changes } => Ok(changes),
+            _ => Err("Unexpected response to an execute request".into()),
+        }
+    }
+
+    fn rows(&mut self, request: &Request) -> Result<Rows, Box<dyn Error>> {
+        match self.exchange(request)? {
+            Response::Rows { columns, rows } => Ok(Rows { columns, rows }),
+            _ => Err("Unexpected response to a query request".into()),
+        }
+    }
+
+    /// Sends `request` and waits for its final response, answering the
+    /// batches of masked values the server sends for comparisons on the way.
+    fn exchange(&mut self, request: &Request) -> Result<Response, Box<dyn Error>> {
+        self.round_trips += 1;
+        let mut response = call(&mut self.stream, request)?;
+        while let Response::Masked { values } = response {
+            self.round_trips += 1;
+            match self.signs(&values) {
+                Ok(positive) => response = call(&mut self.stream, &Request::Signs { positive })?,
+                Err(e) => {
+                    // No signs make the server abandon the request, which
+                    // keeps both sides in step.
+                    let _ = call(&mut self.stream, &Request::Signs { positive: Vec::new() });
+                    return Err(e);
+                }
+            }
+        }
+        Ok(response)
+    }
+
+    fn signs(&self, values: &[String]) -> Result<Vec<bool>, Box<dyn Error>> {
+        let DatabaseKeys { pubkey, privkey, .. } = &self.keys;
+        values
+            .iter()
+            .map(|v| Ok(is_positive(privkey, pubkey, &Ciphertext::from_untrusted_str(v, pubkey)?)))
+            .collect()
+    }
+
+    /// Wraps the request for a rewritten statement in a
+    /// [`Request::Compare`] if it has comparisons to resolve.
+    fn with_comparisons(&self, rewritten: &Rewritten, statement: Request) -> Request {
+        if rewritten.comparisons.is_empty() {
+            return statement;
+        }
+        Request::Compare {
+            comparisons: rewritten.comparisons.clone(),
+            batch_size: self.batch_size as u64,
+            statement: Box::new(statement),
+        }
+    }
+
+    /// Rewrites `sql`, written against plaintext columns, for the encrypted
+    /// columns of the database; see [`rewrite`].
+    pub fn rewrite(&self, sql: &str) -> Result<Rewritten, Box<dyn Error>> {
+        rewrite(sql, &self.schema, &mut |m| self.encrypt_to_blob(m), &|m| self.tag_key.tag(m))
+    }
+
+    /// Binds the parameters added for tags and encrypts the parameters that
+    /// bind encrypted columns. Both take integers or NULL.
+    fn bind(&self, rewritten: &Rewritten, params: &[Value]) -> Result<Vec<Value>, Box<dyn Error>> {
+        let mut bound = params.to_vec();
+        for &(added, source) in &rewritten.tag_params {
+            let tag = integer_param(params, source)?.map_or(Value::Null, |m| self.tag(m));
+            if bound.len() < added {
+                bound.resize(added, Value::Null);
+            }
+            bound[added - 1] = tag;
+        }
+        for &n in &rewritten.encrypted_params {
+            if let Some(m) = integer_param(params, n)? {
+                bound[n - 1] = self.encrypt(m);
+            }
+        }
+        Ok(bound)
+    }
+
+    /// Runs a rewritten statement and returns the number of changed rows.
+    pub fn execute_rewritten(&mut self, rewritten: &Rewritten, params: &[Value]) -> Result<u64, Box<dyn Error>> {
+        let params = self.bind(rewritten, params)?;
+        let request = Request::Execute { sql: rewritten.sql.clone(), params };
+        self.executed(&self.with_comparisons(rewritten, request))
+    }
+
+    /// Runs a rewritten query and decrypts its encrypted result columns:
+    /// sums become integers, or text if they do not fit an `i64`, and
+    /// averages become reals.
+    pub fn query_rewritten(&mut self, rewritten: &Rewritten, params: &[Value]) -> Result<Rows, Box<dyn Error>> {
+        let params = self.bind(rewritten, params)?;
+        let request = Request::Query { sql: rewritten.sql.clone(), params };
+        let mut rows = self.rows(&self.with_comparisons(rewritten, request))?;
+        for row in &mut rows.rows {
+            for (i, value) in row.iter_mut().enumerate() {
+                *value = match (rewritten.column_kind(i), &*value) {
+                    (ColumnKind::Plain, _) | (_, Value::Null) => continue,
+                    (ColumnKind::Encrypted, _) => match self.decrypt(value)? {
+                        Some(m) => m.to_i64().map_or_else(|| Value::Text(m.to_string()), Value::Integer),
+                        None => Value::Null,
+                    },
+                    (ColumnKind::Average, _) => {
+                        let average = parse_average(&self.codec, (&*value).into())
+                            .map_err(|e| format!("Malformed FHEAVG result: {}", e))?
+                            .ok_or("Malformed FHEAVG result")?;
+                        Value::Real(decrypt_mean(&self.keys.privkey, &self.keys.pubkey, &average, 1).1)
+                    }
+                };
+            }
+        }
+        Ok(rows)
+    }
+}
+
+/// The value of parameter `?n`, which binds an encrypted column and must be
+/// an integer or NULL.
+fn integer_param(params: &[Value], n: usize) -> Result<Option<i64>, Box<dyn Error>> {
+    match n.checked_sub(1).and_then(|i| params.get(i)) {
+        Some(Value::Null) => Ok(None),
+        Some(Value::Integer(m)) => Ok(Some(*m)),
+        Some(_) => Err(format!("Parameter ?{} binds an encrypted column and must be an integer", n).into()),
+        None => Err(format!("Missing parameter ?{}", n).into()),
+    }
+}
+
+/// Sends `request` and waits for its response. An error response becomes
+/// an `Err` carrying the server's message.
+fn call<S: Read + Write>(stream: &mut S, request: &Request) -> Result<Response, Box<dyn Error>> {
+    write_frame(stream, &request.encode())?;
+    let frame = read_frame(stream)?.ok_or("Server closed the connection")?;
+    match Response::decode(&frame)? {
+        Response::Error(message) => Err(format!("server: {}", message).into()),
+        response => Ok(response),
+    }
+}
@@ -0,0 +1,131 @@
+use num_bigint::BigUint;
+use paillier_rs::ciphertext::Ciphertext;
+use paillier_rs::encoding::{decode_ciphertext, decode_ciphertext_blob, encode_ciphertext_blob, CIPHERTEXT_BLOB_VERSION};
+use paillier_rs::error::PaillierError;
+use paillier_rs::keygen::PublicKey;
+use paillier_rs::keyid::KeyId;
+use paillier_rs::stats::EncryptedSum;
+use rusqlite::types::ValueRef;
+
+/// First byte of an `FHEAVG` result. Ciphertext blob versions count up
+/// from 1, so this counts down from the top to keep an average from ever
+/// being read as a ciphertext, or the other way round.
+pub const AVERAGE_BLOB_VERSION: u8 = 0xff;
+
+const _: () = assert!(AVERAGE_BLOB_VERSION != CIPHERTEXT_BLOB_VERSION);
+
+/// Length of the big-endian row count in an average blob.
+const COUNT_LEN: usize = 8;
+
+/// Reads and writes the ciphertexts of a database under one key.
+///
+/// Ciphertexts are written as BLOBs in the format of
+/// [`encode_ciphertext_blob`], whose header records the format version and
+/// the [`KeyId`] of the key. Older databases store base-10 TEXT, which is
+/// about 2.4 times larger and slower to parse; it is still read, so a
+/// database keeps working while it is converted with
+/// [`migrate_to_blobs`](crate::migrate::migrate_to_blobs).
+#[derive(Clone, Debug)]
+pub struct CiphertextCodec {
+    pubkey: PublicKey,
+    key_id: KeyId,
+}
+
+impl CiphertextCodec {
+    pub fn new(pubkey: &PublicKey) -> Self {
+        CiphertextCodec { pubkey: pubkey.clone(), key_id: KeyId::of(pubkey) }
+    }
+
+    pub fn pubkey(&self) -> &PublicKey {
+        &self.pubkey
+    }
+
+    pub fn encode(&self, c: &BigUint) -> Vec<u8> {
+        encode_ciphertext_blob(c, &self.key_id)
+    }
+
+    /// Writes the encrypted sum and row count behind a mean, see
+    /// [`encode_average_blob`].
+    pub fn encode_average(&self, average: &EncryptedSum) -> Vec<u8> {
+        encode_average_blob(&average.sum, average.count, &self.key_id)
+    }
+
+    /// Reads a stored ciphertext, BLOB or TEXT, and validates it; NULL is
+    /// `None`. A BLOB must name this codec's key.
+    pub fn decode(&self, value: ValueRef<'_>) -> Result<Option<Ciphertext>, DecodeError> {
+        match value {
+            ValueRef::Null => return Ok(None),
+            ValueRef::Integer(_) | ValueRef::Real(_) => return Err(DecodeError::NotACiphertext),
+            ValueRef::Text(_) | ValueRef::Blob(_) => {}
+        }
+        let (found, value) = parse_stored(value).ok_or(PaillierError::InvalidCiphertext)?;
+        if let Some(found) = found.filter(|&found| found != self.key_id) {
+            return Err(PaillierError::WrongKey { expected: self.key_id, found }.into());
+        }
+        Ok(Some(Ciphertext::from_untrusted(value, &self.pubkey)?))
+    }
+}
+
+/// Parses a stored ciphertext without validating it: a BLOB, which also
+/// yields the [`KeyId`] it records, or base-10 TEXT.
+pub fn parse_stored(value: ValueRef<'_>) -> Option<(Option<KeyId>, BigUint)> {
+    match value {
+        ValueRef::Blob(blob) => decode_ciphertext_blob(blob).map(|(key_id, c)| (Some(key_id), c)),
+        ValueRef::Text(text) => Some((None, decode_ciphertext(std::str::from_utf8(text).ok()?)?)),
+        _ => None,
+    }
+}
+
+/// Encodes the sum and row count of `FHEAVG` as a BLOB:
+///
+/// ```text
+/// version (1 byte) | key id (KeyId::LEN bytes) | count (8 bytes, big-endian) | sum (big-endian)
+/// ```
+///
+/// The version is [`AVERAGE_BLOB_VERSION`].
+pub fn encode_average_blob(sum: &BigUint, count: u64, key_id: &KeyId) -> Vec<u8> {
+    let mut blob = Vec::with_capacity(1 + KeyId::LEN + COUNT_LEN + (sum.bits() as usize).div_ceil(8));
+    blob.push(AVERAGE_BLOB_VERSION);
+    blob.extend_from_slice(key_id.as_bytes());
+    blob.extend_from_slice(&count.to_be_bytes());
+    blob.extend_from_slice(&sum.to_bytes_be());
+    blob
+}
+
+/// Decodes the output of [`encode_average_blob`] into its key id, count and
+/// sum, without checking the sum. Blobs of another version are rejected.
+pub fn decode_average_blob(blob: &[u8]) -> Option<(KeyId, u64, BigUint)> {
+    let (&version, rest) = blob.split_first()?;
+    if version != AVERAGE_BLOB_VERSION || rest.len() <= KeyId::LEN + COUNT_LEN {
+        return None;
+    }
+    let (key_id, rest) = rest.split_at(KeyId::LEN);
+    let (count, sum) = rest.split_at(COUNT_LEN);
+    let count = u64::from_be_bytes(count.try_into().ok()?);
+    Some((KeyId::from_slice(key_id)?, count, BigUint::from_bytes_be(sum)))
+}
+
+/// Why [`CiphertextCodec::decode`] rejected a value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The value is a number, not a BLOB or TEXT.
+    NotACiphertext,
+    Invalid(PaillierError),
+}
+
+impl From<PaillierError> for DecodeError {
+    fn from(e: PaillierError) -> Self {
+        DecodeError::Invalid(e)
+    }
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::NotACiphertext => write!(f, "expected a ciphertext blob or string"),
+            DecodeError::Invalid(e) => write!(f, "invalid ciphertext: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
@@ -0,0 +1,82 @@
+use num_bigint::{BigInt, BigUint, RandBigInt};
+use num_traits::{One, Signed};
+use paillier_rs::arithmetic::{paillier_add, paillier_scalar_mul};
+use paillier_rs::ciphertext::Ciphertext;
+use paillier_rs::decrypt::paillier_decrypt;
+use paillier_rs::encrypt::paillier_encrypt_with_rng;
+use paillier_rs::fixed_point::{decode_signed, encode_signed};
+use paillier_rs::keygen::{PrivateKey, PublicKey};
+use rand::{CryptoRng, RngCore};
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Bits of the multiplicative mask \(\rho\).
+pub const MASK_BITS: u64 = 40;
+
+/// Bits of \(|2d + 1|\) the protocol handles; enough for the difference of
+/// any two `i64` values.
+pub const OPERAND_BITS: u64 = 66;
+
+/// Masked values per round trip when no batch size is given.
+pub const DEFAULT_BATCH_SIZE: usize = 256;
+
+/// Results of the comparisons of the current statement, by comparison
+/// number and rowid.
+pub type ComparisonResults = Arc<Mutex<HashMap<(i64, i64), bool>>>;
+
+/// Checks that the plaintext space of `pubkey` holds every masked value.
+pub fn check_mask_room(pubkey: &PublicKey) -> Result<(), String> {
+    let bits = pubkey.0.bits();
+    if bits < MASK_BITS + OPERAND_BITS + 2 {
+        return Err(format!("A {}-bit key is too small for comparisons of encrypted values", bits));
+    }
+    Ok(())
+}
+
+/// Masks \(Enc(d')\), where \(d' = 2d + 1\) is odd, for the key holder.
+///
+/// Range predicates reduce to the sign of \(d'\): `x > k` holds iff
+/// \(2(x - k) - 1 > 0\), and so on. The server cannot decrypt, and the key
+/// holder must not learn \(d'\), so the server draws \(\rho \in [2^{39},
+/// 2^{40})\), \(\delta \in [0, \rho)\) and a sign \(s = \pm 1\) and sends
+/// \[ Enc(s \cdot (\rho d' + \delta)). \]
+/// Since \(|\rho d'| \ge \rho > \delta\), the masked value has the sign of
+/// \(s d'\) and is never zero. The key holder returns its sign
+/// ([`is_positive`]) and the server, which knows \(s\), recovers that of
+/// \(d'\): the result is `positive != flipped`, where `flipped` is the second
+/// value returned here.
+///
+/// The key holder learns \(|d'|\) only up to the random factor \(\rho\), and
+/// nothing about its sign; the server learns the predicate's result for each
+/// row, as it must to filter. Security is against semi-honest parties.
+pub fn mask_with_rng<R: RngCore + CryptoRng + ?Sized>(
+    pubkey: &PublicKey,
+    c: &Ciphertext,
+    rng: &mut R,
+) -> (BigUint, bool) {
+    let rho = rng.gen_biguint_range(&(BigUint::one() << (MASK_BITS - 1)), &(BigUint::one() << MASK_BITS));
+    let delta = rng.gen_biguint_below(&rho);
+    let flipped = rng.next_u32() & 1 == 1;
+    let sign = if flipped { -1 } else { 1 };
+    let n = &pubkey.0;
+    let scaled = paillier_scalar_mul(c.as_biguint(), &encode_signed(&(BigInt::from(rho) * sign), n), pubkey);
+    let offset = paillier_encrypt_with_rng(pubkey, &encode_signed(&(BigInt::from(delta) * sign), n), rng);
+    (paillier_add(&scaled, &offset, pubkey), flipped)
+}
+
+/// The key holder's answer for a masked value: whether it is positive.
+pub fn is_positive(privkey: &PrivateKey, pubkey: &PublicKey, masked: &Ciphertext) -> bool {
+    decode_signed(&paillier_decrypt(privkey, pubkey, masked.as_biguint()), &pubkey.0).is_positive()
+}
+
+/// Registers `FHECMP(id, rowid)` on `conn`: the result of comparison `id`
+/// for the row, as 1 or 0, or NULL if it was not computed, e.g. because the
+/// compared value is NULL.
+pub fn register_comparison_function(conn: &Connection, results: ComparisonResults) -> rusqlite::Result<()> {
+    conn.create_scalar_function("FHECMP", 2, FunctionFlags::SQLITE_UTF8, move |ctx| {
+        let key = (ctx.get::<i64>(0)?, ctx.get::<i64>(1)?);
+        Ok(results.lock().unwrap().get(&key).map(|&result| result as i64))
+    })
+}
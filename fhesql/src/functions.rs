@@ -0,0 +1,271 @@
+use crate::codec::{decode_average_blob, CiphertextCodec, DecodeError};
+use lru::LruCache;
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::One;
+use paillier_rs::ciphertext::Ciphertext;
+use paillier_rs::encrypt::sample_randomness;
+use paillier_rs::error::PaillierError;
+use paillier_rs::gcd::modinv;
+use paillier_rs::keygen::PublicKey;
+use paillier_rs::keyid::KeyId;
+use paillier_rs::stats::EncryptedSum;
+use rusqlite::functions::{Aggregate, Context, FunctionFlags};
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, Error, Result};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+
+/// Parsed ciphertexts kept per connection by [`register_functions`].
+pub const CACHE_SIZE: usize = 4096;
+
+/// Registers the homomorphic scalar and aggregate functions on `conn`.
+/// Ciphertexts are read as BLOBs or base-10 TEXT under `pubkey` (see
+/// [`CiphertextCodec`]) and returned as BLOBs; integer constants are signed
+/// and encoded modulo n. Every scalar function returns NULL if any
+/// argument is NULL; the aggregates skip NULLs like `SUM` and `AVG` do and
+/// return NULL for a group without values.
+///
+/// | function | result |
+/// |---|---|
+/// | `FHEADD(c1, c2)` | \(Enc(m_1 + m_2)\) |
+/// | `FHESUB(c1, c2)` | \(Enc(m_1 - m_2)\) |
+/// | `FHENEG(c)` | \(Enc(-m)\) |
+/// | `FHEADDCONST(c, k)` | \(Enc(m + k)\) |
+/// | `FHEMULCONST(c, k)` | \(Enc(k \cdot m)\) |
+/// | `FHERERAND(c)` | a fresh ciphertext of \(m\) |
+/// | `FHESUM(c)` | \(Enc(\sum m_i)\) |
+/// | `FHEAVG(c)` | \(Enc(\sum m_i)\) and the count, see [`parse_average`] |
+///
+/// The plaintext row count is available through the ordinary `COUNT(c)`.
+///
+/// Validating a ciphertext costs a gcd with n, so parsed ciphertexts are
+/// reused: a constant argument, such as a bound parameter, is parsed once
+/// per statement and kept by SQLite as auxiliary data, and the last
+/// [`CACHE_SIZE`] BLOBs read on the connection are kept in an LRU cache,
+/// which serves a value read by several calls of one row, as in
+/// `FHEADD(salary, salary)` or `SELECT FHESUM(salary), FHEAVG(salary)`.
+pub fn register_functions(conn: &Connection, pubkey: &PublicKey) -> Result<()> {
+    let deterministic = FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC;
+    let key = Arc::new(KeyContext::new(pubkey));
+
+    let k = key.clone();
+    conn.create_scalar_function("FHEADD", 2, deterministic, move |ctx| {
+        let Some((c1, c2)) = k.binary_args(ctx, "FHEADD")? else { return Ok(None) };
+        Ok(Some(k.encode(&k.add(c1.as_biguint(), c2.as_biguint()))))
+    })?;
+
+    let k = key.clone();
+    conn.create_scalar_function("FHESUB", 2, deterministic, move |ctx| {
+        let Some((c1, c2)) = k.binary_args(ctx, "FHESUB")? else { return Ok(None) };
+        Ok(Some(k.encode(&k.add(c1.as_biguint(), &k.negate(c2.as_biguint())))))
+    })?;
+
+    let k = key.clone();
+    conn.create_scalar_function("FHENEG", 1, deterministic, move |ctx| {
+        let Some(c) = k.scalar_arg(ctx, "FHENEG", 0)? else { return Ok(None) };
+        Ok(Some(k.encode(&k.negate(c.as_biguint()))))
+    })?;
+
+    let k = key.clone();
+    conn.create_scalar_function("FHEADDCONST", 2, deterministic, move |ctx| {
+        let Some(c) = k.scalar_arg(ctx, "FHEADDCONST", 0)? else { return Ok(None) };
+        let Some(m) = integer_arg(ctx, "FHEADDCONST", 1)? else { return Ok(None) };
+        // The trivial encryption g^m keeps the function deterministic; the
+        // sum still carries the randomness of `c`.
+        let cm = k.pow(&k.pubkey().1, &m);
+        Ok(Some(k.encode(&k.add(c.as_biguint(), &cm))))
+    })?;
+
+    let k = key.clone();
+    conn.create_scalar_function("FHEMULCONST", 2, deterministic, move |ctx| {
+        let Some(c) = k.scalar_arg(ctx, "FHEMULCONST", 0)? else { return Ok(None) };
+        let Some(m) = integer_arg(ctx, "FHEMULCONST", 1)? else { return Ok(None) };
+        Ok(Some(k.encode(&k.pow(c.as_biguint(), &m))))
+    })?;
+
+    // Not deterministic: every call must return a different ciphertext.
+    let k = key.clone();
+    conn.create_scalar_function("FHERERAND", 1, FunctionFlags::SQLITE_UTF8, move |ctx| {
+        let Some(c) = k.scalar_arg(ctx, "FHERERAND", 0)? else { return Ok(None) };
+        let n = &k.pubkey().0;
+        let zero = sample_randomness(n).modpow(n, &k.n_squared);
+        Ok(Some(k.encode(&k.add(c.as_biguint(), &zero))))
+    })?;
+
+    let sum = FheSum { key: key.clone(), name: "FHESUM" };
+    conn.create_aggregate_function("FHESUM", 1, deterministic, sum)?;
+    let avg = FheAvg(FheSum { key, name: "FHEAVG" });
+    conn.create_aggregate_function("FHEAVG", 1, deterministic, avg)?;
+
+    Ok(())
+}
+
+/// What the functions registered on one connection share: the key with
+/// \(n^2\) precomputed, and the cache of parsed ciphertexts.
+struct KeyContext {
+    codec: CiphertextCodec,
+    n_squared: BigUint,
+    cache: Mutex<LruCache<Vec<u8>, Arc<Ciphertext>>>,
+}
+
+impl KeyContext {
+    fn new(pubkey: &PublicKey) -> Self {
+        let cache = LruCache::new(NonZeroUsize::new(CACHE_SIZE).expect("the cache is not empty"));
+        KeyContext { codec: CiphertextCodec::new(pubkey), n_squared: &pubkey.0 * &pubkey.0, cache: Mutex::new(cache) }
+    }
+
+    fn pubkey(&self) -> &PublicKey {
+        self.codec.pubkey()
+    }
+
+    fn encode(&self, c: &BigUint) -> Vec<u8> {
+        self.codec.encode(c)
+    }
+
+    /// \(c_1 c_2 \bmod n^2\), an encryption of \(m_1 + m_2\).
+    fn add(&self, c1: &BigUint, c2: &BigUint) -> BigUint {
+        (c1 * c2) % &self.n_squared
+    }
+
+    /// \(c^{-1} \bmod n^2\), an encryption of \(-m\). Ciphertexts are
+    /// validated to be units, so the inverse exists; it is much cheaper than
+    /// \(c^{n-1}\).
+    fn negate(&self, c: &BigUint) -> BigUint {
+        modinv(c, &self.n_squared).expect("validated ciphertexts are invertible")
+    }
+
+    /// \(c^k \bmod n^2\) for a signed k, inverting \(c^{|k|}\) for
+    /// negative k instead of raising c to \(n - |k|\).
+    fn pow(&self, c: &BigUint, k: &BigInt) -> BigUint {
+        let power = c.modpow(k.magnitude(), &self.n_squared);
+        match k.sign() {
+            Sign::Minus => self.negate(&power),
+            Sign::NoSign => BigUint::one(),
+            Sign::Plus => power,
+        }
+    }
+
+    /// Reads a ciphertext through the LRU cache, or `None` for NULL. TEXT
+    /// values, left from before a database was migrated, are not cached.
+    fn cached(&self, value: ValueRef<'_>) -> std::result::Result<Option<Arc<Ciphertext>>, DecodeError> {
+        let ValueRef::Blob(blob) = value else { return Ok(self.codec.decode(value)?.map(Arc::new)) };
+        if let Some(c) = self.cache.lock().unwrap().get(blob) {
+            return Ok(Some(c.clone()));
+        }
+        let c = self.codec.decode(value)?.map(Arc::new);
+        if let Some(c) = &c {
+            self.cache.lock().unwrap().put(blob.to_vec(), c.clone());
+        }
+        Ok(c)
+    }
+
+    /// Reads argument `index` as a ciphertext, or `None` for NULL.
+    fn arg(&self, ctx: &Context, name: &str, index: usize) -> Result<Option<Arc<Ciphertext>>> {
+        self.cached(ctx.get_raw(index)).map_err(|e| user_error(name, index, e))
+    }
+
+    /// Reads argument `index` of a scalar function as a ciphertext, keeping
+    /// it as auxiliary data, which SQLite retains while the argument is a
+    /// constant of the statement.
+    fn scalar_arg(&self, ctx: &Context, name: &str, index: usize) -> Result<Option<Arc<Ciphertext>>> {
+        if let Some(c) = ctx.get_aux::<Arc<Ciphertext>>(index as i32)? {
+            return Ok(Some(Arc::clone(&c)));
+        }
+        let c = self.arg(ctx, name, index)?;
+        if let Some(c) = &c {
+            ctx.set_aux(index as i32, c.clone())?;
+        }
+        Ok(c)
+    }
+
+    fn binary_args(&self, ctx: &Context, name: &str) -> Result<Option<(Arc<Ciphertext>, Arc<Ciphertext>)>> {
+        let Some(c1) = self.scalar_arg(ctx, name, 0)? else { return Ok(None) };
+        let Some(c2) = self.scalar_arg(ctx, name, 1)? else { return Ok(None) };
+        Ok(Some((c1, c2)))
+    }
+}
+
+/// Parses and validates the result of `FHEAVG`, a BLOB in the format of
+/// [`encode_average_blob`](crate::codec::encode_average_blob), for
+/// [`decrypt_mean`](paillier_rs::stats::decrypt_mean); NULL is `None`. The
+/// BLOB must name the key of `codec`.
+pub fn parse_average(
+    codec: &CiphertextCodec,
+    value: ValueRef<'_>,
+) -> std::result::Result<Option<EncryptedSum>, DecodeError> {
+    let blob = match value {
+        ValueRef::Null => return Ok(None),
+        ValueRef::Blob(blob) => blob,
+        _ => return Err(DecodeError::NotACiphertext),
+    };
+    let (found, count, sum) = decode_average_blob(blob).ok_or(PaillierError::InvalidCiphertext)?;
+    let expected = KeyId::of(codec.pubkey());
+    if found != expected {
+        return Err(PaillierError::WrongKey { expected, found }.into());
+    }
+    let sum = Ciphertext::from_untrusted(sum, codec.pubkey())?;
+    Ok(Some(EncryptedSum { sum: sum.as_biguint().clone(), count }))
+}
+
+/// Folds the non-NULL ciphertexts of a group by homomorphic addition,
+/// counting them along the way.
+struct FheSum {
+    key: Arc<KeyContext>,
+    name: &'static str,
+}
+
+impl FheSum {
+    fn fold(&self, ctx: &mut Context<'_>, acc: &mut EncryptedSum) -> Result<()> {
+        if let Some(c) = self.key.arg(ctx, self.name, 0)? {
+            acc.sum = self.key.add(&acc.sum, c.as_biguint());
+            acc.count += 1;
+        }
+        Ok(())
+    }
+}
+
+/// The empty sum is the trivial encryption of 0, namely 1.
+fn empty_sum() -> EncryptedSum {
+    EncryptedSum { sum: BigUint::from(1u32), count: 0 }
+}
+
+impl Aggregate<EncryptedSum, Option<Vec<u8>>> for FheSum {
+    fn init(&self, _: &mut Context<'_>) -> Result<EncryptedSum> {
+        Ok(empty_sum())
+    }
+
+    fn step(&self, ctx: &mut Context<'_>, acc: &mut EncryptedSum) -> Result<()> {
+        self.fold(ctx, acc)
+    }
+
+    fn finalize(&self, _: &mut Context<'_>, acc: Option<EncryptedSum>) -> Result<Option<Vec<u8>>> {
+        Ok(acc.filter(|acc| acc.count > 0).map(|acc| self.key.encode(&acc.sum)))
+    }
+}
+
+struct FheAvg(FheSum);
+
+impl Aggregate<EncryptedSum, Option<Vec<u8>>> for FheAvg {
+    fn init(&self, _: &mut Context<'_>) -> Result<EncryptedSum> {
+        Ok(empty_sum())
+    }
+
+    fn step(&self, ctx: &mut Context<'_>, acc: &mut EncryptedSum) -> Result<()> {
+        self.0.fold(ctx, acc)
+    }
+
+    fn finalize(&self, _: &mut Context<'_>, acc: Option<EncryptedSum>) -> Result<Option<Vec<u8>>> {
+        Ok(acc.filter(|acc| acc.count > 0).map(|acc| self.0.key.codec.encode_average(&acc)))
+    }
+}
+
+fn user_error(name: &str, index: usize, message: impl std::fmt::Display) -> Error {
+    Error::UserFunctionError(format!("{}: argument {}: {}", name, index + 1, message).into())
+}
+
+/// Reads argument `index` as a signed integer, or `None` for NULL.
+fn integer_arg(ctx: &Context, name: &str, index: usize) -> Result<Option<BigInt>> {
+    ctx.get::<Option<i64>>(index)
+        .map(|k| k.map(BigInt::from))
+        .map_err(|_| user_error(name, index, "expected an integer"))
+}
@@ -0,0 +1,128 @@
+use crate::metadata::{active_key, key_version, public_key, store_public_key, stored_key_versions};
+use crate::reencrypt::{check_tag_key, reencrypt_columns, reencrypt_table};
+use crate::schema::has_table;
+use crate::tag::TagKey;
+use paillier_rs::keygen::{check_key_pair, PrivateKey, PublicKey};
+use paillier_rs::keyid::KeyId;
+use paillier_rs::keyproof::{verify_key, KeyProof};
+use paillier_rs::rotation::{Checkpoint, Reencryptor};
+use rusqlite::Connection;
+use std::error::Error;
+use std::path::Path;
+
+/// The key pair a database is opened with. The public key lives in the
+/// database's `paillier_keys` table; the private key is kept outside it and
+/// supplied by the caller.
+pub struct DatabaseKeys {
+    pub version: i64,
+    pub pubkey: PublicKey,
+    pub privkey: PrivateKey,
+}
+
+fn check_proof(pubkey: &PublicKey, proof: &KeyProof) -> Result<(), Box<dyn Error>> {
+    if verify_key(pubkey, proof) {
+        Ok(())
+    } else {
+        Err(format!("The proof of key {} does not verify", KeyId::of(pubkey)).into())
+    }
+}
+
+/// Checks that `privkey` belongs to `pubkey` and that the key's proof
+/// verifies, before the key is stored in a database.
+pub fn check_new_key(pubkey: &PublicKey, proof: &KeyProof, privkey: &PrivateKey) -> Result<(), Box<dyn Error>> {
+    check_proof(pubkey, proof)?;
+    check_key_pair(pubkey, privkey).map_err(|e| format!("Key {}: {}", KeyId::of(pubkey), e))?;
+    Ok(())
+}
+
+/// Stores `pubkey` as the key of a database that has none yet and returns
+/// its version. Attaching the database's current key again is a no-op; a
+/// database that already has another key must be moved with [`rotate_key`].
+pub fn attach_key(
+    conn: &Connection,
+    pubkey: &PublicKey,
+    proof: &KeyProof,
+    privkey: &PrivateKey,
+) -> Result<i64, Box<dyn Error>> {
+    check_new_key(pubkey, proof, privkey)?;
+    if let Some((version, active)) = active_key(conn)? {
+        if &active != pubkey {
+            return Err(format!(
+                "Database already has key {} (version {}); use rotate to replace it",
+                KeyId::of(&active),
+                version
+            )
+            .into());
+        }
+    }
+    store_public_key(conn, pubkey, proof)
+}
+
+/// Opens a database with `privkey`, which must belong to its active key.
+pub fn open_keys(conn: &Connection, privkey: PrivateKey) -> Result<DatabaseKeys, Box<dyn Error>> {
+    let (version, pubkey) = active_key(conn)?.ok_or("Database has no key; run init or attach first")?;
+    if check_key_pair(&pubkey, &privkey).is_err() {
+        return Err(format!(
+            "Private key does not match key {} (version {}) of this database",
+            KeyId::of(&pubkey),
+            version
+        )
+        .into());
+    }
+    Ok(DatabaseKeys { version, pubkey, privkey })
+}
+
+/// Finds the stored key `privkey` belongs to, active or not.
+fn find_key(conn: &Connection, privkey: &PrivateKey) -> Result<Option<(i64, PublicKey)>, Box<dyn Error>> {
+    for version in stored_key_versions(conn)? {
+        if let Some(pubkey) = public_key(conn, version)? {
+            if check_key_pair(&pubkey, privkey).is_ok() {
+                return Ok(Some((version, pubkey)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Makes `new_pubkey` the active key and re-encrypts under it the rows of
+/// `encrypted_table` under the key `old_privkey` belongs to, then the values
+/// of the declared encrypted columns. Returns the new key's version.
+///
+/// The new private key is only needed to recompute the [tags](crate::tag)
+/// of tagged columns, which depend on it; without it, a database with
+/// tagged columns is refused before anything is changed. When given, the
+/// new pair is checked with [`check_new_key`]. The new key is
+/// stored before any row is touched, so an interrupted rotation is resumed
+/// by running it again with the same arguments; rows and values already
+/// moved are skipped (see [`reencrypt_table`] and [`reencrypt_columns`]).
+/// The checkpoint tracks `encrypted_table` only.
+#[allow(clippy::too_many_arguments)]
+pub fn rotate_key(
+    conn: &mut Connection,
+    old_privkey: &PrivateKey,
+    new_pubkey: &PublicKey,
+    new_proof: &KeyProof,
+    new_privkey: Option<&PrivateKey>,
+    batch_size: usize,
+    checkpoint: &mut Checkpoint,
+    checkpoint_path: Option<&Path>,
+) -> Result<i64, Box<dyn Error>> {
+    match new_privkey {
+        Some(new_privkey) => check_new_key(new_pubkey, new_proof, new_privkey)?,
+        None => check_proof(new_pubkey, new_proof)?,
+    }
+    let tag_key = new_privkey.map(TagKey::derive);
+    check_tag_key(conn, tag_key.as_ref())?;
+    let (old_version, old_pubkey) =
+        find_key(conn, old_privkey)?.ok_or("Old private key does not match any key of this database")?;
+    if matches!(key_version(conn, new_pubkey)?, Some(version) if version <= old_version) {
+        return Err(format!("Key {} is not newer than the key being rotated", KeyId::of(new_pubkey)).into());
+    }
+    let reencryptor = Reencryptor::new(&old_pubkey, old_privkey, new_pubkey)?;
+    let version = store_public_key(conn, new_pubkey, new_proof)?;
+    if has_table(conn, "encrypted_table")? {
+        reencrypt_table(conn, &reencryptor, old_version, version, batch_size, checkpoint, checkpoint_path)?;
+    }
+    reencrypt_columns(conn, &reencryptor, tag_key.as_ref(), batch_size)?;
+    Ok(version)
+}
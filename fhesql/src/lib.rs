@@ -0,0 +1,15 @@
+pub mod cli;
+pub mod client;
+pub mod codec;
+pub mod compare;
+pub mod functions;
+pub mod keys;
+pub mod metadata;
+pub mod migrate;
+pub mod protocol;
+pub mod reencrypt;
+pub mod rewrite;
+pub mod schema;
+pub mod server;
+pub mod tag;
+pub mod transfer;
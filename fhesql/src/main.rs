@@ -1,66 +1,329 @@
-use rusqlite::{functions::FunctionFlags, params, Connection, Result};
-use paillier_rs::keygen::paillier_keygen;
+use rusqlite::types::Value;
+use rusqlite::{params, Connection};
+use paillier_rs::keygen::{try_paillier_keygen_with_proof, PrivateKey, PublicKey};
+use paillier_rs::keyproof::{import_public_key, KeyProof};
+use paillier_rs::encoding::{decode_private_key, decode_public_key, encode_private_key, encode_public_key};
+use paillier_rs::rotation::{Checkpoint, Reencryptor};
+use fhesql::cli::{flag, last_positional, option, required};
+use fhesql::codec::CiphertextCodec;
+use fhesql::functions::register_functions;
+use fhesql::keys::{attach_key, open_keys, rotate_key, DatabaseKeys};
+use fhesql::metadata::active_key;
+use fhesql::migrate::migrate_to_blobs;
+use fhesql::reencrypt::{ensure_key_version_column, reencrypt_table, DEFAULT_BATCH_SIZE};
+use fhesql::transfer::{export, import, Format};
 use paillier_rs::encrypt::paillier_encrypt;
 use paillier_rs::decrypt::paillier_decrypt;
-use paillier_rs::arithmetic::paillier_add;
+use paillier_rs::keyid::KeyId;
 use num_bigint::BigUint;
-use num_traits::{ToPrimitive, One};
+use num_traits::ToPrimitive;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
 
-fn main() -> Result<()> {
+const USAGE: &str = "usage:
+  fhesql                      run the encrypted-table demo on example.db, keeping
+                              its key in example.pub and example.key
+  fhesql keygen --bits <bits> --out <prefix>
+  fhesql init --db <path> --bits <bits> --out <prefix>
+  fhesql attach --db <path> --public <file> --private <file>
+  fhesql rotate --db <path> --private <file> --new-public <file> --new-private <file>
+                [--batch-size <n>] [--checkpoint <file>]
+  fhesql reencrypt --db <path> --old-public <file> --old-private <file> --old-version <n>
+                   --new-public <file> --version <n>
+                   [--batch-size <n>] [--checkpoint <file>]
+  fhesql migrate --db <path> [--public <file>] [--batch-size <n>]
+  fhesql import --db <path> [--table <name>] [--encrypt-cols <col,...>] [--scale <n>]
+                [--format csv|json] [--batch-size <n>] <file>
+  fhesql export --db <path> --table <name> [--decrypt --private <file>] [--scale <n>]
+                [--format csv|json] [--batch-size <n>] [--out <file>]";
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => demo()?,
+        Some("keygen") => keygen_command(&args[1..])?,
+        Some("init") => init_command(&args[1..])?,
+        Some("attach") => attach_command(&args[1..])?,
+        Some("rotate") => rotate_command(&args[1..])?,
+        Some("reencrypt") => reencrypt_command(&args[1..])?,
+        Some("migrate") => migrate_command(&args[1..])?,
+        Some("import") => import_command(&args[1..])?,
+        Some("export") => export_command(&args[1..])?,
+        Some(_) => return Err(USAGE.into()),
+    }
+    Ok(())
+}
+
+fn read_public_key(path: &str) -> Result<(PublicKey, KeyProof), Box<dyn Error>> {
+    Ok(decode_public_key(&fs::read_to_string(path)?).ok_or_else(|| format!("Failed to parse public key {}", path))?)
+}
+
+fn read_private_key(path: &str) -> Result<PrivateKey, Box<dyn Error>> {
+    Ok(decode_private_key(&fs::read_to_string(path)?).ok_or_else(|| format!("Failed to parse private key {}", path))?)
+}
+
+/// Writes `contents` to a file that must not exist yet, so key files are
+/// never overwritten.
+fn write_new(path: &str, contents: &str) -> Result<(), Box<dyn Error>> {
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| format!("Cannot create {}: {}", path, e))?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+/// Generates a key pair for a database without one, writes it to
+/// `<prefix>.pub` and `<prefix>.key` and stores the public key in the
+/// database. If storing fails, the written files can be attached later.
+fn init_keys(conn: &Connection, bits: usize, prefix: &str) -> Result<DatabaseKeys, Box<dyn Error>> {
+    if let Some((version, pubkey)) = active_key(conn)? {
+        return Err(format!("Database already has key {} (version {})", KeyId::of(&pubkey), version).into());
+    }
+    let (pubkey, privkey, proof) = try_paillier_keygen_with_proof(bits)?;
+    write_new(&format!("{}.pub", prefix), &encode_public_key(&pubkey, &proof))?;
+    write_new(&format!("{}.key", prefix), &encode_private_key(&privkey))?;
+    attach_key(conn, &pubkey, &proof, &privkey)?;
+    open_keys(conn, privkey)
+}
+
+/// Writes `<prefix>.pub` (public key with its key proof) and
+/// `<prefix>.key` (private key).
+fn keygen_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let bits: usize = required(args, "bits", USAGE)?.parse()?;
+    let prefix = required(args, "out", USAGE)?;
+    let (pubkey, privkey, proof) = try_paillier_keygen_with_proof(bits)?;
+    write_new(&format!("{}.pub", prefix), &encode_public_key(&pubkey, &proof))?;
+    write_new(&format!("{}.key", prefix), &encode_private_key(&privkey))?;
+    Ok(())
+}
+
+fn init_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let conn = Connection::open(required(args, "db", USAGE)?)?;
+    let bits: usize = required(args, "bits", USAGE)?.parse()?;
+    let keys = init_keys(&conn, bits, required(args, "out", USAGE)?)?;
+    println!("Initialized key {} (version {})", KeyId::of(&keys.pubkey), keys.version);
+    Ok(())
+}
+
+/// Stores an existing key pair, as written by `keygen`, in a database
+/// without a key.
+fn attach_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let conn = Connection::open(required(args, "db", USAGE)?)?;
+    let (pubkey, proof) = read_public_key(required(args, "public", USAGE)?)?;
+    let privkey = read_private_key(required(args, "private", USAGE)?)?;
+    let version = attach_key(&conn, &pubkey, &proof, &privkey)?;
+    println!("Attached key {} (version {})", KeyId::of(&pubkey), version);
+    Ok(())
+}
+
+/// Makes a new key pair the database's key and re-encrypts its rows,
+/// resuming from the checkpoint file if one is given and exists.
+fn rotate_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut conn = Connection::open(required(args, "db", USAGE)?)?;
+    let old_privkey = read_private_key(required(args, "private", USAGE)?)?;
+    let (new_pubkey, new_proof) = read_public_key(required(args, "new-public", USAGE)?)?;
+    let new_privkey = read_private_key(required(args, "new-private", USAGE)?)?;
+    let batch_size = batch_size(args)?;
+    let checkpoint_path = option(args, "checkpoint").map(Path::new);
+    let mut checkpoint = match checkpoint_path {
+        Some(path) => Checkpoint::load(path)?.unwrap_or_default(),
+        None => Checkpoint::default(),
+    };
+    if checkpoint.rows_done > 0 {
+        println!("Resuming after row {} ({} rows done)", checkpoint.last_id, checkpoint.rows_done);
+    }
+
+    rotate_key(
+        &mut conn,
+        &old_privkey,
+        &new_pubkey,
+        &new_proof,
+        Some(&new_privkey),
+        batch_size,
+        &mut checkpoint,
+        checkpoint_path,
+    )?;
+    let keys = open_keys(&conn, new_privkey)?;
+    println!(
+        "Rotated to key {} (version {}); re-encrypted {} rows",
+        KeyId::of(&keys.pubkey),
+        keys.version,
+        checkpoint.rows_done
+    );
+    Ok(())
+}
+
+/// Moves the rows of `encrypted_table` under `--old-version` to a new key,
+/// resuming from the checkpoint file if one is given and exists.
+fn reencrypt_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut conn = Connection::open(required(args, "db", USAGE)?)?;
+    let (old_pubkey, _) = decode_public_key(&fs::read_to_string(required(args, "old-public", USAGE)?)?)
+        .ok_or("Failed to parse old public key")?;
+    let old_privkey = decode_private_key(&fs::read_to_string(required(args, "old-private", USAGE)?)?)
+        .ok_or("Failed to parse old private key")?;
+    let new_pubkey = import_public_key(&fs::read_to_string(required(args, "new-public", USAGE)?)?)
+        .ok_or("New public key is malformed or its proof does not verify")?;
+    let old_version: i64 = required(args, "old-version", USAGE)?.parse()?;
+    let version: i64 = required(args, "version", USAGE)?.parse()?;
+    let batch_size = batch_size(args)?;
+    let checkpoint_path = option(args, "checkpoint").map(Path::new);
+    let mut checkpoint = match checkpoint_path {
+        Some(path) => Checkpoint::load(path)?.unwrap_or_default(),
+        None => Checkpoint::default(),
+    };
+    if checkpoint.rows_done > 0 {
+        println!("Resuming after row {} ({} rows done)", checkpoint.last_id, checkpoint.rows_done);
+    }
+
+    let reencryptor = Reencryptor::new(&old_pubkey, &old_privkey, &new_pubkey)?;
+    reencrypt_table(&mut conn, &reencryptor, old_version, version, batch_size, &mut checkpoint, checkpoint_path)?;
+    println!("Re-encrypted {} rows to key version {}", checkpoint.rows_done, version);
+    Ok(())
+}
+
+/// Converts the TEXT ciphertexts of a database to BLOBs. Rows of key
+/// versions with no recorded key, as in databases from before keys were
+/// stored, are attributed to the key given with `--public`.
+fn migrate_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut conn = Connection::open(required(args, "db", USAGE)?)?;
+    let fallback = match option(args, "public") {
+        Some(path) => Some(read_public_key(path)?.0),
+        None => None,
+    };
+    let migrated = migrate_to_blobs(&mut conn, fallback.as_ref(), batch_size(args)?)?;
+    println!("Converted {} ciphertexts to BLOBs", migrated);
+    Ok(())
+}
+
+/// The batch size given with `--batch-size`, which must be positive, or the
+/// default.
+fn batch_size(args: &[String]) -> Result<usize, Box<dyn Error>> {
+    match option(args, "batch-size") {
+        Some(s) => match s.parse()? {
+            0 => Err("--batch-size must be at least 1".into()),
+            n => Ok(n),
+        },
+        None => Ok(DEFAULT_BATCH_SIZE),
+    }
+}
+
+fn scale(args: &[String]) -> Result<Option<u64>, Box<dyn Error>> {
+    Ok(option(args, "scale").map(str::parse).transpose()?)
+}
+
+/// The format given with `--format`, or else that of `path`'s extension.
+fn format(args: &[String], path: Option<&str>) -> Result<Format, Box<dyn Error>> {
+    match option(args, "format") {
+        Some(name) => Ok(Format::from_name(name).ok_or_else(|| format!("Unknown format {}\n{}", name, USAGE))?),
+        None => Ok(path.map_or(Format::Csv, |path| Format::from_path(Path::new(path)))),
+    }
+}
+
+/// Loads a CSV or JSON Lines file into a table, by default named after the
+/// file, encrypting the columns given with `--encrypt-cols`.
+fn import_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut conn = Connection::open(required(args, "db", USAGE)?)?;
+    let path = last_positional(args).ok_or_else(|| format!("missing input file\n{}", USAGE))?;
+    let table = match option(args, "table") {
+        Some(table) => table,
+        None => Path::new(path).file_stem().and_then(|s| s.to_str()).ok_or("Cannot name a table after the file")?,
+    };
+    let encrypt: Vec<&str> = option(args, "encrypt-cols").map_or_else(Vec::new, |cols| cols.split(',').collect());
+    let input = BufReader::new(fs::File::open(path).map_err(|e| format!("Cannot open {}: {}", path, e))?);
+    let format = format(args, Some(path))?;
+    let imported = import(&mut conn, table, input, format, &encrypt, scale(args)?, batch_size(args)?)?;
+    println!("Imported {} rows into {}", imported, table);
+    Ok(())
+}
+
+/// Writes a table to a file or stdout, decrypting its encrypted columns
+/// with `--decrypt`.
+fn export_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let conn = Connection::open(required(args, "db", USAGE)?)?;
+    let table = required(args, "table", USAGE)?;
+    let keys = if flag(args, "decrypt") {
+        Some(open_keys(&conn, read_private_key(required(args, "private", USAGE)?)?)?)
+    } else {
+        None
+    };
+    let out = option(args, "out");
+    let format = format(args, out)?;
+    let (scale, batch_size) = (scale(args)?, batch_size(args)?);
+    match out {
+        Some(path) => {
+            let output = BufWriter::new(fs::File::create(path).map_err(|e| format!("Cannot create {}: {}", path, e))?);
+            let exported = export(&conn, table, keys.as_ref(), scale, output, format, batch_size)?;
+            println!("Exported {} rows to {}", exported, path);
+        }
+        None => {
+            export(&conn, table, keys.as_ref(), scale, io::stdout().lock(), format, batch_size)?;
+        }
+    }
+    Ok(())
+}
+
+/// Encrypts a few values into `encrypted_table`, doubles them with `FHEADD`
+/// and prints the decrypted results. The key is generated on the first run
+/// and reused afterwards, so rows from earlier runs stay decryptable.
+fn demo() -> Result<(), Box<dyn Error>> {
     // Open (or create) the local SQLite database.
     let conn = Connection::open("example.db")?;
 
-    // Generate Paillier keys (using 64-bit primes for demonstration).
-    let bits = 256;
-    let (pubkey, privkey) = paillier_keygen(bits);
-
     // Create a table to store encrypted values.
     conn.execute(
         "CREATE TABLE IF NOT EXISTS encrypted_table (
             id         INTEGER PRIMARY KEY,
-            ciphertext TEXT NOT NULL
+            ciphertext BLOB NOT NULL,
+            key_version INTEGER NOT NULL DEFAULT 1
         )",
         [],
     )?;
+    ensure_key_version_column(&conn)?;
+
+    // Load the database's key, or generate one on the first run (using
+    // 256-bit primes for demonstration).
+    let keys = match active_key(&conn)? {
+        Some(_) => open_keys(&conn, read_private_key("example.key")?)?,
+        None => init_keys(&conn, 256, "example")?,
+    };
+    let DatabaseKeys { version, pubkey, privkey } = &keys;
+    println!("Key ID {} (version {})", KeyId::of(pubkey), version);
+    let codec = CiphertextCodec::new(pubkey);
 
     // Insert sample plaintext values (encrypt them first).
     let plaintexts = vec![10u32, 20u32, 30u32];
     for &m in &plaintexts {
         let m_big = BigUint::from(m);
-        let c = paillier_encrypt(&pubkey, &m_big);
-        let c_str = c.to_str_radix(10);
-        conn.execute("INSERT INTO encrypted_table (ciphertext) VALUES (?1)", params![c_str])?;
+        let c = paillier_encrypt(pubkey, &m_big);
+        conn.execute(
+            "INSERT INTO encrypted_table (ciphertext, key_version) VALUES (?1, ?2)",
+            params![codec.encode(&c), version],
+        )?;
     }
 
-    // Register the custom scalar function FHEADD.
-    // FHEADD takes two ciphertext strings (base‑10), parses them into BigUint,
-    // adds them homomorphically using paillier_add, and returns the resulting ciphertext as a string.
-    let pubkey_clone = pubkey.clone(); // clone public key for use in the closure.
-    conn.create_scalar_function(
-        "FHEADD",
-        2,
-        FunctionFlags::SQLITE_DETERMINISTIC,
-        move |ctx| {
-            let s1: String = ctx.get(0)?;
-            let s2: String = ctx.get(1)?;
-            let c1 = BigUint::parse_bytes(s1.as_bytes(), 10)
-                .ok_or_else(|| rusqlite::Error::UserFunctionError("Failed to parse ciphertext 1".into()))?;
-            let c2 = BigUint::parse_bytes(s2.as_bytes(), 10)
-                .ok_or_else(|| rusqlite::Error::UserFunctionError("Failed to parse ciphertext 2".into()))?;
-            let c_sum = paillier_add(&c1, &c2, &pubkey_clone);
-            Ok(c_sum.to_str_radix(10))
-        },
-    )?;
+    // Register FHEADD and the other homomorphic functions for this key.
+    register_functions(&conn, pubkey)?;
 
     // Query the table to get id, original ciphertext, and doubled ciphertext (via FHEADD).
     let mut stmt = conn.prepare(
         "SELECT id, ciphertext, FHEADD(ciphertext, ciphertext) as doubled 
-         FROM encrypted_table"
+         FROM encrypted_table WHERE key_version = ?1"
     )?;
-    let rows = stmt.query_map([], |row| {
+    let rows = stmt.query_map(params![version], |row| {
         let id: i64 = row.get(0)?;
-        let orig: String = row.get(1)?;
-        let doubled: String = row.get(2)?;
+        let orig: Value = row.get(1)?;
+        let doubled: Value = row.get(2)?;
         Ok((id, orig, doubled))
     })?;
 
@@ -68,15 +331,14 @@ fn main() -> Result<()> {
     let mut results = Vec::new();
     for row in rows {
         let (id, orig, doubled) = row?;
-        // Parse and decrypt original ciphertext.
-        let orig_big = BigUint::parse_bytes(orig.as_bytes(), 10)
-            .ok_or(rusqlite::Error::UserFunctionError("Failed to parse original ciphertext".into()))?;
-        let dec_orig = paillier_decrypt(&privkey, &pubkey, &orig_big);
+        // Parse and decrypt original ciphertext (a BLOB, or TEXT in rows
+        // not migrated yet).
+        let orig_c = codec.decode((&orig).into())?.ok_or("Original ciphertext is NULL")?;
+        let dec_orig = paillier_decrypt(privkey, pubkey, orig_c.as_biguint());
         // Parse and decrypt doubled ciphertext.
-        let doubled_big = BigUint::parse_bytes(doubled.as_bytes(), 10)
-            .ok_or(rusqlite::Error::UserFunctionError("Failed to parse doubled ciphertext".into()))?;
-        let dec_doubled = paillier_decrypt(&privkey, &pubkey, &doubled_big);
-        results.push((id, orig, dec_orig, doubled, dec_doubled));
+        let doubled_c = codec.decode((&doubled).into())?.ok_or("Doubled ciphertext is NULL")?;
+        let dec_doubled = paillier_decrypt(privkey, pubkey, doubled_c.as_biguint());
+        results.push((id, hex(&orig), dec_orig, hex(&doubled), dec_doubled));
     }
 
     // Define fixed column widths.
@@ -147,3 +409,12 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Shows a stored ciphertext: BLOBs as hex, TEXT as is.
+fn hex(value: &Value) -> String {
+    match value {
+        Value::Blob(blob) => blob.iter().map(|b| format!("{:02x}", b)).collect(),
+        Value::Text(s) => s.clone(),
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,142 @@
+use paillier_rs::encoding::{decode_public_key, encode_public_key};
+use paillier_rs::keygen::PublicKey;
+use paillier_rs::keyid::KeyId;
+use paillier_rs::keyproof::KeyProof;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::error::Error;
+
+/// Creates the `paillier_keys` table if it does not exist. Each row maps a
+/// key version, as stored in `encrypted_table.key_version`, to the
+/// [`KeyId`] of the public key its ciphertexts were encrypted under, and
+/// optionally to the encoded public key itself. Tables created before public
+/// keys were stored get the `public_key` column added.
+pub fn ensure_key_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS paillier_keys (
+            version      INTEGER PRIMARY KEY,
+            key_id       TEXT NOT NULL UNIQUE,
+            modulus_bits INTEGER NOT NULL,
+            public_key   TEXT
+        )",
+        [],
+    )?;
+    if conn.prepare("SELECT public_key FROM paillier_keys LIMIT 0").is_err() {
+        conn.execute("ALTER TABLE paillier_keys ADD COLUMN public_key TEXT", [])?;
+    }
+    Ok(())
+}
+
+/// Returns the key ID recorded for `version`, if any.
+pub fn key_id(conn: &Connection, version: i64) -> rusqlite::Result<Option<KeyId>> {
+    ensure_key_table(conn)?;
+    let id: Option<String> = conn
+        .query_row("SELECT key_id FROM paillier_keys WHERE version = ?1", params![version], |row| row.get(0))
+        .optional()?;
+    Ok(id.and_then(|id| KeyId::from_hex(&id)))
+}
+
+/// Returns the version under which `pubkey` is recorded, if any.
+pub fn key_version(conn: &Connection, pubkey: &PublicKey) -> rusqlite::Result<Option<i64>> {
+    ensure_key_table(conn)?;
+    conn.query_row(
+        "SELECT version FROM paillier_keys WHERE key_id = ?1",
+        params![KeyId::of(pubkey).to_string()],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Records `pubkey` as key `version`. Recording the same pair again is a
+/// no-op; a version already bound to another key, or a key already recorded
+/// under another version, is an error.
+pub fn record_key(conn: &Connection, version: i64, pubkey: &PublicKey) -> Result<(), Box<dyn Error>> {
+    let id = KeyId::of(pubkey);
+    match (key_id(conn, version)?, key_version(conn, pubkey)?) {
+        (Some(existing), _) if existing != id => {
+            Err(format!("Key version {} belongs to key {}, not {}", version, existing, id).into())
+        }
+        (_, Some(existing)) if existing != version => {
+            Err(format!("Key {} is already recorded as version {}", id, existing).into())
+        }
+        (Some(_), Some(_)) => Ok(()),
+        _ => {
+            conn.execute(
+                "INSERT INTO paillier_keys (version, key_id, modulus_bits) VALUES (?1, ?2, ?3)",
+                params![version, id.to_string(), pubkey.0.bits() as i64],
+            )?;
+            Ok(())
+        }
+    }
+}
+
+/// Returns the version of `pubkey`, recording it under the next unused
+/// version if it is not known yet. Versions already present in
+/// `encrypted_table` but missing from `paillier_keys` (rows written before
+/// key IDs were recorded) are never reused.
+pub fn register_key(conn: &Connection, pubkey: &PublicKey) -> Result<i64, Box<dyn Error>> {
+    if let Some(version) = key_version(conn, pubkey)? {
+        return Ok(version);
+    }
+    let mut version: i64 = conn.query_row("SELECT COALESCE(MAX(version), 0) FROM paillier_keys", [], |row| row.get(0))?;
+    if let Ok(max_row_version) =
+        conn.query_row("SELECT COALESCE(MAX(key_version), 0) FROM encrypted_table", [], |row| row.get::<_, i64>(0))
+    {
+        version = version.max(max_row_version);
+    }
+    record_key(conn, version + 1, pubkey)?;
+    Ok(version + 1)
+}
+
+/// Registers `pubkey` like [`register_key`] and stores it, with its proof,
+/// in the format of [`encode_public_key`]. Returns its version.
+pub fn store_public_key(conn: &Connection, pubkey: &PublicKey, proof: &KeyProof) -> Result<i64, Box<dyn Error>> {
+    let version = register_key(conn, pubkey)?;
+    conn.execute(
+        "UPDATE paillier_keys SET public_key = ?1 WHERE version = ?2",
+        params![encode_public_key(pubkey, proof), version],
+    )?;
+    Ok(version)
+}
+
+/// Returns the stored public key of `version`, if any. A stored key that does
+/// not match the recorded key ID is an error.
+pub fn public_key(conn: &Connection, version: i64) -> Result<Option<PublicKey>, Box<dyn Error>> {
+    Ok(public_key_with_proof(conn, version)?.map(|(pubkey, _)| pubkey))
+}
+
+/// Like [`public_key`], also returning the proof stored with the key. The
+/// proof is not verified.
+pub fn public_key_with_proof(conn: &Connection, version: i64) -> Result<Option<(PublicKey, KeyProof)>, Box<dyn Error>> {
+    ensure_key_table(conn)?;
+    let row: Option<(String, Option<String>)> = conn
+        .query_row(
+            "SELECT key_id, public_key FROM paillier_keys WHERE version = ?1",
+            params![version],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let Some((id, Some(encoded))) = row else { return Ok(None) };
+    let (pubkey, proof) =
+        decode_public_key(&encoded).ok_or_else(|| format!("Stored public key of version {} is malformed", version))?;
+    if KeyId::of(&pubkey).to_string() != id {
+        return Err(format!("Stored public key of version {} does not match key ID {}", version, id).into());
+    }
+    Ok(Some((pubkey, proof)))
+}
+
+/// Returns the versions that have a stored public key, oldest first.
+pub fn stored_key_versions(conn: &Connection) -> rusqlite::Result<Vec<i64>> {
+    ensure_key_table(conn)?;
+    let mut stmt = conn.prepare("SELECT version FROM paillier_keys WHERE public_key IS NOT NULL ORDER BY version")?;
+    let versions = stmt.query_map([], |row| row.get(0))?.collect();
+    versions
+}
+
+/// Returns the active key: the newest one with a stored public key. New rows
+/// are encrypted under it.
+pub fn active_key(conn: &Connection) -> Result<Option<(i64, PublicKey)>, Box<dyn Error>> {
+    match stored_key_versions(conn)?.last() {
+        Some(&version) => Ok(public_key(conn, version)?.map(|pubkey| (version, pubkey))),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,130 @@
+use crate::metadata::{active_key, key_id, record_key};
+use crate::reencrypt::ensure_key_version_column;
+use crate::schema::{ensure_schema_table, has_table, quote, SCHEMA_QUERY};
+use paillier_rs::encoding::{decode_ciphertext, encode_ciphertext_blob};
+use paillier_rs::keygen::PublicKey;
+use paillier_rs::keyid::KeyId;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Converts the base-10 TEXT ciphertexts of a database to BLOBs (see
+/// [`CiphertextCodec`](crate::codec::CiphertextCodec)), `batch_size` values
+/// per transaction, which must be positive, and returns how many were converted. Those of
+/// `encrypted_table` get the key recorded for their `key_version`, those of
+/// the columns declared in `encrypted_columns` the active key.
+///
+/// Databases from before key IDs were recorded have no key for their rows;
+/// such rows are attributed to `fallback`, which is recorded under their
+/// version, and are an error without it. Only TEXT values are touched, so
+/// an interrupted migration is resumed by running it again, and since every
+/// reader accepts both formats the database stays usable meanwhile.
+pub fn migrate_to_blobs(
+    conn: &mut Connection,
+    fallback: Option<&PublicKey>,
+    batch_size: usize,
+) -> Result<u64, Box<dyn Error>> {
+    if batch_size == 0 {
+        return Err("Batch size must be positive".into());
+    }
+    let mut keys = KeyIds { fallback, versions: HashMap::new() };
+    let mut migrated = 0;
+    if has_table(conn, "encrypted_table")? {
+        ensure_key_version_column(conn)?;
+        migrated += migrate_column(conn, &mut keys, "encrypted_table", "ciphertext", "key_version", batch_size)?;
+    }
+    if !has_table(conn, "encrypted_columns")? {
+        return Ok(migrated);
+    }
+
+    ensure_schema_table(conn)?;
+    let declared = {
+        let mut stmt = conn.prepare(SCHEMA_QUERY)?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()?
+    };
+    for (table, column) in declared {
+        migrated += migrate_column(conn, &mut keys, &table, &column, "NULL", batch_size)?;
+    }
+    Ok(migrated)
+}
+
+/// The key IDs migrated values are recorded under, by key version; `None`
+/// stands for the active key.
+struct KeyIds<'a> {
+    fallback: Option<&'a PublicKey>,
+    versions: HashMap<Option<i64>, KeyId>,
+}
+
+impl KeyIds<'_> {
+    fn of(&mut self, conn: &Connection, version: Option<i64>) -> Result<KeyId, Box<dyn Error>> {
+        if let Some(&id) = self.versions.get(&version) {
+            return Ok(id);
+        }
+        let id = match version {
+            Some(version) => match key_id(conn, version)? {
+                Some(id) => id,
+                None => {
+                    let pubkey = self.fallback.ok_or_else(|| {
+                        format!("Key version {} has no recorded key; pass its public key with --public", version)
+                    })?;
+                    record_key(conn, version, pubkey)?;
+                    KeyId::of(pubkey)
+                }
+            },
+            None => match (active_key(conn)?, self.fallback) {
+                (Some((_, pubkey)), _) => KeyId::of(&pubkey),
+                (None, Some(pubkey)) => KeyId::of(pubkey),
+                (None, None) => return Err("Database has no key; pass its public key with --public".into()),
+            },
+        };
+        self.versions.insert(version, id);
+        Ok(id)
+    }
+}
+
+/// Converts the TEXT values of `table.column`, each under the key version
+/// given by the SQL expression `version`.
+fn migrate_column(
+    conn: &mut Connection,
+    keys: &mut KeyIds,
+    table: &str,
+    column: &str,
+    version: &str,
+    batch_size: usize,
+) -> Result<u64, Box<dyn Error>> {
+    let select = format!(
+        "SELECT rowid, {c}, {} FROM {} WHERE typeof({c}) = 'text' LIMIT ?1",
+        version,
+        quote(table),
+        c = quote(column)
+    );
+    let update = format!("UPDATE {} SET {} = ?1 WHERE rowid = ?2", quote(table), quote(column));
+    let mut migrated = 0;
+    loop {
+        let batch = {
+            let mut stmt = conn.prepare(&select)?;
+            let rows = stmt.query_map(params![batch_size as i64], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<i64>>(2)?))
+            })?;
+            let mut batch = Vec::new();
+            for row in rows {
+                let (rowid, text, version) = row?;
+                let c = decode_ciphertext(&text)
+                    .ok_or_else(|| format!("{}.{}: failed to parse ciphertext of row {}", table, column, rowid))?;
+                batch.push((rowid, encode_ciphertext_blob(&c, &keys.of(conn, version)?)));
+            }
+            batch
+        };
+        if batch.is_empty() {
+            return Ok(migrated);
+        }
+
+        let tx = conn.transaction()?;
+        for (rowid, blob) in &batch {
+            tx.execute(&update, params![blob, rowid])?;
+        }
+        tx.commit()?;
+        migrated += batch.len() as u64;
+    }
+}
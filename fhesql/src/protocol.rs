@@ -0,0 +1,321 @@
+use rusqlite::types::Value;
+use std::io::{self, Read, Write};
+
+/// Largest frame either side accepts, so a bad length prefix cannot make the
+/// peer allocate without bound.
+pub const MAX_FRAME_LEN: usize = 64 << 20;
+
+/// An SQL statement with its parameters.
+pub type Statement = (String, Vec<Value>);
+
+/// Client → server messages.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Request {
+    /// Asks for the database's active public key.
+    PublicKey,
+    /// Runs a statement that returns no rows.
+    Execute { sql: String, params: Vec<Value> },
+    /// Runs a query and returns all of its rows.
+    Query { sql: String, params: Vec<Value> },
+    /// Resolves range predicates on encrypted values with the client, then
+    /// runs `statement`, an `Execute` or `Query` that reads the results; see
+    /// [`compare`](crate::compare). Each entry of `comparisons` is a query
+    /// returning `(rowid, Enc(2d + 1))` rows, taking the statement's
+    /// parameters. The server sends [`Response::Masked`] batches of at most
+    /// `batch_size` values, each answered with [`Request::Signs`], before
+    /// the statement's response.
+    Compare { comparisons: Vec<String>, batch_size: u64, statement: Box<Request> },
+    /// The signs of the values of a [`Response::Masked`] batch, in order.
+    Signs { positive: Vec<bool> },
+    /// Runs `(sql, params)` statements in one transaction, which is rolled
+    /// back if any of them fails; answered with the total number of
+    /// changed rows.
+    Transaction { statements: Vec<Statement> },
+}
+
+/// Server → client messages.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Response {
+    /// The active key in the format of
+    /// [`encode_public_key`](paillier_rs::encoding::encode_public_key).
+    PublicKey { version: i64, key: String },
+    Executed { changes: u64 },
+    Rows { columns: Vec<String>, rows: Vec<Vec<Value>> },
+    /// A batch of masked comparison ciphertexts for the client to decrypt
+    /// and answer with [`Request::Signs`].
+    Masked { values: Vec<String> },
+    /// The request failed; the message is meant for the user.
+    Error(String),
+}
+
+/// Writes `payload` preceded by its length as a big-endian `u32`.
+pub fn write_frame<W: Write + ?Sized>(w: &mut W, payload: &[u8]) -> io::Result<()> {
+    if payload.len() > MAX_FRAME_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame too large"));
+    }
+    w.write_all(&(payload.len() as u32).to_be_bytes())?;
+    w.write_all(payload)?;
+    w.flush()
+}
+
+/// Reads a frame written by [`write_frame`]. Returns `None` if the stream
+/// ends before a new frame starts.
+pub fn read_frame<R: Read + ?Sized>(r: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match r.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too large"));
+    }
+    let mut payload = vec![0u8; len];
+    r.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
+
+fn malformed() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed message")
+}
+
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u8(&mut self, v: u8) {
+        self.0.push(v);
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.0.extend_from_slice(&v.to_be_bytes());
+    }
+
+    fn bytes(&mut self, v: &[u8]) {
+        self.u64(v.len() as u64);
+        self.0.extend_from_slice(v);
+    }
+
+    fn value(&mut self, v: &Value) {
+        match v {
+            Value::Null => self.u8(0),
+            Value::Integer(i) => {
+                self.u8(1);
+                self.u64(*i as u64);
+            }
+            Value::Real(f) => {
+                self.u8(2);
+                self.u64(f.to_bits());
+            }
+            Value::Text(s) => {
+                self.u8(3);
+                self.bytes(s.as_bytes());
+            }
+            Value::Blob(b) => {
+                self.u8(4);
+                self.bytes(b);
+            }
+        }
+    }
+
+    fn values(&mut self, vs: &[Value]) {
+        self.u64(vs.len() as u64);
+        vs.iter().for_each(|v| self.value(v));
+    }
+
+    fn strings(&mut self, ss: &[String]) {
+        self.u64(ss.len() as u64);
+        ss.iter().for_each(|s| self.bytes(s.as_bytes()));
+    }
+}
+
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if n > self.0.len() {
+            return Err(malformed());
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// Reads a count of items that each take at least one byte, rejecting
+    /// counts the remaining input cannot hold.
+    fn len(&mut self) -> io::Result<usize> {
+        let len = self.u64()?;
+        if len > self.0.len() as u64 {
+            return Err(malformed());
+        }
+        Ok(len as usize)
+    }
+
+    fn bytes(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.len()?;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|_| malformed())
+    }
+
+    fn value(&mut self) -> io::Result<Value> {
+        Ok(match self.u8()? {
+            0 => Value::Null,
+            1 => Value::Integer(self.u64()? as i64),
+            2 => Value::Real(f64::from_bits(self.u64()?)),
+            3 => Value::Text(self.string()?),
+            4 => Value::Blob(self.bytes()?.to_vec()),
+            _ => return Err(malformed()),
+        })
+    }
+
+    fn values(&mut self) -> io::Result<Vec<Value>> {
+        let len = self.len()?;
+        (0..len).map(|_| self.value()).collect()
+    }
+
+    fn strings(&mut self) -> io::Result<Vec<String>> {
+        let len = self.len()?;
+        (0..len).map(|_| self.string()).collect()
+    }
+
+    fn bools(&mut self) -> io::Result<Vec<bool>> {
+        let len = self.len()?;
+        (0..len)
+            .map(|_| match self.u8()? {
+                0 => Ok(false),
+                1 => Ok(true),
+                _ => Err(malformed()),
+            })
+            .collect()
+    }
+
+    fn finish<T>(self, v: T) -> io::Result<T> {
+        if self.0.is_empty() {
+            Ok(v)
+        } else {
+            Err(malformed())
+        }
+    }
+}
+
+impl Request {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut e = Encoder(Vec::new());
+        match self {
+            Request::PublicKey => e.u8(1),
+            Request::Execute { sql, params } => {
+                e.u8(2);
+                e.bytes(sql.as_bytes());
+                e.values(params);
+            }
+            Request::Query { sql, params } => {
+                e.u8(3);
+                e.bytes(sql.as_bytes());
+                e.values(params);
+            }
+            Request::Compare { comparisons, batch_size, statement } => {
+                e.u8(4);
+                e.strings(comparisons);
+                e.u64(*batch_size);
+                e.bytes(&statement.encode());
+            }
+            Request::Signs { positive } => {
+                e.u8(5);
+                e.u64(positive.len() as u64);
+                positive.iter().for_each(|&p| e.u8(p as u8));
+            }
+            Request::Transaction { statements } => {
+                e.u8(6);
+                e.u64(statements.len() as u64);
+                for (sql, params) in statements {
+                    e.bytes(sql.as_bytes());
+                    e.values(params);
+                }
+            }
+        }
+        e.0
+    }
+
+    pub fn decode(payload: &[u8]) -> io::Result<Self> {
+        let mut d = Decoder(payload);
+        let request = match d.u8()? {
+            1 => Request::PublicKey,
+            2 => Request::Execute { sql: d.string()?, params: d.values()? },
+            3 => Request::Query { sql: d.string()?, params: d.values()? },
+            4 => Request::Compare {
+                comparisons: d.strings()?,
+                batch_size: d.u64()?,
+                statement: Box::new(Request::decode(d.bytes()?)?),
+            },
+            5 => Request::Signs { positive: d.bools()? },
+            6 => {
+                let len = d.len()?;
+                let statements = (0..len).map(|_| Ok((d.string()?, d.values()?))).collect::<io::Result<_>>()?;
+                Request::Transaction { statements }
+            }
+            _ => return Err(malformed()),
+        };
+        d.finish(request)
+    }
+}
+
+impl Response {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut e = Encoder(Vec::new());
+        match self {
+            Response::PublicKey { version, key } => {
+                e.u8(1);
+                e.u64(*version as u64);
+                e.bytes(key.as_bytes());
+            }
+            Response::Executed { changes } => {
+                e.u8(2);
+                e.u64(*changes);
+            }
+            Response::Rows { columns, rows } => {
+                e.u8(3);
+                e.u64(columns.len() as u64);
+                columns.iter().for_each(|c| e.bytes(c.as_bytes()));
+                e.u64(rows.len() as u64);
+                rows.iter().for_each(|row| e.values(row));
+            }
+            Response::Error(message) => {
+                e.u8(4);
+                e.bytes(message.as_bytes());
+            }
+            Response::Masked { values } => {
+                e.u8(5);
+                e.strings(values);
+            }
+        }
+        e.0
+    }
+
+    pub fn decode(payload: &[u8]) -> io::Result<Self> {
+        let mut d = Decoder(payload);
+        let response = match d.u8()? {
+            1 => Response::PublicKey { version: d.u64()? as i64, key: d.string()? },
+            2 => Response::Executed { changes: d.u64()? },
+            3 => {
+                let columns = (0..d.len()?).map(|_| d.string()).collect::<io::Result<_>>()?;
+                let rows = (0..d.len()?).map(|_| d.values()).collect::<io::Result<_>>()?;
+                Response::Rows { columns, rows }
+            }
+            4 => Response::Error(d.string()?),
+            5 => Response::Masked { values: d.strings()? },
+            _ => return Err(malformed()),
+        };
+        d.finish(response)
+    }
+}
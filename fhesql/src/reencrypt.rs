@@ -0,0 +1,225 @@
+use crate::codec::parse_stored;
+use crate::metadata::record_key;
+use crate::schema::{ensure_schema_table, has_table, quote, SCHEMA_QUERY};
+use crate::tag::TagKey;
+use paillier_rs::decrypt::check_ciphertext;
+use paillier_rs::encoding::encode_ciphertext_blob;
+use paillier_rs::keyid::KeyId;
+use paillier_rs::rotation::{Checkpoint, Reencryptor};
+use rusqlite::types::Value;
+use rusqlite::{params, Connection};
+use std::error::Error;
+use std::path::Path;
+
+/// Rows re-encrypted per transaction when no batch size is given.
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Adds the `key_version` column to `encrypted_table` if an older database
+/// does not have it yet. Existing rows are assumed to be under version 1.
+pub fn ensure_key_version_column(conn: &Connection) -> rusqlite::Result<()> {
+    if conn.prepare("SELECT key_version FROM encrypted_table LIMIT 0").is_err() {
+        conn.execute(
+            "ALTER TABLE encrypted_table ADD COLUMN key_version INTEGER NOT NULL DEFAULT 1",
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+/// Re-encrypts the rows of `encrypted_table` under `old_version` to
+/// `new_version`, in id order and `batch_size` rows per transaction.
+///
+/// Both public keys are recorded in `paillier_keys` under their versions;
+/// a version already recorded with another key is an error. Ciphertexts are
+/// read as BLOBs or TEXT and written as BLOBs; a BLOB that records another
+/// key than the old one is an error.
+///
+/// After each committed batch the checkpoint is advanced and, if
+/// `checkpoint_path` is given, saved. A resumed run starts after the
+/// checkpoint's `last_id`; rows already moved to `new_version` no longer
+/// match `old_version`, so a crash between the commit and the checkpoint
+/// write is harmless.
+pub fn reencrypt_table(
+    conn: &mut Connection,
+    reencryptor: &Reencryptor,
+    old_version: i64,
+    new_version: i64,
+    batch_size: usize,
+    checkpoint: &mut Checkpoint,
+    checkpoint_path: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    if old_version == new_version {
+        return Err(format!("Old and new key version are both {}", new_version).into());
+    }
+    ensure_key_version_column(conn)?;
+    record_key(conn, old_version, reencryptor.old_pubkey)?;
+    record_key(conn, new_version, reencryptor.new_pubkey)?;
+    let (old_id, new_id) = (KeyId::of(reencryptor.old_pubkey), KeyId::of(reencryptor.new_pubkey));
+    loop {
+        let batch = {
+            let mut stmt = conn.prepare(
+                "SELECT id, ciphertext FROM encrypted_table
+                 WHERE id > ?1 AND key_version = ?2
+                 ORDER BY id LIMIT ?3",
+            )?;
+            let query = params![checkpoint.last_id, old_version, batch_size as i64];
+            let rows = stmt.query_map(query, |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, Value>(1)?))
+            })?;
+            let mut batch = Vec::new();
+            for row in rows {
+                let (id, value) = row?;
+                let (key_id, c) =
+                    parse_stored((&value).into()).ok_or_else(|| format!("Failed to parse ciphertext of row {}", id))?;
+                if let Some(key_id) = key_id.filter(|&key_id| key_id != old_id) {
+                    return Err(format!("Row {} is encrypted under key {}, not {}", id, key_id, old_id).into());
+                }
+                check_ciphertext(&c, reencryptor.old_pubkey).map_err(|e| format!("Row {}: {}", id, e))?;
+                batch.push((id, c));
+            }
+            batch
+        };
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let reencrypted = reencryptor.reencrypt_batch(&batch)?;
+        let tx = conn.transaction()?;
+        for (id, c) in &reencrypted {
+            tx.execute(
+                "UPDATE encrypted_table SET ciphertext = ?1, key_version = ?2 WHERE id = ?3",
+                params![encode_ciphertext_blob(c, &new_id), new_version, id],
+            )?;
+        }
+        tx.commit()?;
+
+        checkpoint.advance(&reencrypted);
+        if let Some(path) = checkpoint_path {
+            checkpoint.save(path)?;
+        }
+    }
+}
+
+/// The columns declared in `encrypted_columns`, with their tag columns.
+fn declared_columns(conn: &Connection) -> rusqlite::Result<Vec<(String, String, Option<String>)>> {
+    if !has_table(conn, "encrypted_columns")? {
+        return Ok(Vec::new());
+    }
+    ensure_schema_table(conn)?;
+    let mut stmt = conn.prepare(SCHEMA_QUERY)?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+    rows.collect()
+}
+
+/// Fails if a declared column has a tag column but no `tag_key` is given:
+/// tags are derived from the private key, so moving such a column to a new
+/// key needs the new private key to recompute them.
+pub fn check_tag_key(conn: &Connection, tag_key: Option<&TagKey>) -> Result<(), Box<dyn Error>> {
+    if tag_key.is_some() {
+        return Ok(());
+    }
+    match declared_columns(conn)?.into_iter().find_map(|(t, c, tag)| Some((t, c, tag?))) {
+        Some((table, column, tag_column)) => Err(format!(
+            "{}.{} has tags in {}, which need the new private key to be recomputed",
+            table, column, tag_column
+        )
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// Re-encrypts the values of the columns declared in `encrypted_columns`
+/// under the new key of `reencryptor`, `batch_size` values per transaction,
+/// and returns how many were moved. The tags of tagged columns are
+/// recomputed with `tag_key`, the [`TagKey`] of the new private key, in the
+/// same transaction; without it, a database with tagged columns is refused
+/// (see [`check_tag_key`]).
+///
+/// Values are signed integers encoded modulo n, and are re-encoded modulo
+/// the new n. Each column is walked in rowid order. Its values must be
+/// BLOBs recording the old key or TEXT, which is taken to be under it; a
+/// BLOB that records any other key is an error. BLOBs already under the
+/// new key are skipped, so like
+/// [`migrate_to_blobs`](crate::migrate::migrate_to_blobs) an interrupted
+/// run is resumed by running it again.
+pub fn reencrypt_columns(
+    conn: &mut Connection,
+    reencryptor: &Reencryptor,
+    tag_key: Option<&TagKey>,
+    batch_size: usize,
+) -> Result<u64, Box<dyn Error>> {
+    check_tag_key(conn, tag_key)?;
+    let mut moved = 0;
+    for (table, column, tag_column) in declared_columns(conn)? {
+        let tags = tag_column.as_deref().zip(tag_key);
+        moved += reencrypt_column(conn, reencryptor, &table, &column, tags, batch_size)?;
+    }
+    Ok(moved)
+}
+
+/// Re-encrypts the values of `table.column` that are not yet under the new
+/// key, in rowid order, and with `tags` recomputes the tag column.
+fn reencrypt_column(
+    conn: &mut Connection,
+    reencryptor: &Reencryptor,
+    table: &str,
+    column: &str,
+    tags: Option<(&str, &TagKey)>,
+    batch_size: usize,
+) -> Result<u64, Box<dyn Error>> {
+    let (old_id, new_id) = (KeyId::of(reencryptor.old_pubkey), KeyId::of(reencryptor.new_pubkey));
+    // The key ID of a BLOB follows its version byte.
+    let select = format!(
+        "SELECT rowid, {c} FROM {} WHERE rowid > ?1 AND {c} IS NOT NULL
+         AND NOT (typeof({c}) = 'blob' AND substr({c}, 2, ?2) = ?3)
+         ORDER BY rowid LIMIT ?4",
+        quote(table),
+        c = quote(column)
+    );
+    let update = match tags {
+        Some((tag_column, _)) => {
+            format!("UPDATE {} SET {} = ?1, {} = ?3 WHERE rowid = ?2", quote(table), quote(column), quote(tag_column))
+        }
+        None => format!("UPDATE {} SET {} = ?1 WHERE rowid = ?2", quote(table), quote(column)),
+    };
+    let (mut last_rowid, mut moved) = (i64::MIN, 0);
+    loop {
+        let batch = {
+            let mut stmt = conn.prepare(&select)?;
+            let query = params![last_rowid, KeyId::LEN as i64, new_id.as_bytes(), batch_size as i64];
+            let rows = stmt.query_map(query, |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Value>(1)?)))?;
+            let mut batch = Vec::new();
+            for row in rows {
+                let (rowid, value) = row?;
+                let (key_id, c) = parse_stored((&value).into())
+                    .ok_or_else(|| format!("{}.{}: failed to parse ciphertext of row {}", table, column, rowid))?;
+                if let Some(key_id) = key_id.filter(|&key_id| key_id != old_id) {
+                    return Err(
+                        format!("{}.{}: row {} is encrypted under key {}, not {}", table, column, rowid, key_id, old_id)
+                            .into(),
+                    );
+                }
+                batch.push((rowid, c));
+            }
+            batch
+        };
+        let Some(&(last, _)) = batch.last() else { return Ok(moved) };
+
+        let tx = conn.transaction()?;
+        for (rowid, c) in &batch {
+            // Decrypted once for both the new ciphertext and the tag.
+            let m = reencryptor
+                .decrypt_signed(c)
+                .map_err(|e| format!("{}.{}: row {}: {}", table, column, rowid, e))?;
+            let c = reencryptor.encrypt_signed(&m);
+            let blob = encode_ciphertext_blob(&c, &new_id);
+            match tags {
+                Some((_, tag_key)) => tx.execute(&update, params![blob, rowid, tag_key.tag(&m)])?,
+                None => tx.execute(&update, params![blob, rowid])?,
+            };
+        }
+        tx.commit()?;
+        last_rowid = last;
+        moved += batch.len() as u64;
+    }
+}
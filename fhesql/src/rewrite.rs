@@ -0,0 +1,766 @@
+use crate::schema::Schema;
+use num_bigint::BigInt;
+use sqlparser::ast::{
+    visit_expressions, visit_expressions_mut, visit_relations, Assignment, AssignmentTarget, BinaryOperator, Expr,
+    FromTable, Function, FunctionArg, FunctionArgExpr, FunctionArgumentList, FunctionArguments, GroupByExpr, Ident,
+    Insert, ObjectName, Query, SelectItem, SetExpr, Statement, TableFactor, TableWithJoins, UnaryOperator, Value,
+    Visit, VisitMut,
+};
+use sqlparser::dialect::SQLiteDialect;
+use sqlparser::parser::Parser;
+use std::collections::HashSet;
+use std::error::Error;
+use std::ops::ControlFlow;
+
+/// How the client reads a result column of a rewritten query.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnKind {
+    Plain,
+    /// A ciphertext of a signed integer.
+    Encrypted,
+    /// An `FHEAVG` result, see [`parse_average`](crate::functions::parse_average).
+    Average,
+}
+
+/// A statement rewritten by [`rewrite`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rewritten {
+    pub sql: String,
+    /// Whether the statement is a query.
+    pub returns_rows: bool,
+    /// Kinds of the result columns. Statements that touch no encrypted
+    /// column are not analyzed and leave this empty.
+    pub columns: Vec<ColumnKind>,
+    /// Numbers of the parameters that bind encrypted columns. Their values
+    /// must be encrypted before the statement is run.
+    pub encrypted_params: Vec<usize>,
+    /// Parameters added for tags, as `(added, source)` pairs: parameter
+    /// `added` must be bound to the tag of the value of parameter `source`.
+    pub tag_params: Vec<(usize, usize)>,
+    /// Queries returning `(rowid, Enc(2d + 1))` for each range predicate on
+    /// encrypted values, to be resolved with the key holder before the
+    /// statement runs; see [`Request::Compare`](crate::protocol::Request::Compare).
+    pub comparisons: Vec<String>,
+}
+
+impl Rewritten {
+    pub fn column_kind(&self, index: usize) -> ColumnKind {
+        self.columns.get(index).copied().unwrap_or(ColumnKind::Plain)
+    }
+}
+
+/// Rewrites `sql`, written as if every column held plaintext, into SQL over
+/// the encrypted columns declared in `schema`:
+///
+/// - `SUM` and `AVG` of encrypted values become `FHESUM` and `FHEAVG`;
+///   `COUNT` is left alone.
+/// - `+`, `-` and multiplication by a plaintext become `FHEADD`, `FHESUB`,
+///   `FHENEG`, `FHEADDCONST` and `FHEMULCONST`.
+/// - Integer literals assigned to encrypted columns in `INSERT` and
+///   `UPDATE` are encrypted with `encrypt` and inlined as BLOB literals;
+///   parameters bound to them are listed in
+///   [`Rewritten::encrypted_params`].
+/// - For columns with a tag column, `INSERT` and `UPDATE` also store the
+///   value's tag, computed with `tag`. `=`, `<>` and `IN` against integer
+///   literals, parameters or other tagged columns, and `GROUP BY`, compare
+///   the tag columns instead. Parameters that need a tag are listed in
+///   [`Rewritten::tag_params`].
+/// - `<`, `<=`, `>`, `>=` and `BETWEEN` on encrypted values in the `WHERE`
+///   of a single-table statement become `FHECMP` lookups of the
+///   [`Rewritten::comparisons`], which the server resolves interactively
+///   with the key holder.
+///
+/// Operations Paillier cannot evaluate on ciphertexts, such as other
+/// predicates on encrypted columns, `ORDER BY`, `DISTINCT` or untagged
+/// `GROUP BY` on them, or the product of two encrypted values, are rejected
+/// with an error naming the clause and column. Statements that do not touch
+/// a table with encrypted columns are returned unchanged.
+pub fn rewrite(
+    sql: &str,
+    schema: &Schema,
+    encrypt: &mut dyn FnMut(&BigInt) -> Vec<u8>,
+    tag: &dyn Fn(&BigInt) -> String,
+) -> Result<Rewritten, Box<dyn Error>> {
+    let mut statements = Parser::parse_sql(&SQLiteDialect {}, sql)?;
+    if statements.len() != 1 {
+        return Err("Expected a single SQL statement".into());
+    }
+    let mut statement = statements.remove(0);
+    let returns_rows = matches!(statement, Statement::Query(_));
+
+    let touches_encrypted = visit_relations(&statement, |name| {
+        if schema.has_encrypted_columns(&table_name(name)) {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    });
+    if touches_encrypted.is_continue() {
+        return Ok(unchanged(sql, returns_rows));
+    }
+
+    let last_param = number_parameters(&mut statement);
+    let mut rewriter = Rewriter {
+        schema,
+        encrypt,
+        tag,
+        scope: Vec::new(),
+        aliases: HashSet::new(),
+        params: Vec::new(),
+        last_param,
+        tag_params: Vec::new(),
+        comparisons: Vec::new(),
+    };
+    let columns = match &mut statement {
+        Statement::Query(query) => rewriter.query(query)?,
+        Statement::Insert(insert) => {
+            rewriter.insert(insert)?;
+            Vec::new()
+        }
+        Statement::Update { table, assignments, from, selection, returning, .. } => {
+            if returning.is_some() {
+                return Err("RETURNING is not supported on tables with encrypted columns".into());
+            }
+            rewriter.scope = tables(std::slice::from_ref(table))?;
+            if let Some(from) = from {
+                rewriter.scope.extend(tables(std::slice::from_ref(from))?);
+            }
+            rewriter.tag_comparisons(selection);
+            rewriter.range_comparisons(selection)?;
+            rewriter.check_plain(selection, "WHERE")?;
+            rewriter.assignments(&table_name_of(table)?, assignments)?;
+            Vec::new()
+        }
+        Statement::Delete(delete) => {
+            if delete.returning.is_some() {
+                return Err("RETURNING is not supported on tables with encrypted columns".into());
+            }
+            let (FromTable::WithFromKeyword(from) | FromTable::WithoutKeyword(from)) = &delete.from;
+            rewriter.scope = tables(from)?;
+            rewriter.tag_comparisons(&mut delete.selection);
+            rewriter.range_comparisons(&mut delete.selection)?;
+            rewriter.check_plain(&delete.selection, "WHERE")?;
+            rewriter.check_plain(&delete.order_by, "ORDER BY")?;
+            Vec::new()
+        }
+        // Schema changes and the like do not operate on values.
+        _ => return Ok(unchanged(sql, returns_rows)),
+    };
+    Ok(Rewritten {
+        sql: statement.to_string(),
+        returns_rows,
+        columns,
+        encrypted_params: rewriter.params,
+        tag_params: rewriter.tag_params,
+        comparisons: rewriter.comparisons,
+    })
+}
+
+fn unchanged(sql: &str, returns_rows: bool) -> Rewritten {
+    Rewritten {
+        sql: sql.to_string(),
+        returns_rows,
+        columns: Vec::new(),
+        encrypted_params: Vec::new(),
+        tag_params: Vec::new(),
+        comparisons: Vec::new(),
+    }
+}
+
+/// Gives every anonymous `?` parameter its SQLite number, so parameters can
+/// be referred to by number whatever style the statement uses, and returns
+/// the highest number.
+fn number_parameters(statement: &mut Statement) -> usize {
+    let mut last = 0;
+    let _ = visit_expressions_mut(statement, |e| {
+        if let Expr::Value(Value::Placeholder(p)) = e {
+            if p == "?" {
+                last += 1;
+                *p = format!("?{}", last);
+            } else if let Some(n) = parameter_number(p) {
+                last = usize::max(last, n);
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    last
+}
+
+/// The number of a `?NNN` parameter.
+fn parameter_number(placeholder: &str) -> Option<usize> {
+    placeholder.strip_prefix('?').and_then(|n| n.parse().ok())
+}
+
+fn table_name(name: &ObjectName) -> String {
+    name.0.last().map(|ident| ident.value.to_lowercase()).unwrap_or_default()
+}
+
+fn table_name_of(table: &TableWithJoins) -> Result<String, Box<dyn Error>> {
+    match &table.relation {
+        TableFactor::Table { name, .. } => Ok(table_name(name)),
+        _ => Err("Expected a table name".into()),
+    }
+}
+
+/// Tables in scope with their aliases, lowercased.
+type Scope = Vec<(String, Option<String>)>;
+
+/// The tables of a `FROM` clause.
+fn tables(from: &[TableWithJoins]) -> Result<Scope, Box<dyn Error>> {
+    let mut scope = Vec::new();
+    for table in from {
+        for relation in std::iter::once(&table.relation).chain(table.joins.iter().map(|join| &join.relation)) {
+            match relation {
+                TableFactor::Table { name, alias, .. } => {
+                    scope.push((table_name(name), alias.as_ref().map(|a| a.name.value.to_lowercase())));
+                }
+                _ => return Err("Subqueries in FROM are not supported on tables with encrypted columns".into()),
+            }
+        }
+    }
+    Ok(scope)
+}
+
+fn take(e: &mut Expr) -> Expr {
+    std::mem::replace(e, Expr::Value(Value::Null))
+}
+
+fn call(name: &str, args: Vec<Expr>) -> Expr {
+    Expr::Function(Function {
+        name: ObjectName(vec![Ident::new(name)]),
+        uses_odbc_syntax: false,
+        parameters: FunctionArguments::None,
+        args: FunctionArguments::List(FunctionArgumentList {
+            duplicate_treatment: None,
+            args: args.into_iter().map(|a| FunctionArg::Unnamed(FunctionArgExpr::Expr(a))).collect(),
+            clauses: Vec::new(),
+        }),
+        filter: None,
+        null_treatment: None,
+        over: None,
+        within_group: Vec::new(),
+    })
+}
+
+fn number(n: i64) -> Expr {
+    Expr::Value(Value::Number(n.to_string(), false))
+}
+
+fn negate(e: Expr) -> Expr {
+    Expr::UnaryOp { op: UnaryOperator::Minus, expr: Box::new(Expr::Nested(Box::new(e))) }
+}
+
+/// The value of an integer literal such as `42` or `-7`.
+fn integer_literal(e: &Expr) -> Option<BigInt> {
+    match e {
+        Expr::Value(Value::Number(n, _)) => n.parse().ok(),
+        Expr::Nested(e) | Expr::UnaryOp { op: UnaryOperator::Plus, expr: e } => integer_literal(e),
+        Expr::UnaryOp { op: UnaryOperator::Minus, expr: e } => integer_literal(e).map(|k| -k),
+        _ => None,
+    }
+}
+
+/// Whether `e` is an integer literal or a numbered parameter, whose tag
+/// the client can compute.
+fn taggable(e: &Expr) -> bool {
+    match e {
+        Expr::Value(Value::Placeholder(p)) => parameter_number(p).is_some(),
+        _ => integer_literal(e).is_some(),
+    }
+}
+
+struct Rewriter<'a> {
+    schema: &'a Schema,
+    encrypt: &'a mut dyn FnMut(&BigInt) -> Vec<u8>,
+    tag: &'a dyn Fn(&BigInt) -> String,
+    scope: Scope,
+    /// Aliases of encrypted result columns, lowercased.
+    aliases: HashSet<String>,
+    params: Vec<usize>,
+    /// Highest parameter number in use.
+    last_param: usize,
+    tag_params: Vec<(usize, usize)>,
+    comparisons: Vec<String>,
+}
+
+impl Rewriter<'_> {
+    /// Tables in scope that `qualifier`, a table name or alias, refers to.
+    fn qualified<'s>(&'s self, qualifier: &'s str) -> impl Iterator<Item = &'s str> {
+        let qualifier = qualifier.to_lowercase();
+        self.scope
+            .iter()
+            .filter(move |(table, alias)| *table == qualifier || alias.as_deref() == Some(qualifier.as_str()))
+            .map(|(table, _)| table.as_str())
+    }
+
+    /// Returns the name of the encrypted column `e` refers to, if it is one.
+    fn encrypted_column(&self, e: &Expr) -> Option<String> {
+        match e {
+            Expr::Identifier(ident) => {
+                let column = ident.value.to_lowercase();
+                let encrypted = self.scope.iter().any(|(table, _)| self.schema.is_encrypted(table, &column));
+                encrypted.then(|| ident.value.clone())
+            }
+            Expr::CompoundIdentifier(parts) if parts.len() >= 2 => {
+                let qualifier = &parts[parts.len() - 2].value;
+                let column = &parts[parts.len() - 1].value;
+                self.qualified(qualifier)
+                    .any(|table| self.schema.is_encrypted(table, column))
+                    .then(|| format!("{}.{}", qualifier, column))
+            }
+            _ => None,
+        }
+    }
+
+    /// If `e` refers to an encrypted column with a tag column, returns a
+    /// reference to the tag column to put in its place.
+    fn tag_of(&self, e: &Expr) -> Option<Expr> {
+        match e {
+            Expr::Identifier(ident) => {
+                let tag = self.scope.iter().find_map(|(table, _)| self.schema.tag_column(table, &ident.value))?;
+                Some(Expr::Identifier(Ident::new(tag)))
+            }
+            Expr::CompoundIdentifier(parts) if parts.len() >= 2 => {
+                let column = &parts[parts.len() - 1].value;
+                let qualifier = &parts[parts.len() - 2].value;
+                let tag = self.qualified(qualifier).find_map(|table| self.schema.tag_column(table, column))?;
+                let mut parts = parts.clone();
+                *parts.last_mut()? = Ident::new(tag);
+                Some(Expr::CompoundIdentifier(parts))
+            }
+            _ => None,
+        }
+    }
+
+    /// The tag of a [taggable] operand: a literal's tag, or a new parameter
+    /// bound to the tag of a parameter's value.
+    fn tag_operand(&mut self, e: &Expr) -> Option<Expr> {
+        if let Some(k) = integer_literal(e) {
+            return Some(Expr::Value(Value::SingleQuotedString((self.tag)(&k))));
+        }
+        let Expr::Value(Value::Placeholder(p)) = e else { return None };
+        let source = parameter_number(p)?;
+        self.last_param += 1;
+        self.tag_params.push((self.last_param, source));
+        Some(Expr::Value(Value::Placeholder(format!("?{}", self.last_param))))
+    }
+
+    /// The tag stored for `e`, the new value of the tagged column `column`.
+    fn tag_for_value(&mut self, e: &Expr, column: &str) -> Result<Expr, Box<dyn Error>> {
+        if let Expr::Value(Value::Null) = e {
+            return Ok(Expr::Value(Value::Null));
+        }
+        self.tag_operand(e).ok_or_else(|| {
+            format!("Value for tagged column {} must be an integer literal, a parameter or NULL", column).into()
+        })
+    }
+
+    /// Turns `=`, `<>` and `IN` comparisons of tagged columns in `node` into
+    /// comparisons of their tags, where the other side is a tagged column or
+    /// [taggable]. Other comparisons are left for [`check_plain`](Self::check_plain)
+    /// to reject.
+    fn tag_comparisons<V: VisitMut>(&mut self, node: &mut V) {
+        let _ = visit_expressions_mut(node, |e| {
+            match e {
+                Expr::BinaryOp { left, op: BinaryOperator::Eq | BinaryOperator::NotEq, right } => {
+                    let (l, r) = match (self.tag_of(left), self.tag_of(right)) {
+                        (Some(l), Some(r)) => (l, r),
+                        (Some(l), None) => match self.tag_operand(right) {
+                            Some(r) => (l, r),
+                            None => return ControlFlow::<()>::Continue(()),
+                        },
+                        (None, Some(r)) => match self.tag_operand(left) {
+                            Some(l) => (l, r),
+                            None => return ControlFlow::Continue(()),
+                        },
+                        (None, None) => return ControlFlow::Continue(()),
+                    };
+                    (**left, **right) = (l, r);
+                }
+                Expr::InList { expr, list, .. } if list.iter().all(taggable) => {
+                    if let Some(tag) = self.tag_of(expr) {
+                        **expr = tag;
+                        for e in list {
+                            *e = self.tag_operand(e).expect("taggable operand");
+                        }
+                    }
+                }
+                _ => {}
+            }
+            ControlFlow::Continue(())
+        });
+    }
+
+    /// Fails if `node` refers to an encrypted column, or to the alias of an
+    /// encrypted result column, anywhere. `clause` names the place for the
+    /// error message.
+    fn check_plain<V: Visit>(&self, node: &V, clause: &str) -> Result<(), Box<dyn Error>> {
+        let found = visit_expressions(node, |e| {
+            if matches!(e, Expr::Subquery(_) | Expr::InSubquery { .. } | Expr::Exists { .. }) {
+                let message = "Subqueries are not supported in statements on tables with encrypted columns";
+                return ControlFlow::Break(message.into());
+            }
+            let column = self.encrypted_column(e).or_else(|| match e {
+                Expr::Identifier(ident) if self.aliases.contains(&ident.value.to_lowercase()) => {
+                    Some(ident.value.clone())
+                }
+                _ => None,
+            });
+            match column {
+                Some(column) => ControlFlow::Break(format!(
+                    "{} on encrypted column {} is not supported under Paillier",
+                    clause, column
+                )),
+                None => ControlFlow::Continue(()),
+            }
+        });
+        match found {
+            ControlFlow::Break(message) => Err(message.into()),
+            ControlFlow::Continue(()) => Ok(()),
+        }
+    }
+
+    /// Replaces `<`, `<=`, `>`, `>=` and `BETWEEN` on encrypted values in
+    /// `node` with `FHECMP` lookups of new [comparisons](Rewritten::comparisons).
+    fn range_comparisons<V: VisitMut>(&mut self, node: &mut V) -> Result<(), Box<dyn Error>> {
+        let result = visit_expressions_mut(node, |e| match self.range_predicate(e) {
+            Ok(Some(lookup)) => {
+                *e = lookup;
+                ControlFlow::Continue(())
+            }
+            Ok(None) => ControlFlow::Continue(()),
+            Err(err) => ControlFlow::Break(err),
+        });
+        match result {
+            ControlFlow::Break(err) => Err(err),
+            ControlFlow::Continue(()) => Ok(()),
+        }
+    }
+
+    /// The `FHECMP` lookup replacing `e`, if it is a range predicate on
+    /// encrypted values.
+    fn range_predicate(&mut self, e: &Expr) -> Result<Option<Expr>, Box<dyn Error>> {
+        match e {
+            Expr::BinaryOp { left, op, right } => self.comparison(left, op, right),
+            Expr::Between { expr, negated, low, high } => {
+                let low_lookup = self.comparison(expr, &BinaryOperator::GtEq, low)?;
+                let high_lookup = self.comparison(expr, &BinaryOperator::LtEq, high)?;
+                if low_lookup.is_none() && high_lookup.is_none() {
+                    return Ok(None);
+                }
+                // A bound compared with plaintext stays an ordinary comparison.
+                let plain = |op, bound: &Expr| {
+                    Expr::BinaryOp { left: expr.clone(), op, right: Box::new(bound.clone()) }
+                };
+                let low = low_lookup.unwrap_or_else(|| plain(BinaryOperator::GtEq, low));
+                let high = high_lookup.unwrap_or_else(|| plain(BinaryOperator::LtEq, high));
+                let both = Expr::BinaryOp { left: Box::new(low), op: BinaryOperator::And, right: Box::new(high) };
+                let both = Expr::Nested(Box::new(both));
+                Ok(Some(if *negated { Expr::UnaryOp { op: UnaryOperator::Not, expr: Box::new(both) } } else { both }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Adds the comparison `left op right` if it involves encrypted values
+    /// and returns its lookup. `x > y` holds iff \(2(x - y) - 1 > 0\), and
+    /// likewise for the other operators, so each comparison computes an odd
+    /// \(d' = \pm 2(x - y) \pm 1\) whose sign the key holder determines.
+    fn comparison(&mut self, left: &Expr, op: &BinaryOperator, right: &Expr) -> Result<Option<Expr>, Box<dyn Error>> {
+        let (scale, offset) = match op {
+            BinaryOperator::Gt => (2, -1),
+            BinaryOperator::GtEq => (2, 1),
+            BinaryOperator::Lt => (-2, -1),
+            BinaryOperator::LtEq => (-2, 1),
+            _ => return Ok(None),
+        };
+        let mut difference =
+            Expr::BinaryOp { left: Box::new(left.clone()), op: BinaryOperator::Minus, right: Box::new(right.clone()) };
+        match self.value(&mut difference)? {
+            ColumnKind::Plain => return Ok(None),
+            ColumnKind::Average => return Err("The result of AVG cannot be compared".into()),
+            ColumnKind::Encrypted => {}
+        }
+        let [(table, alias)] = self.scope.as_slice() else {
+            return Err("Comparisons of encrypted values are only supported on a single table".into());
+        };
+        let (qualifier, from) = match alias {
+            Some(alias) => (alias.clone(), format!("{} AS {}", table, alias)),
+            None => (table.clone(), table.clone()),
+        };
+        let d = call("FHEADDCONST", vec![call("FHEMULCONST", vec![difference, number(scale)]), number(offset)]);
+        let id = self.comparisons.len() as i64;
+        self.comparisons.push(format!("SELECT {}.rowid, {} FROM {}", qualifier, d, from));
+        let rowid = Expr::CompoundIdentifier(vec![Ident::new(qualifier), Ident::new("rowid")]);
+        Ok(Some(call("FHECMP", vec![number(id), rowid])))
+    }
+
+    fn query(&mut self, query: &mut Query) -> Result<Vec<ColumnKind>, Box<dyn Error>> {
+        if query.with.is_some() {
+            return Err("WITH is not supported on tables with encrypted columns".into());
+        }
+        let SetExpr::Select(select) = query.body.as_mut() else {
+            return Err("Only plain SELECT queries are supported on tables with encrypted columns".into());
+        };
+        self.scope = tables(&select.from)?;
+        for table in &mut select.from {
+            for join in &mut table.joins {
+                self.tag_comparisons(&mut join.join_operator);
+                self.check_plain(&join.join_operator, "JOIN")?;
+            }
+        }
+
+        let mut columns = Vec::with_capacity(select.projection.len());
+        for item in &mut select.projection {
+            let kind = match item {
+                SelectItem::UnnamedExpr(e) => {
+                    let original = e.to_string();
+                    let kind = self.value(e)?;
+                    // Keep the column name SQLite would have given the
+                    // original expression.
+                    if e.to_string() != original {
+                        *item = SelectItem::ExprWithAlias { expr: take(e), alias: Ident::with_quote('"', original) };
+                    }
+                    kind
+                }
+                SelectItem::ExprWithAlias { expr, alias } => {
+                    let kind = self.value(expr)?;
+                    if kind != ColumnKind::Plain {
+                        self.aliases.insert(alias.value.to_lowercase());
+                    }
+                    kind
+                }
+                _ => return Err("SELECT * is not supported on tables with encrypted columns; list the columns".into()),
+            };
+            columns.push(kind);
+        }
+        if select.distinct.is_some() && columns.iter().any(|kind| *kind != ColumnKind::Plain) {
+            return Err("DISTINCT on encrypted columns is not supported under Paillier".into());
+        }
+
+        self.tag_comparisons(&mut select.selection);
+        self.tag_comparisons(&mut select.having);
+        self.range_comparisons(&mut select.selection)?;
+        if let GroupByExpr::Expressions(exprs, _) = &mut select.group_by {
+            for e in exprs {
+                if let Some(tag) = self.tag_of(e) {
+                    *e = tag;
+                }
+            }
+        }
+        self.check_plain(&select.selection, "WHERE")?;
+        self.check_plain(&select.group_by, "GROUP BY")?;
+        self.check_plain(&select.having, "HAVING")?;
+        self.check_plain(&query.order_by, "ORDER BY")?;
+        self.check_plain(&query.limit, "LIMIT")?;
+        self.check_plain(&query.offset, "OFFSET")?;
+        Ok(columns)
+    }
+
+    /// Rewrites an expression whose value is returned or stored, and
+    /// returns its kind.
+    fn value(&mut self, e: &mut Expr) -> Result<ColumnKind, Box<dyn Error>> {
+        let rewritten = match e {
+            Expr::Identifier(_) | Expr::CompoundIdentifier(_) => {
+                return Ok(if self.encrypted_column(e).is_some() { ColumnKind::Encrypted } else { ColumnKind::Plain });
+            }
+            Expr::Value(_) => return Ok(ColumnKind::Plain),
+            Expr::Nested(inner) => return self.value(inner),
+            Expr::Function(f) => return self.function(f),
+            Expr::UnaryOp { op: UnaryOperator::Minus, expr } => match self.value(expr)? {
+                ColumnKind::Plain => return Ok(ColumnKind::Plain),
+                ColumnKind::Encrypted => call("FHENEG", vec![take(expr)]),
+                ColumnKind::Average => return Err("The result of AVG cannot be used in further arithmetic".into()),
+            },
+            Expr::BinaryOp { left, op, right } => {
+                let kinds = (self.value(left)?, self.value(right)?);
+                if kinds == (ColumnKind::Plain, ColumnKind::Plain) {
+                    return Ok(ColumnKind::Plain);
+                }
+                if kinds.0 == ColumnKind::Average || kinds.1 == ColumnKind::Average {
+                    return Err("The result of AVG cannot be used in further arithmetic".into());
+                }
+                let encrypted = (kinds.0 == ColumnKind::Encrypted, kinds.1 == ColumnKind::Encrypted);
+                let (l, r) = (take(left), take(right));
+                match (op, encrypted) {
+                    (BinaryOperator::Plus, (true, true)) => call("FHEADD", vec![l, r]),
+                    (BinaryOperator::Plus, (true, false)) => call("FHEADDCONST", vec![l, r]),
+                    (BinaryOperator::Plus, (false, true)) => call("FHEADDCONST", vec![r, l]),
+                    (BinaryOperator::Minus, (true, true)) => call("FHESUB", vec![l, r]),
+                    (BinaryOperator::Minus, (true, false)) => call("FHEADDCONST", vec![l, negate(r)]),
+                    (BinaryOperator::Minus, (false, true)) => call("FHEADDCONST", vec![call("FHENEG", vec![r]), l]),
+                    (BinaryOperator::Multiply, (true, true)) => {
+                        return Err("Multiplying two encrypted values is not supported under Paillier".into());
+                    }
+                    (BinaryOperator::Multiply, (true, false)) => call("FHEMULCONST", vec![l, r]),
+                    (BinaryOperator::Multiply, (false, true)) => call("FHEMULCONST", vec![r, l]),
+                    (op, _) => {
+                        let message = format!("Operator {} on encrypted values is not supported under Paillier", op);
+                        return Err(message.into());
+                    }
+                }
+            }
+            _ => {
+                self.check_plain(e, &format!("`{}`", e))?;
+                return Ok(ColumnKind::Plain);
+            }
+        };
+        *e = rewritten;
+        Ok(ColumnKind::Encrypted)
+    }
+
+    fn function(&mut self, f: &mut Function) -> Result<ColumnKind, Box<dyn Error>> {
+        let name = f.name.to_string().to_uppercase();
+        // Calls to the homomorphic functions themselves pass through.
+        if name.starts_with("FHE") {
+            return Ok(if name == "FHEAVG" { ColumnKind::Average } else { ColumnKind::Encrypted });
+        }
+        let aggregate = matches!(name.as_str(), "SUM" | "AVG" | "COUNT");
+        let arg = match &mut f.args {
+            FunctionArguments::List(list) if aggregate && list.args.len() == 1 => match &mut list.args[0] {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(arg)) => Some(arg),
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some(arg) = arg else {
+            self.check_plain(f, &format!("{}()", name))?;
+            return Ok(ColumnKind::Plain);
+        };
+        match self.value(arg)? {
+            ColumnKind::Plain => {
+                self.check_plain(f, &format!("{}()", name))?;
+                return Ok(ColumnKind::Plain);
+            }
+            ColumnKind::Average => return Err("The result of AVG cannot be aggregated again".into()),
+            ColumnKind::Encrypted => {}
+        }
+        let distinct = matches!(&f.args, FunctionArguments::List(list) if list.duplicate_treatment.is_some());
+        if distinct || f.over.is_some() || f.filter.is_some() || !f.within_group.is_empty() {
+            return Err(format!("{} with DISTINCT, FILTER or OVER on encrypted values is not supported", name).into());
+        }
+        Ok(match name.as_str() {
+            "COUNT" => ColumnKind::Plain,
+            "SUM" => {
+                f.name = ObjectName(vec![Ident::new("FHESUM")]);
+                ColumnKind::Encrypted
+            }
+            _ => {
+                f.name = ObjectName(vec![Ident::new("FHEAVG")]);
+                ColumnKind::Average
+            }
+        })
+    }
+
+    /// Rewrites a value stored into the encrypted column `column`.
+    fn encrypted_value(&mut self, e: &mut Expr, column: &str) -> Result<(), Box<dyn Error>> {
+        if let Expr::Value(Value::Null) = e {
+            return Ok(());
+        }
+        if let Expr::Value(Value::Placeholder(p)) = e {
+            let n = parameter_number(p)
+                .ok_or_else(|| format!("Only ? and ?NNN parameters can bind encrypted column {}", column))?;
+            self.params.push(n);
+            return Ok(());
+        }
+        if let Some(k) = integer_literal(e) {
+            let blob = (self.encrypt)(&k);
+            *e = Expr::Value(Value::HexStringLiteral(blob.iter().map(|b| format!("{:02x}", b)).collect()));
+            return Ok(());
+        }
+        match self.value(e)? {
+            ColumnKind::Encrypted => Ok(()),
+            _ => Err(format!(
+                "Value for encrypted column {} must be an integer literal, a parameter or an encrypted expression",
+                column
+            )
+            .into()),
+        }
+    }
+
+    fn insert(&mut self, insert: &mut Insert) -> Result<(), Box<dyn Error>> {
+        let table = table_name(&insert.table_name);
+        if insert.returning.is_some() || insert.on.is_some() {
+            return Err("RETURNING and ON CONFLICT are not supported on tables with encrypted columns".into());
+        }
+        if insert.columns.is_empty() && self.schema.has_encrypted_columns(&table) {
+            return Err(format!("INSERT into {}, which has encrypted columns, needs a column list", table).into());
+        }
+        let schema = self.schema;
+        let columns: Vec<(String, bool)> =
+            insert.columns.iter().map(|c| (c.value.clone(), schema.is_encrypted(&table, &c.value))).collect();
+        let mut tag_columns = Vec::new();
+        for (i, (column, _)) in columns.iter().enumerate() {
+            if let Some(tagged) = schema.tagged_by(&table, column) {
+                return Err(format!("Column {} holds the tags of {} and cannot be set directly", column, tagged).into());
+            }
+            if let Some(tag_column) = schema.tag_column(&table, column) {
+                tag_columns.push((i, tag_column));
+            }
+        }
+        let Some(source) = &mut insert.source else { return Ok(()) };
+        match source.body.as_mut() {
+            SetExpr::Values(values) => {
+                for row in &mut values.rows {
+                    let mut tags = Vec::with_capacity(tag_columns.len());
+                    for &(i, _) in &tag_columns {
+                        let value = row.get(i).ok_or("Every row of VALUES needs a value for each column")?;
+                        tags.push(self.tag_for_value(value, &columns[i].0)?);
+                    }
+                    for (e, (column, encrypted)) in row.iter_mut().zip(&columns) {
+                        if *encrypted {
+                            self.encrypted_value(e, column)?;
+                        } else {
+                            self.check_plain(e, "VALUES")?;
+                        }
+                    }
+                    row.extend(tags);
+                }
+                insert.columns.extend(tag_columns.iter().map(|(_, tag_column)| Ident::new(*tag_column)));
+                Ok(())
+            }
+            SetExpr::Select(_) if !tag_columns.is_empty() => {
+                let column = &columns[tag_columns[0].0].0;
+                Err(format!("INSERT ... SELECT into tagged column {} is not supported", column).into())
+            }
+            SetExpr::Select(_) => {
+                let kinds = self.query(source)?;
+                for (kind, (column, encrypted)) in kinds.iter().zip(&columns) {
+                    if *encrypted != (*kind == ColumnKind::Encrypted) || *kind == ColumnKind::Average {
+                        let message = format!("INSERT ... SELECT mixes encrypted and plain values in {}", column);
+                        return Err(message.into());
+                    }
+                }
+                Ok(())
+            }
+            _ => Err("Only INSERT ... VALUES and INSERT ... SELECT are supported on tables with encrypted columns"
+                .into()),
+        }
+    }
+
+    fn assignments(&mut self, table: &str, assignments: &mut Vec<Assignment>) -> Result<(), Box<dyn Error>> {
+        let schema = self.schema;
+        let mut tags = Vec::new();
+        for assignment in assignments.iter_mut() {
+            let AssignmentTarget::ColumnName(name) = &assignment.target else {
+                return Err("Tuple assignments are not supported on tables with encrypted columns".into());
+            };
+            let column = table_name(name);
+            if let Some(tagged) = schema.tagged_by(table, &column) {
+                return Err(format!("Column {} holds the tags of {} and cannot be set directly", column, tagged).into());
+            }
+            if let Some(tag_column) = schema.tag_column(table, &column) {
+                let value = self.tag_for_value(&assignment.value, &column)?;
+                let target = AssignmentTarget::ColumnName(ObjectName(vec![Ident::new(tag_column)]));
+                tags.push(Assignment { target, value });
+            }
+            if schema.is_encrypted(table, &column) {
+                self.encrypted_value(&mut assignment.value, &column)?;
+            } else {
+                self.check_plain(&assignment.value, &format!("SET {}", column))?;
+            }
+        }
+        assignments.extend(tags);
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+
+/// Query returning the `(table, column, tag column)` rows of the registry.
+pub const SCHEMA_QUERY: &str = "SELECT table_name, column_name, tag_column FROM encrypted_columns";
+
+/// Statement declaring the column `?2` of table `?1` encrypted, with the tag
+/// column `?3` or NULL. Declaring a column again without a tag column keeps
+/// its tag column. Names are stored lowercased, since SQLite identifiers are
+/// case-insensitive.
+pub const DECLARE_SQL: &str = "INSERT INTO encrypted_columns (table_name, column_name, tag_column) VALUES (?1, ?2, ?3)
+    ON CONFLICT (table_name, column_name) DO UPDATE SET tag_column = COALESCE(excluded.tag_column, tag_column)";
+
+/// Creates the `encrypted_columns` table if it does not exist. Each row
+/// declares one column whose values are ciphertexts under the database key
+/// and, optionally, the column holding their [tags](crate::tag). Tables
+/// created before tags were supported get the `tag_column` column added.
+pub fn ensure_schema_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS encrypted_columns (
+            table_name  TEXT NOT NULL,
+            column_name TEXT NOT NULL,
+            tag_column  TEXT,
+            PRIMARY KEY (table_name, column_name)
+        )",
+        [],
+    )?;
+    if conn.prepare("SELECT tag_column FROM encrypted_columns LIMIT 0").is_err() {
+        conn.execute("ALTER TABLE encrypted_columns ADD COLUMN tag_column TEXT", [])?;
+    }
+    Ok(())
+}
+
+/// Declares `table.column` encrypted, with its tags in `tag_column` if
+/// given. The tag column itself must be filled by a key holder, see
+/// [`Client::declare_tagged`](crate::client::Client::declare_tagged).
+pub fn declare_encrypted_column(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    tag_column: Option<&str>,
+) -> rusqlite::Result<()> {
+    ensure_schema_table(conn)?;
+    let tag_column = tag_column.map(str::to_lowercase);
+    conn.execute(DECLARE_SQL, params![table.to_lowercase(), column.to_lowercase(), tag_column])?;
+    Ok(())
+}
+
+pub(crate) fn has_table(conn: &Connection, name: &str) -> rusqlite::Result<bool> {
+    let found = conn
+        .query_row("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1", params![name], |_| Ok(()))
+        .optional()?;
+    Ok(found.is_some())
+}
+
+/// Quotes an SQL identifier.
+pub(crate) fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Registry of the encrypted columns of a database, used by the
+/// [`rewrite`](crate::rewrite) module to decide which parts of a statement
+/// operate on ciphertexts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Schema {
+    /// Encrypted columns of each table, with their tag columns.
+    tables: HashMap<String, HashMap<String, Option<String>>>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Schema::default()
+    }
+
+    /// Reads the registry from `conn`.
+    pub fn load(conn: &Connection) -> rusqlite::Result<Self> {
+        ensure_schema_table(conn)?;
+        let mut stmt = conn.prepare(SCHEMA_QUERY)?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?))
+        })?;
+        let mut schema = Schema::new();
+        for row in rows {
+            let (table, column, tag_column) = row?;
+            match tag_column {
+                Some(tag_column) => schema.declare_tagged(&table, &column, &tag_column),
+                None => schema.declare(&table, &column),
+            }
+        }
+        Ok(schema)
+    }
+
+    /// Declares `table.column` encrypted. A tag column declared before is
+    /// kept.
+    pub fn declare(&mut self, table: &str, column: &str) {
+        self.tables.entry(table.to_lowercase()).or_default().entry(column.to_lowercase()).or_insert(None);
+    }
+
+    /// Declares `table.column` encrypted, with its tags in `tag_column`.
+    pub fn declare_tagged(&mut self, table: &str, column: &str, tag_column: &str) {
+        let columns = self.tables.entry(table.to_lowercase()).or_default();
+        columns.insert(column.to_lowercase(), Some(tag_column.to_lowercase()));
+    }
+
+    pub fn is_encrypted(&self, table: &str, column: &str) -> bool {
+        self.tables.get(&table.to_lowercase()).is_some_and(|columns| columns.contains_key(&column.to_lowercase()))
+    }
+
+    /// The column holding the tags of `table.column`, if it has one.
+    pub fn tag_column(&self, table: &str, column: &str) -> Option<&str> {
+        self.tables.get(&table.to_lowercase())?.get(&column.to_lowercase())?.as_deref()
+    }
+
+    /// The encrypted column whose tags `table.column` holds, if it is a tag
+    /// column.
+    pub fn tagged_by(&self, table: &str, column: &str) -> Option<&str> {
+        let column = column.to_lowercase();
+        let columns = self.tables.get(&table.to_lowercase())?;
+        columns.iter().find(|(_, tag)| tag.as_deref() == Some(column.as_str())).map(|(c, _)| c.as_str())
+    }
+
+    /// Whether `table` has any encrypted column.
+    pub fn has_encrypted_columns(&self, table: &str) -> bool {
+        self.tables.contains_key(&table.to_lowercase())
+    }
+}
@@ -0,0 +1,227 @@
+use crate::codec::CiphertextCodec;
+use crate::compare::{check_mask_room, mask_with_rng, register_comparison_function, ComparisonResults};
+use crate::functions::register_functions;
+use crate::metadata::{public_key_with_proof, stored_key_versions};
+use crate::protocol::{read_frame, write_frame, Request, Response, Statement};
+use crate::schema::ensure_schema_table;
+use paillier_rs::ciphertext::Ciphertext;
+use paillier_rs::encoding::encode_public_key;
+use paillier_rs::keygen::PublicKey;
+use paillier_rs::keyid::KeyId;
+use rand::thread_rng;
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
+use std::path::Path;
+
+/// The party that owns the SQLite file. It holds the database's public key
+/// only: clients encrypt parameters and decrypt results themselves, and the
+/// server evaluates the homomorphic SQL functions on ciphertexts.
+pub struct Server {
+    conn: Connection,
+    version: i64,
+    pubkey: PublicKey,
+    codec: CiphertextCodec,
+    encoded_key: String,
+    comparisons: ComparisonResults,
+}
+
+impl Server {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Server::new(Connection::open(path)?)
+    }
+
+    /// Serves `conn` under its active key, as stored by
+    /// [`attach_key`](crate::keys::attach_key).
+    pub fn new(conn: Connection) -> Result<Self, Box<dyn Error>> {
+        let version = *stored_key_versions(&conn)?.last().ok_or("Database has no key; run init or attach first")?;
+        let (pubkey, proof) = public_key_with_proof(&conn, version)?.ok_or("Database has no key")?;
+        register_functions(&conn, &pubkey)?;
+        let comparisons = ComparisonResults::default();
+        register_comparison_function(&conn, comparisons.clone())?;
+        ensure_schema_table(&conn)?;
+        let encoded_key = encode_public_key(&pubkey, &proof);
+        let codec = CiphertextCodec::new(&pubkey);
+        Ok(Server { conn, version, pubkey, codec, encoded_key, comparisons })
+    }
+
+    pub fn key_id(&self) -> KeyId {
+        KeyId::of(&self.pubkey)
+    }
+
+    pub fn version(&self) -> i64 {
+        self.version
+    }
+
+    /// Answers a single request. SQL errors are reported to the client
+    /// rather than returned. Comparisons need the client's answers, so
+    /// [`Request::Compare`] is only served by
+    /// [`serve_connection`](Self::serve_connection).
+    pub fn handle(&self, request: &Request) -> Response {
+        let result = match request {
+            Request::PublicKey => Ok(Response::PublicKey { version: self.version, key: self.encoded_key.clone() }),
+            Request::Execute { sql, params } => self.execute(sql, params),
+            Request::Query { sql, params } => self.query(sql, params),
+            Request::Transaction { statements } => self.transaction(statements),
+            Request::Compare { .. } => return Response::Error("Comparisons need a connection to the client".into()),
+            Request::Signs { .. } => return Response::Error("Signs sent outside of a comparison".into()),
+        };
+        result.unwrap_or_else(|e| Response::Error(e.to_string()))
+    }
+
+    /// Serves a [`Request::Compare`]: resolves each comparison with the
+    /// client on `stream`, batch by batch, then runs `statement` with the
+    /// results available to `FHECMP`.
+    fn compare<S: Read + Write>(
+        &self,
+        stream: &mut S,
+        comparisons: &[String],
+        batch_size: u64,
+        statement: &Request,
+    ) -> Result<Response, Box<dyn Error>> {
+        let (Request::Execute { sql, params } | Request::Query { sql, params }) = statement else {
+            return Err("Comparisons must precede a query or statement".into());
+        };
+        check_mask_room(&self.pubkey)?;
+        let batch_size = usize::try_from(batch_size).ok().filter(|&b| b > 0).ok_or("Batch size must be positive")?;
+
+        let mut results = HashMap::new();
+        for (id, comparison) in comparisons.iter().enumerate() {
+            let rows = self.comparison_rows(comparison, params)?;
+            for batch in rows.chunks(batch_size) {
+                let mut rng = thread_rng();
+                let (values, flips): (Vec<String>, Vec<bool>) = batch
+                    .iter()
+                    .map(|(_, c)| {
+                        let (masked, flipped) = mask_with_rng(&self.pubkey, c, &mut rng);
+                        (masked.to_str_radix(10), flipped)
+                    })
+                    .unzip();
+                write_frame(stream, &Response::Masked { values }.encode())?;
+                let frame = read_frame(stream)?.ok_or("Client closed the connection during a comparison")?;
+                let Request::Signs { positive } = Request::decode(&frame)? else {
+                    return Err("Expected the signs of a comparison batch".into());
+                };
+                if positive.len() != batch.len() {
+                    return Err(format!("Expected {} signs, got {}", batch.len(), positive.len()).into());
+                }
+                for (((row, _), flipped), positive) in batch.iter().zip(flips).zip(positive) {
+                    results.insert((id as i64, *row), positive != flipped);
+                }
+            }
+        }
+
+        // The rewritten statement may no longer use every parameter.
+        let params = &params[..self.conn.prepare(sql)?.parameter_count().min(params.len())];
+        *self.comparisons.lock().unwrap() = results;
+        let response = match statement {
+            Request::Execute { .. } => self.execute(sql, params),
+            _ => self.query(sql, params),
+        };
+        self.comparisons.lock().unwrap().clear();
+        Ok(response?)
+    }
+
+    /// Runs a comparison query, returning its `(rowid, ciphertext)` rows.
+    /// Rows whose ciphertext is NULL are left out.
+    fn comparison_rows(&self, sql: &str, params: &[Value]) -> Result<Vec<(i64, Ciphertext)>, Box<dyn Error>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let params = &params[..stmt.parameter_count().min(params.len())];
+        let mut rows = stmt.query(params_from_iter(params))?;
+        let mut compared = Vec::new();
+        while let Some(row) = rows.next()? {
+            if let Some(c) = self.codec.decode(row.get_ref(1)?).map_err(|e| format!("comparison: {}", e))? {
+                compared.push((row.get(0)?, c));
+            }
+        }
+        Ok(compared)
+    }
+
+    fn execute(&self, sql: &str, params: &[Value]) -> rusqlite::Result<Response> {
+        let changes = self.conn.execute(sql, params_from_iter(params))?;
+        Ok(Response::Executed { changes: changes as u64 })
+    }
+
+    /// Runs `statements` in one transaction. Dropping the transaction on an
+    /// error rolls it back; a client that already opened one with `BEGIN`
+    /// gets an error instead of a nested transaction.
+    fn transaction(&self, statements: &[Statement]) -> rusqlite::Result<Response> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut changes = 0;
+        for (sql, params) in statements {
+            changes += tx.execute(sql, params_from_iter(params))? as u64;
+        }
+        tx.commit()?;
+        Ok(Response::Executed { changes })
+    }
+
+    fn query(&self, sql: &str, params: &[Value]) -> rusqlite::Result<Response> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+        let mut rows = stmt.query(params_from_iter(params))?;
+        let mut values = Vec::new();
+        while let Some(row) = rows.next()? {
+            values.push((0..columns.len()).map(|i| row.get(i)).collect::<rusqlite::Result<_>>()?);
+        }
+        Ok(Response::Rows { columns, rows: values })
+    }
+
+    /// Answers requests on `stream` until the client closes it. Works over
+    /// any byte stream, e.g. a `TcpStream` or a `UnixStream`.
+    ///
+    /// Clients share the server's connection, so a transaction the client
+    /// left open, because it disconnected or failed after `BEGIN`, is rolled
+    /// back when the stream ends, however it ends.
+    pub fn serve_connection<S: Read + Write>(&self, stream: S) -> io::Result<()> {
+        let result = self.answer(stream);
+        if !self.conn.is_autocommit() {
+            if let Err(e) = self.conn.execute_batch("ROLLBACK") {
+                return result.and(Err(io::Error::other(e)));
+            }
+        }
+        result
+    }
+
+    fn answer<S: Read + Write>(&self, mut stream: S) -> io::Result<()> {
+        while let Some(frame) = read_frame(&mut stream)? {
+            let response = match Request::decode(&frame) {
+                Ok(Request::Compare { comparisons, batch_size, statement }) => self
+                    .compare(&mut stream, &comparisons, batch_size, &statement)
+                    .unwrap_or_else(|e| Response::Error(e.to_string())),
+                Ok(request) => self.handle(&request),
+                Err(e) => Response::Error(e.to_string()),
+            };
+            write_frame(&mut stream, &response.encode())?;
+        }
+        Ok(())
+    }
+
+    /// Serves clients on `listener` one connection at a time, forever. A
+    /// connection that fails, or cannot even be accepted, is reported on
+    /// stderr and dropped; the server goes on with the next one.
+    pub fn serve(&self, listener: &TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("accepting a connection: {}", e);
+                    continue;
+                }
+            };
+            let peer = match stream.peer_addr() {
+                Ok(peer) => peer,
+                Err(e) => {
+                    eprintln!("connection from an unknown peer: {}", e);
+                    continue;
+                }
+            };
+            if let Err(e) = self.serve_connection(stream) {
+                eprintln!("connection from {}: {}", peer, e);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use hmac::{Hmac, Mac};
+use num_bigint::BigInt;
+use paillier_rs::keygen::PrivateKey;
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Bytes of HMAC output kept in a tag.
+pub const TAG_LEN: usize = 16;
+
+/// Key of the deterministic tags stored next to encrypted columns, as in
+/// CryptDB's equality onion. Paillier ciphertexts are randomized, so the
+/// server cannot compare them; equal plaintexts have equal tags, which lets
+/// it evaluate `=`, `IN`, `GROUP BY` and equi-joins on the tag columns
+/// instead. The server learns which rows are equal, nothing more.
+///
+/// The key is derived from the database's private key, so key holders need
+/// no second secret. Tags therefore change when the database is moved to a
+/// new key.
+#[derive(Clone)]
+pub struct TagKey([u8; 32]);
+
+impl TagKey {
+    pub fn derive(privkey: &PrivateKey) -> Self {
+        let mut mac = HmacSha256::new_from_slice(&privkey.0.to_bytes_be()).expect("HMAC takes keys of any length");
+        mac.update(b"fhesql tag key v1");
+        TagKey(mac.finalize().into_bytes().into())
+    }
+
+    /// The tag of the signed integer `m`, as lowercase hex.
+    pub fn tag(&self, m: &BigInt) -> String {
+        let mut mac = HmacSha256::new_from_slice(&self.0).expect("HMAC takes keys of any length");
+        mac.update(&m.to_signed_bytes_be());
+        mac.finalize().into_bytes()[..TAG_LEN].iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
@@ -0,0 +1,414 @@
+use crate::codec::CiphertextCodec;
+use crate::keys::DatabaseKeys;
+use crate::metadata::active_key;
+use crate::schema::{declare_encrypted_column, has_table, quote, Schema};
+use num_bigint::{BigInt, BigUint};
+use num_traits::{FromPrimitive, ToPrimitive};
+use paillier_rs::decrypt::paillier_decrypt;
+use paillier_rs::encrypt::paillier_encrypt;
+use paillier_rs::fixed_point::{decode_f64, decode_signed, encode_signed};
+use rayon::prelude::*;
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use serde_json::de::IoRead;
+use serde_json::{Map, Number, StreamDeserializer, Value as Json};
+use std::error::Error;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// File formats of [`import`] and [`export`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// CSV with a header row; empty fields are NULL.
+    Csv,
+    /// JSON Lines: one object per line, keyed by column name.
+    Json,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "csv" => Some(Format::Csv),
+            "json" | "jsonl" => Some(Format::Json),
+            _ => None,
+        }
+    }
+
+    /// The format of a file by its extension; anything but `.json` and
+    /// `.jsonl` is CSV.
+    pub fn from_path(path: &Path) -> Format {
+        path.extension().and_then(|e| e.to_str()).and_then(Format::from_name).unwrap_or(Format::Csv)
+    }
+}
+
+/// Imports the records of `input` into `table`, `batch_size` rows per
+/// transaction (at least one), and returns how many were imported.
+///
+/// The columns in `encrypt` are declared encrypted, as are those declared
+/// before; their values are encrypted under the database's active key, each
+/// batch in parallel, and stored as BLOBs. They must be signed integers, or
+/// with `scale` decimals, stored as the fixed-point integer
+/// `round(x * scale)` like [`encode_f64`](paillier_rs::fixed_point::encode_f64)
+/// does, so that `FHESUM` and `FHEAVG` apply unchanged. Other values are
+/// stored as they are; CSV fields that parse as numbers become INTEGER or
+/// REAL. A missing table is created with the input's columns. Tag columns
+/// need the private key, so tagged columns cannot be imported into.
+///
+/// Batches are committed as they go, so a failed import leaves the rows
+/// before the failing batch in the table.
+pub fn import<R: Read>(
+    conn: &mut Connection,
+    table: &str,
+    input: R,
+    format: Format,
+    encrypt: &[&str],
+    scale: Option<u64>,
+    batch_size: usize,
+) -> Result<u64, Box<dyn Error>> {
+    let (_, pubkey) = active_key(conn)?.ok_or("Database has no key; run init or attach first")?;
+    let codec = CiphertextCodec::new(&pubkey);
+    let mut input = Input::new(input, format)?;
+    let columns = input.columns.clone();
+    let has_column = |names: &[String], name: &str| names.iter().any(|c| c.eq_ignore_ascii_case(name));
+    if let Some(column) = encrypt.iter().find(|column| !has_column(&columns, column)) {
+        return Err(format!("No column {} in the input", column).into());
+    }
+
+    let schema = Schema::load(conn)?;
+    let encrypted: Vec<bool> = columns
+        .iter()
+        .map(|c| encrypt.iter().any(|e| e.eq_ignore_ascii_case(c)) || schema.is_encrypted(table, c))
+        .collect();
+    for (column, _) in columns.iter().zip(&encrypted).filter(|(_, &encrypted)| encrypted) {
+        if let Some(tag_column) = schema.tag_column(table, column) {
+            return Err(format!("{}.{} has tags in {}, which need the private key", table, column, tag_column).into());
+        }
+    }
+    if has_table(conn, table)? {
+        let existing = table_columns(conn, table)?;
+        if let Some(column) = columns.iter().find(|column| !has_column(&existing, column)) {
+            return Err(format!("No column {} in table {}", column, table).into());
+        }
+    } else {
+        let definitions: Vec<String> = columns
+            .iter()
+            .zip(&encrypted)
+            .map(|(column, &encrypted)| if encrypted { format!("{} BLOB", quote(column)) } else { quote(column) })
+            .collect();
+        conn.execute(&format!("CREATE TABLE {} ({})", quote(table), definitions.join(", ")), [])?;
+    }
+    for column in encrypt {
+        declare_encrypted_column(conn, table, column, None)?;
+    }
+
+    let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+    let names: Vec<String> = columns.iter().map(|c| quote(c)).collect();
+    let insert = format!("INSERT INTO {} ({}) VALUES ({})", quote(table), names.join(", "), placeholders.join(", "));
+    let batch_size = batch_size.max(1);
+    let mut imported = 0;
+    loop {
+        let mut batch = Vec::with_capacity(batch_size);
+        while batch.len() < batch_size {
+            let Some(record) = input.next()? else { break };
+            let number = imported + batch.len() as u64 + 1;
+            let cells = record.into_iter().zip(&columns).zip(&encrypted).map(|((value, column), &encrypted)| {
+                let cell = if encrypted {
+                    plaintext(&value, scale, &pubkey.0).map(|m| m.map_or(Cell::Plain(Value::Null), Cell::Encrypt))
+                } else if format == Format::Csv {
+                    Ok(Cell::Plain(infer(value)))
+                } else {
+                    Ok(Cell::Plain(value))
+                };
+                cell.map_err(|e| format!("record {}: {}: {}", number, column, e))
+            });
+            batch.push(cells.collect::<Result<Vec<_>, _>>()?);
+        }
+        if batch.is_empty() {
+            return Ok(imported);
+        }
+
+        let rows: Vec<Vec<Value>> = batch
+            .into_par_iter()
+            .map(|cells| {
+                let cells = cells.into_iter().map(|cell| match cell {
+                    Cell::Plain(value) => value,
+                    Cell::Encrypt(m) => Value::Blob(codec.encode(&paillier_encrypt(&pubkey, &m))),
+                });
+                cells.collect()
+            })
+            .collect();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(&insert)?;
+            for row in &rows {
+                stmt.execute(params_from_iter(row))?;
+            }
+        }
+        tx.commit()?;
+        imported += rows.len() as u64;
+    }
+}
+
+/// Writes the rows of `table` to `output`, `batch_size` at a time (at least
+/// one), and returns how many were written.
+///
+/// With `keys`, the values of the declared encrypted columns are decrypted,
+/// each batch in parallel, to signed integers or, with `scale`, to the
+/// decimals [`import`] stored with that scale; tag columns are left out.
+/// Without, ciphertexts are written as hex.
+pub fn export<W: Write>(
+    conn: &Connection,
+    table: &str,
+    keys: Option<&DatabaseKeys>,
+    scale: Option<u64>,
+    output: W,
+    format: Format,
+    batch_size: usize,
+) -> Result<u64, Box<dyn Error>> {
+    if !has_table(conn, table)? {
+        return Err(format!("No table {}", table).into());
+    }
+    let schema = Schema::load(conn)?;
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {}", quote(table)))?;
+    let names: Vec<String> = stmt.column_names().into_iter().map(str::to_string).collect();
+    let kept: Vec<usize> =
+        (0..names.len()).filter(|&i| keys.is_none() || schema.tagged_by(table, &names[i]).is_none()).collect();
+    let columns: Vec<String> = kept.iter().map(|&i| names[i].clone()).collect();
+    let encrypted: Vec<bool> = columns.iter().map(|c| schema.is_encrypted(table, c)).collect();
+    let keys = keys.map(|keys| (CiphertextCodec::new(&keys.pubkey), keys));
+
+    let mut output = Output::new(output, format, &columns)?;
+    let mut rows = stmt.query([])?;
+    let batch_size = batch_size.max(1);
+    let mut exported = 0;
+    loop {
+        let mut batch = Vec::with_capacity(batch_size);
+        while batch.len() < batch_size {
+            let Some(row) = rows.next()? else { break };
+            batch.push(kept.iter().map(|&i| row.get::<_, Value>(i)).collect::<rusqlite::Result<Vec<_>>>()?);
+        }
+        if batch.is_empty() {
+            break;
+        }
+
+        let batch = batch
+            .into_par_iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let cells = row.into_iter().zip(&columns).zip(&encrypted);
+                cells
+                    .map(|((value, column), &encrypted)| match &keys {
+                        Some((codec, keys)) if encrypted => decrypt(codec, keys, &value, scale)
+                            .map_err(|e| format!("record {}: {}: {}", exported + i as u64 + 1, column, e)),
+                        _ => Ok(value),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for row in &batch {
+            output.write(&columns, row)?;
+        }
+        exported += batch.len() as u64;
+    }
+    output.finish()?;
+    Ok(exported)
+}
+
+/// A value of an imported row: stored as is, or encrypted first.
+enum Cell {
+    Plain(Value),
+    Encrypt(BigUint),
+}
+
+/// The plaintext of a value of an encrypted column in \(\mathbb{Z}_n\), or
+/// `None` for NULL.
+fn plaintext(value: &Value, scale: Option<u64>, n: &BigUint) -> Result<Option<BigUint>, String> {
+    let text = match value {
+        Value::Null => return Ok(None),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(x) => x.to_string(),
+        Value::Text(s) => s.trim().to_string(),
+        Value::Blob(_) => return Err("expected a number".into()),
+    };
+    let v = match (text.parse::<BigInt>(), text.parse::<f64>(), scale) {
+        (Ok(v), _, scale) => v * scale.unwrap_or(1),
+        (_, Ok(x), Some(scale)) if x.is_finite() => BigInt::from_f64((x * scale as f64).round())
+            .ok_or_else(|| format!("{} is out of range", text))?,
+        (_, Ok(x), None) if x.is_finite() => return Err(format!("{} is not an integer; pass a scale", text)),
+        _ => return Err(format!("{:?} is not a number", text)),
+    };
+    if v.magnitude() >= &(n >> 1) {
+        return Err(format!("{} is too large for the key", text));
+    }
+    Ok(Some(encode_signed(&v, n)))
+}
+
+/// Types a CSV field: integers and finite decimals become numbers.
+fn infer(value: Value) -> Value {
+    let Value::Text(text) = value else { return value };
+    if let Ok(i) = text.parse::<i64>() {
+        return Value::Integer(i);
+    }
+    match text.parse::<f64>() {
+        Ok(x) if x.is_finite() => Value::Real(x),
+        _ => Value::Text(text),
+    }
+}
+
+fn decrypt(codec: &CiphertextCodec, keys: &DatabaseKeys, value: &Value, scale: Option<u64>) -> Result<Value, String> {
+    let Some(c) = codec.decode(value.into()).map_err(|e| e.to_string())? else { return Ok(Value::Null) };
+    let m = paillier_decrypt(&keys.privkey, &keys.pubkey, c.as_biguint());
+    if let Some(scale) = scale {
+        return Ok(Value::Real(decode_f64(&m, scale, &keys.pubkey.0)));
+    }
+    let v = decode_signed(&m, &keys.pubkey.0);
+    Ok(v.to_i64().map_or_else(|| Value::Text(v.to_string()), Value::Integer))
+}
+
+fn table_columns(conn: &Connection, table: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1)")?;
+    let names = stmt.query_map([table], |row| row.get(0))?;
+    names.collect()
+}
+
+/// The records of an input file as SQL values, in the order of its
+/// columns: CSV fields as TEXT, or NULL when empty; JSON values with their
+/// types, or NULL when missing.
+struct Input<R: Read> {
+    columns: Vec<String>,
+    records: Records<R>,
+    read: u64,
+}
+
+enum Records<R: Read> {
+    Csv(csv::StringRecordsIntoIter<R>),
+    /// The objects of a JSON Lines file; the first one is read ahead for its
+    /// keys.
+    Json(Option<Map<String, Json>>, StreamDeserializer<'static, IoRead<R>, Map<String, Json>>),
+}
+
+impl<R: Read> Input<R> {
+    fn new(input: R, format: Format) -> Result<Self, Box<dyn Error>> {
+        let (columns, records) = match format {
+            Format::Csv => {
+                let mut reader = csv::Reader::from_reader(input);
+                let columns = reader.headers()?.iter().map(str::to_string).collect();
+                (columns, Records::Csv(reader.into_records()))
+            }
+            Format::Json => {
+                let mut objects = serde_json::Deserializer::from_reader(input).into_iter();
+                let first: Option<Map<String, Json>> = objects.next().transpose()?;
+                let columns = first.iter().flat_map(|object| object.keys().cloned()).collect();
+                (columns, Records::Json(first, objects))
+            }
+        };
+        Ok(Input { columns, records, read: 0 })
+    }
+
+    fn next(&mut self) -> Result<Option<Vec<Value>>, Box<dyn Error>> {
+        let number = self.read + 1;
+        let record = match &mut self.records {
+            Records::Csv(records) => match records.next().transpose()? {
+                Some(record) => record
+                    .iter()
+                    .map(|field| if field.is_empty() { Value::Null } else { Value::Text(field.to_string()) })
+                    .collect(),
+                None => return Ok(None),
+            },
+            Records::Json(first, objects) => {
+                let Some(mut object) = first.take().map_or_else(|| objects.next().transpose(), |o| Ok(Some(o)))? else {
+                    return Ok(None);
+                };
+                let values = self.columns.iter().map(|column| match object.remove(column) {
+                    Some(value) => sql_value(value).map_err(|e| format!("record {}: {}: {}", number, column, e)),
+                    None => Ok(Value::Null),
+                });
+                let values = values.collect::<Result<Vec<_>, _>>()?;
+                if let Some(column) = object.keys().next() {
+                    return Err(format!("record {}: unknown column {}", number, column).into());
+                }
+                values
+            }
+        };
+        self.read = number;
+        Ok(Some(record))
+    }
+}
+
+fn sql_value(value: Json) -> Result<Value, &'static str> {
+    Ok(match value {
+        Json::Null => Value::Null,
+        Json::Bool(b) => Value::Integer(b.into()),
+        Json::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => Value::Integer(i),
+            // Integers beyond i64 stay exact as text.
+            (None, _) if n.is_u64() => Value::Text(n.to_string()),
+            (None, x) => Value::Real(x.unwrap_or(f64::NAN)),
+        },
+        Json::String(s) => Value::Text(s),
+        Json::Array(_) | Json::Object(_) => return Err("nested values are not supported"),
+    })
+}
+
+/// Writes rows in a [`Format`]. NULL is an empty CSV field or JSON `null`;
+/// BLOBs are written as hex.
+enum Output<W: Write> {
+    Csv(Box<csv::Writer<W>>),
+    Json(W),
+}
+
+impl<W: Write> Output<W> {
+    fn new(output: W, format: Format, columns: &[String]) -> Result<Self, Box<dyn Error>> {
+        Ok(match format {
+            Format::Csv => {
+                let mut writer = csv::Writer::from_writer(output);
+                writer.write_record(columns)?;
+                Output::Csv(Box::new(writer))
+            }
+            Format::Json => Output::Json(output),
+        })
+    }
+
+    fn write(&mut self, columns: &[String], row: &[Value]) -> Result<(), Box<dyn Error>> {
+        match self {
+            Output::Csv(writer) => writer.write_record(row.iter().map(|value| match value {
+                Value::Null => String::new(),
+                Value::Integer(i) => i.to_string(),
+                Value::Real(x) => x.to_string(),
+                Value::Text(s) => s.clone(),
+                Value::Blob(blob) => hex(blob),
+            }))?,
+            Output::Json(writer) => {
+                let object: Map<String, Json> = columns
+                    .iter()
+                    .zip(row)
+                    .map(|(column, value)| {
+                        let value = match value {
+                            Value::Null => Json::Null,
+                            Value::Integer(i) => Json::from(*i),
+                            Value::Real(x) => Number::from_f64(*x).map_or(Json::Null, Json::Number),
+                            Value::Text(s) => Json::String(s.clone()),
+                            Value::Blob(blob) => Json::String(hex(blob)),
+                        };
+                        (column.clone(), value)
+                    })
+                    .collect();
+                serde_json::to_writer(&mut *writer, &object)?;
+                writer.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Box<dyn Error>> {
+        match self {
+            Output::Csv(mut writer) => writer.flush()?,
+            Output::Json(mut writer) => writer.flush()?,
+        }
+        Ok(())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,451 @@
+use fhesql::client::Client;
+use fhesql::codec::CiphertextCodec;
+use fhesql::keys::{attach_key, rotate_key};
+use fhesql::protocol::{read_frame, write_frame, Request, Response, MAX_FRAME_LEN};
+use fhesql::schema::declare_encrypted_column;
+use fhesql::server::Server;
+use fhesql::tag::TagKey;
+use num_bigint::BigInt;
+use paillier_rs::encrypt::paillier_encrypt;
+use paillier_rs::fixed_point::encode_signed;
+use paillier_rs::keygen::paillier_keygen_with_proof;
+use paillier_rs::rotation::Checkpoint;
+use rusqlite::types::Value;
+use rusqlite::{params, Connection};
+use std::io::Cursor;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+/// Starts a server on an ephemeral port that handles a single connection in
+/// a background thread, and returns its address.
+fn start_server(conn: Connection) -> String {
+    let server = Server::new(conn).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        server.serve_connection(stream).unwrap();
+    });
+    addr
+}
+
+#[test]
+fn client_and_server_over_tcp() {
+    let (pubkey, privkey, proof) = paillier_keygen_with_proof(128);
+    let conn = Connection::open_in_memory().unwrap();
+    let version = attach_key(&conn, &pubkey, &proof, &privkey).unwrap();
+    conn.execute_batch("CREATE TABLE emp (id INTEGER PRIMARY KEY, dept TEXT, salary TEXT)").unwrap();
+
+    let mut client = Client::connect(start_server(conn), privkey).unwrap();
+    assert_eq!(client.keys().version, version);
+    for (dept, salary) in [("eng", 120), ("eng", -20), ("ops", 70)] {
+        let salary = client.encrypt(salary);
+        let changes = client
+            .execute("INSERT INTO emp (dept, salary) VALUES (?1, ?2)", &[Value::Text(dept.into()), salary])
+            .unwrap();
+        assert_eq!(changes, 1);
+    }
+
+    let rows = client
+        .query("SELECT dept, FHESUM(salary) AS total FROM emp GROUP BY dept ORDER BY dept", &[])
+        .unwrap();
+    assert_eq!(rows.columns, ["dept", "total"]);
+    let total = rows.column("total").unwrap();
+    let totals: Vec<BigInt> = rows.rows.iter().map(|row| client.decrypt(&row[total]).unwrap().unwrap()).collect();
+    assert_eq!(totals, [BigInt::from(100), BigInt::from(70)]);
+
+    // The server stores ciphertexts, not the plaintexts.
+    let rows = client.query("SELECT salary FROM emp WHERE id = 3", &[]).unwrap();
+    assert_ne!(rows.rows[0][0], Value::Text("70".into()));
+    assert_eq!(client.decrypt(&rows.rows[0][0]).unwrap(), Some(70.into()));
+
+    let err = client.query("SELECT * FROM missing", &[]).unwrap_err().to_string();
+    assert!(err.starts_with("server: no such table"), "{}", err);
+    let err = client.execute("SELECT FHENEG('abc')", &[]).unwrap_err().to_string();
+    assert!(err.contains("FHENEG: argument 1: invalid ciphertext"), "{}", err);
+}
+
+#[test]
+fn transaction_left_open_is_rolled_back() {
+    let path = std::env::temp_dir().join(format!("fhesql-dangling-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let (pubkey, privkey, proof) = paillier_keygen_with_proof(128);
+    let conn = Connection::open(&path).unwrap();
+    attach_key(&conn, &pubkey, &proof, &privkey).unwrap();
+    conn.execute_batch("CREATE TABLE log (entry TEXT)").unwrap();
+    let server = Server::new(conn).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || server.serve(&listener));
+
+    // The first client opens a transaction, writes and disconnects.
+    let mut client = Client::connect(&addr, privkey.clone()).unwrap();
+    client.execute("BEGIN", &[]).unwrap();
+    client.execute("INSERT INTO log VALUES ('lost')", &[]).unwrap();
+    drop(client);
+
+    // The second client's write is committed on its own.
+    let mut client = Client::connect(&addr, privkey).unwrap();
+    assert_eq!(client.execute("INSERT INTO log VALUES ('kept')", &[]).unwrap(), 1);
+    let reader = Connection::open(&path).unwrap();
+    let entries: String = reader.query_row("SELECT group_concat(entry) FROM log", [], |row| row.get(0)).unwrap();
+    assert_eq!(entries, "kept");
+    drop(client);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn transaction_request_is_atomic() {
+    let (pubkey, privkey, proof) = paillier_keygen_with_proof(128);
+    let conn = Connection::open_in_memory().unwrap();
+    attach_key(&conn, &pubkey, &proof, &privkey).unwrap();
+    conn.execute_batch("CREATE TABLE log (entry TEXT)").unwrap();
+    let server = Server::new(conn).unwrap();
+    let insert = |entry: &str| ("INSERT INTO log VALUES (?1)".to_string(), vec![Value::Text(entry.into())]);
+    let count = || server.handle(&Request::Query { sql: "SELECT count(*) FROM log".into(), params: vec![] });
+    let counted = |n| Response::Rows { columns: vec!["count(*)".into()], rows: vec![vec![Value::Integer(n)]] };
+
+    let missing = ("INSERT INTO missing VALUES (1)".to_string(), vec![]);
+    let failing = Request::Transaction { statements: vec![insert("a"), missing] };
+    assert!(matches!(server.handle(&failing), Response::Error(e) if e.contains("no such table")));
+    assert_eq!(count(), counted(0));
+    let ok = Request::Transaction { statements: vec![insert("a"), insert("b")] };
+    assert_eq!(server.handle(&ok), Response::Executed { changes: 2 });
+    assert_eq!(count(), counted(2));
+
+    // A transaction cannot be nested in one the client opened itself.
+    server.handle(&Request::Execute { sql: "BEGIN".into(), params: vec![] });
+    assert!(matches!(server.handle(&ok), Response::Error(_)));
+    server.handle(&Request::Execute { sql: "ROLLBACK".into(), params: vec![] });
+    assert_eq!(count(), counted(2));
+}
+
+#[test]
+fn failed_tag_declaration_changes_nothing() {
+    let (pubkey, privkey, proof) = paillier_keygen_with_proof(128);
+    let conn = Connection::open_in_memory().unwrap();
+    attach_key(&conn, &pubkey, &proof, &privkey).unwrap();
+    conn.execute_batch("CREATE TABLE emp (name TEXT, salary BLOB); INSERT INTO emp VALUES ('ann', 'garbage')")
+        .unwrap();
+    let mut client = Client::connect(start_server(conn), privkey).unwrap();
+    assert!(client.declare_tagged("emp", "salary", None).is_err());
+    let columns = client.query("SELECT name FROM pragma_table_info('emp')", &[]).unwrap();
+    assert_eq!(columns.rows, [[Value::Text("name".into())], [Value::Text("salary".into())]]);
+    let declared = client.query("SELECT count(*) FROM encrypted_columns", &[]).unwrap();
+    assert_eq!(declared.rows, [[Value::Integer(0)]]);
+}
+
+#[test]
+fn client_rejects_foreign_private_key() {
+    let (pubkey, privkey, proof) = paillier_keygen_with_proof(128);
+    let (_, other_privkey, _) = paillier_keygen_with_proof(128);
+    let conn = Connection::open_in_memory().unwrap();
+    attach_key(&conn, &pubkey, &proof, &privkey).unwrap();
+    let err = Client::connect(start_server(conn), other_privkey).err().unwrap().to_string();
+    assert!(err.contains("does not match"), "{}", err);
+}
+
+#[test]
+fn server_needs_a_stored_key() {
+    let conn = Connection::open_in_memory().unwrap();
+    assert!(Server::new(conn).err().unwrap().to_string().contains("no key"));
+}
+
+#[test]
+fn messages_round_trip() {
+    let params = vec![
+        Value::Null,
+        Value::Integer(-5),
+        Value::Real(1.5),
+        Value::Text("é".into()),
+        Value::Blob(vec![0, 255]),
+    ];
+    let requests = [
+        Request::PublicKey,
+        Request::Execute { sql: "UPDATE t SET x = ?1".into(), params: params.clone() },
+        Request::Query { sql: "SELECT 1".into(), params: vec![] },
+        Request::Compare {
+            comparisons: vec!["SELECT rowid, x FROM t".into()],
+            batch_size: 64,
+            statement: Box::new(Request::Query {
+                sql: "SELECT FHECMP(0, rowid) FROM t".into(),
+                params: params.clone(),
+            }),
+        },
+        Request::Signs { positive: vec![true, false, true] },
+        Request::Transaction {
+            statements: vec![("UPDATE t SET x = ?1".into(), params.clone()), ("DELETE FROM t".into(), vec![])],
+        },
+        Request::Transaction { statements: vec![] },
+    ];
+    for request in requests {
+        assert_eq!(Request::decode(&request.encode()).unwrap(), request);
+    }
+    let responses = [
+        Response::PublicKey { version: 3, key: "paillier-public-key v1\n".into() },
+        Response::Executed { changes: 7 },
+        Response::Rows { columns: vec!["a".into(), "b".into()], rows: vec![params.clone(), vec![]] },
+        Response::Error("boom".into()),
+        Response::Masked { values: vec!["12".into(), "34".into()] },
+    ];
+    for response in responses {
+        assert_eq!(Response::decode(&response.encode()).unwrap(), response);
+    }
+
+    let mut stream = Vec::new();
+    write_frame(&mut stream, b"one").unwrap();
+    write_frame(&mut stream, b"").unwrap();
+    let mut stream = Cursor::new(stream);
+    assert_eq!(read_frame(&mut stream).unwrap(), Some(b"one".to_vec()));
+    assert_eq!(read_frame(&mut stream).unwrap(), Some(vec![]));
+    assert_eq!(read_frame(&mut stream).unwrap(), None);
+}
+
+#[test]
+fn malformed_messages_are_rejected() {
+    let mut encoded = Request::Query { sql: "SELECT 1".into(), params: vec![Value::Integer(1)] }.encode();
+    assert!(Request::decode(&encoded[..encoded.len() - 1]).is_err());
+    encoded.push(0);
+    assert!(Request::decode(&encoded).is_err());
+    assert!(Request::decode(&[9]).is_err());
+    assert!(Response::decode(&[]).is_err());
+    // A count larger than the remaining input.
+    assert!(Response::decode(&[3, 0xff, 0, 0, 0, 0, 0, 0, 0]).is_err());
+
+    let too_long = ((MAX_FRAME_LEN + 1) as u32).to_be_bytes();
+    assert!(read_frame(&mut Cursor::new(too_long)).is_err());
+    // The stream ends inside a frame.
+    assert!(read_frame(&mut Cursor::new([0, 0, 0, 5, 1])).is_err());
+}
+
+#[test]
+fn rewritten_statements_over_tcp() {
+    let (pubkey, privkey, proof) = paillier_keygen_with_proof(128);
+    let conn = Connection::open_in_memory().unwrap();
+    attach_key(&conn, &pubkey, &proof, &privkey).unwrap();
+    conn.execute_batch("CREATE TABLE emp (id INTEGER PRIMARY KEY, dept TEXT, salary TEXT)").unwrap();
+
+    let mut client = Client::connect(start_server(conn), privkey).unwrap();
+    client.declare_encrypted("emp", "salary").unwrap();
+    let insert = client.rewrite("INSERT INTO emp (dept, salary) VALUES ('eng', 120), (?, ?)").unwrap();
+    let params = [Value::Text("eng".into()), Value::Integer(-20)];
+    assert_eq!(client.execute_rewritten(&insert, &params).unwrap(), 2);
+    let insert = client.rewrite("INSERT INTO emp (dept, salary) VALUES ('ops', 70)").unwrap();
+    client.execute_rewritten(&insert, &[]).unwrap();
+    let update = client.rewrite("UPDATE emp SET salary = salary + 10 WHERE dept = ?").unwrap();
+    assert_eq!(client.execute_rewritten(&update, &[Value::Text("ops".into())]).unwrap(), 1);
+
+    let query = client
+        .rewrite("SELECT dept, SUM(salary), AVG(salary) AS mean FROM emp GROUP BY dept ORDER BY dept")
+        .unwrap();
+    let rows = client.query_rewritten(&query, &[]).unwrap();
+    assert_eq!(rows.columns, ["dept", "SUM(salary)", "mean"]);
+    assert_eq!(
+        rows.rows,
+        [
+            [Value::Text("eng".into()), Value::Integer(100), Value::Real(50.0)],
+            [Value::Text("ops".into()), Value::Integer(80), Value::Real(80.0)],
+        ]
+    );
+
+    // The declaration is stored on the server, not just in this client.
+    let err = client.rewrite("SELECT dept FROM emp ORDER BY salary").unwrap_err().to_string();
+    assert!(err.contains("ORDER BY on encrypted column salary"), "{}", err);
+    client.reload_schema().unwrap();
+    assert!(client.schema().is_encrypted("emp", "salary"));
+}
+
+#[test]
+fn tagged_columns_over_tcp() {
+    let (pubkey, privkey, proof) = paillier_keygen_with_proof(128);
+    let conn = Connection::open_in_memory().unwrap();
+    attach_key(&conn, &pubkey, &proof, &privkey).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE emp (id INTEGER PRIMARY KEY, name TEXT, salary TEXT);
+         CREATE TABLE grades (grade TEXT, salary TEXT);",
+    )
+    .unwrap();
+
+    let mut client = Client::connect(start_server(conn), privkey).unwrap();
+    client.declare_encrypted("emp", "salary").unwrap();
+    let insert = client.rewrite("INSERT INTO emp (name, salary) VALUES (?, ?)").unwrap();
+    for (name, salary) in [("ann", Value::Integer(100)), ("bob", Value::Integer(80)), ("cid", Value::Null)] {
+        client.execute_rewritten(&insert, &[Value::Text(name.into()), salary]).unwrap();
+    }
+
+    // Declaring the tag adds the column and tags the rows already stored;
+    // rows written afterwards are tagged by the rewriter.
+    client.declare_tagged("emp", "salary", None).unwrap();
+    client.declare_tagged("grades", "salary", Some("tag")).unwrap();
+    let insert = client.rewrite("INSERT INTO emp (name, salary) VALUES ('dan', 100)").unwrap();
+    client.execute_rewritten(&insert, &[]).unwrap();
+    let insert = client.rewrite("INSERT INTO grades (grade, salary) VALUES ('A', 100), ('B', 80)").unwrap();
+    client.execute_rewritten(&insert, &[]).unwrap();
+
+    let rows = client.query("SELECT salary_tag FROM emp ORDER BY id", &[]).unwrap();
+    assert_eq!(rows.rows, [[client.tag(100)], [client.tag(80)], [Value::Null], [client.tag(100)]]);
+
+    let query = client.rewrite("SELECT name FROM emp WHERE salary = ? ORDER BY name").unwrap();
+    let rows = client.query_rewritten(&query, &[Value::Integer(100)]).unwrap();
+    assert_eq!(rows.rows, [[Value::Text("ann".into())], [Value::Text("dan".into())]]);
+
+    let query = client.rewrite("SELECT salary, COUNT(*) AS n FROM emp GROUP BY salary ORDER BY n").unwrap();
+    let rows = client.query_rewritten(&query, &[]).unwrap();
+    assert_eq!(
+        rows.rows,
+        [
+            [Value::Null, Value::Integer(1)],
+            [Value::Integer(80), Value::Integer(1)],
+            [Value::Integer(100), Value::Integer(2)],
+        ]
+    );
+
+    let query = client
+        .rewrite("SELECT e.name, g.grade FROM emp e JOIN grades g ON e.salary = g.salary ORDER BY e.name")
+        .unwrap();
+    let rows = client.query_rewritten(&query, &[]).unwrap();
+    let pairs: Vec<_> = rows.rows.iter().map(|row| (row[0].clone(), row[1].clone())).collect();
+    let text = |s: &str| Value::Text(s.into());
+    assert_eq!(pairs, [(text("ann"), text("A")), (text("bob"), text("B")), (text("dan"), text("A"))]);
+
+    let update = client.rewrite("UPDATE emp SET salary = ? WHERE salary = ?").unwrap();
+    assert_eq!(client.execute_rewritten(&update, &[Value::Integer(90), Value::Integer(80)]).unwrap(), 1);
+    let query = client.rewrite("SELECT name FROM emp WHERE salary = 90").unwrap();
+    assert_eq!(client.query_rewritten(&query, &[]).unwrap().rows, [[Value::Text("bob".into())]]);
+}
+
+#[test]
+fn tags_follow_a_key_rotation() {
+    let (old_pubkey, old_privkey, old_proof) = paillier_keygen_with_proof(128);
+    let (new_pubkey, new_privkey, new_proof) = paillier_keygen_with_proof(160);
+    let mut conn = Connection::open_in_memory().unwrap();
+    attach_key(&conn, &old_pubkey, &old_proof, &old_privkey).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE emp (id INTEGER PRIMARY KEY, name TEXT, salary BLOB, salary_tag TEXT);
+         CREATE TABLE grades (grade TEXT, salary BLOB, tag TEXT);",
+    )
+    .unwrap();
+    declare_encrypted_column(&conn, "emp", "salary", Some("salary_tag")).unwrap();
+    declare_encrypted_column(&conn, "grades", "salary", Some("tag")).unwrap();
+
+    // Rows written and tagged under the old key.
+    let (codec, tag_key) = (CiphertextCodec::new(&old_pubkey), TagKey::derive(&old_privkey));
+    let encrypt = |m: i64| {
+        let m = BigInt::from(m);
+        (codec.encode(&paillier_encrypt(&old_pubkey, &encode_signed(&m, &old_pubkey.0))), tag_key.tag(&m))
+    };
+    for (name, salary) in [("ann", Some(100)), ("bob", Some(-80)), ("cid", None), ("dan", Some(100))] {
+        let (c, tag) = salary.map(encrypt).unzip();
+        conn.execute("INSERT INTO emp (name, salary, salary_tag) VALUES (?1, ?2, ?3)", params![name, c, tag])
+            .unwrap();
+    }
+    for (grade, salary) in [("A", 100), ("B", -80)] {
+        let (c, tag) = encrypt(salary);
+        conn.execute("INSERT INTO grades VALUES (?1, ?2, ?3)", params![grade, c, tag]).unwrap();
+    }
+
+    let mut checkpoint = Checkpoint::default();
+    rotate_key(&mut conn, &old_privkey, &new_pubkey, &new_proof, Some(&new_privkey), 1, &mut checkpoint, None)
+        .unwrap();
+    let mut client = Client::connect(start_server(conn), new_privkey).unwrap();
+
+    let rows = client.query("SELECT salary_tag FROM emp ORDER BY id", &[]).unwrap();
+    assert_eq!(rows.rows, [[client.tag(100)], [client.tag(-80)], [Value::Null], [client.tag(100)]]);
+    // Rows written after the rotation are tagged under the new key and must
+    // match the rotated ones.
+    let insert = client.rewrite("INSERT INTO emp (name, salary) VALUES ('eve', -80), ('fay', 100)").unwrap();
+    client.execute_rewritten(&insert, &[]).unwrap();
+    let insert = client.rewrite("INSERT INTO grades (grade, salary) VALUES ('C', 100)").unwrap();
+    client.execute_rewritten(&insert, &[]).unwrap();
+    let text = |s: &str| Value::Text(s.into());
+
+    let query = client.rewrite("SELECT name FROM emp WHERE salary = ? ORDER BY name").unwrap();
+    let rows = client.query_rewritten(&query, &[Value::Integer(100)]).unwrap();
+    assert_eq!(rows.rows, [[text("ann")], [text("dan")], [text("fay")]]);
+
+    let query = client.rewrite("SELECT name FROM emp WHERE salary IN (-80, 5) ORDER BY name").unwrap();
+    assert_eq!(client.query_rewritten(&query, &[]).unwrap().rows, [[text("bob")], [text("eve")]]);
+
+    let query = client.rewrite("SELECT salary, COUNT(*) AS n FROM emp GROUP BY salary ORDER BY n").unwrap();
+    let rows = client.query_rewritten(&query, &[]).unwrap();
+    assert_eq!(
+        rows.rows,
+        [
+            [Value::Null, Value::Integer(1)],
+            [Value::Integer(-80), Value::Integer(2)],
+            [Value::Integer(100), Value::Integer(3)],
+        ]
+    );
+
+    let query = client
+        .rewrite("SELECT e.name, g.grade FROM emp e JOIN grades g ON e.salary = g.salary ORDER BY e.name, g.grade")
+        .unwrap();
+    let rows = client.query_rewritten(&query, &[]).unwrap();
+    let pairs: Vec<String> = rows
+        .rows
+        .iter()
+        .map(|row| match (&row[0], &row[1]) {
+            (Value::Text(name), Value::Text(grade)) => format!("{}-{}", name, grade),
+            other => panic!("unexpected row {:?}", other),
+        })
+        .collect();
+    assert_eq!(pairs, ["ann-A", "ann-C", "bob-B", "dan-A", "dan-C", "eve-B", "fay-A", "fay-C"]);
+}
+
+/// Runs a rewritten query whose first column is an integer id. Returns the
+/// ids and the number of round trips the query took.
+fn ids(client: &mut Client<TcpStream>, sql: &str, params: &[Value]) -> (Vec<i64>, u64) {
+    let query = client.rewrite(sql).unwrap();
+    let before = client.round_trips();
+    let rows = client.query_rewritten(&query, params).unwrap();
+    let ids = rows.rows.iter().map(|row| if let Value::Integer(id) = row[0] { id } else { panic!() }).collect();
+    (ids, client.round_trips() - before)
+}
+
+#[test]
+fn range_predicates_over_tcp() {
+    let (pubkey, privkey, proof) = paillier_keygen_with_proof(128);
+    let conn = Connection::open_in_memory().unwrap();
+    attach_key(&conn, &pubkey, &proof, &privkey).unwrap();
+    conn.execute_batch("CREATE TABLE emp (id INTEGER PRIMARY KEY, salary TEXT, bonus TEXT)").unwrap();
+
+    let mut client = Client::connect(start_server(conn), privkey).unwrap();
+    client.declare_encrypted("emp", "salary").unwrap();
+    client.declare_encrypted("emp", "bonus").unwrap();
+    let salaries = [-40_000, 0, 10_000, 49_999, 50_000, 50_001, 70_000, i64::MAX, i64::MIN];
+    let insert = client.rewrite("INSERT INTO emp (id, salary, bonus) VALUES (?, ?, ?)").unwrap();
+    for (id, salary) in salaries.into_iter().enumerate() {
+        let params = [Value::Integer(id as i64), Value::Integer(salary), Value::Integer(id as i64 * 10_000)];
+        client.execute_rewritten(&insert, &params).unwrap();
+    }
+    client.execute("INSERT INTO emp (id, salary) VALUES (9, NULL)", &[]).unwrap();
+
+    client.set_batch_size(4);
+
+    // One comparison over the 9 non-NULL salaries takes 3 batches of at
+    // most 4, plus the request itself.
+    assert_eq!(ids(&mut client, "SELECT id FROM emp WHERE salary > 50000 ORDER BY id", &[]), (vec![5, 6, 7], 4));
+    let at_least = ids(&mut client, "SELECT id FROM emp WHERE salary >= ? ORDER BY id", &[Value::Integer(50_000)]);
+    assert_eq!(at_least, (vec![4, 5, 6, 7], 4));
+    assert_eq!(ids(&mut client, "SELECT id FROM emp WHERE 0 > salary ORDER BY id", &[]), (vec![0, 8], 4));
+    assert_eq!(ids(&mut client, "SELECT id FROM emp WHERE salary <= 0 ORDER BY id", &[]), (vec![0, 1, 8], 4));
+    // BETWEEN needs two comparisons; NULL salaries satisfy neither form.
+    let between = ids(&mut client, "SELECT id FROM emp WHERE salary BETWEEN 10000 AND 50000 ORDER BY id", &[]);
+    assert_eq!(between, (vec![2, 3, 4], 7));
+    let outside = ids(&mut client, "SELECT id FROM emp WHERE salary NOT BETWEEN 10000 AND 50000 ORDER BY id", &[]);
+    assert_eq!(outside.0, [0, 1, 5, 6, 7, 8]);
+    // Encrypted against encrypted: bonus is 10000 * id.
+    assert_eq!(ids(&mut client, "SELECT id FROM emp WHERE salary < bonus AND id < 8 ORDER BY id", &[]).0, [0, 1, 2]);
+
+    client.set_batch_size(100);
+    assert_eq!(ids(&mut client, "SELECT id FROM emp WHERE salary > 50000 ORDER BY id", &[]).1, 2);
+
+    let delete = client.rewrite("DELETE FROM emp WHERE salary < 0").unwrap();
+    assert_eq!(client.execute_rewritten(&delete, &[]).unwrap(), 2);
+    let update = client.rewrite("UPDATE emp SET bonus = 1 WHERE salary BETWEEN 1 AND 50000").unwrap();
+    assert_eq!(client.execute_rewritten(&update, &[]).unwrap(), 3);
+
+    // Results do not outlive their statement.
+    let rows = client.query("SELECT COUNT(FHECMP(0, rowid)) FROM emp", &[]).unwrap();
+    assert_eq!(rows.rows, [[Value::Integer(0)]]);
+}
@@ -0,0 +1,38 @@
+use fhesql::compare::{check_mask_room, is_positive, mask_with_rng, MASK_BITS, OPERAND_BITS};
+use num_bigint::BigInt;
+use num_traits::One;
+use paillier_rs::ciphertext::Ciphertext;
+use paillier_rs::encrypt::paillier_encrypt;
+use paillier_rs::fixed_point::encode_signed;
+use paillier_rs::keygen::paillier_keygen;
+use rand::thread_rng;
+
+#[test]
+fn masked_signs_recover_the_sign() {
+    let (pubkey, privkey) = paillier_keygen(128);
+    check_mask_room(&pubkey).unwrap();
+    let largest: BigInt = (BigInt::one() << OPERAND_BITS) - 1;
+    let mut rng = thread_rng();
+    for d in [BigInt::one(), -BigInt::one(), BigInt::from(3), BigInt::from(-12345), largest.clone(), -largest] {
+        let c = paillier_encrypt(&pubkey, &encode_signed(&d, &pubkey.0));
+        let c = Ciphertext::from_untrusted(c, &pubkey).unwrap();
+        let mut flips = [0, 0];
+        for _ in 0..32 {
+            let (masked, flipped) = mask_with_rng(&pubkey, &c, &mut rng);
+            let positive = is_positive(&privkey, &pubkey, &Ciphertext::from_untrusted(masked, &pubkey).unwrap());
+            assert_eq!(positive != flipped, d > BigInt::from(0), "d = {}", d);
+            flips[flipped as usize] += 1;
+        }
+        // The key holder sees either sign.
+        assert!(flips[0] > 0 && flips[1] > 0);
+    }
+}
+
+#[test]
+fn small_keys_have_no_room_for_masks() {
+    // Two 48-bit primes give a modulus of at most 96 bits.
+    let (pubkey, _) = paillier_keygen(48);
+    assert!(pubkey.0.bits() < MASK_BITS + OPERAND_BITS);
+    let err = check_mask_room(&pubkey).unwrap_err();
+    assert!(err.contains("too small for comparisons"), "{}", err);
+}
@@ -0,0 +1,282 @@
+use fhesql::codec::{decode_average_blob, CiphertextCodec, DecodeError, AVERAGE_BLOB_VERSION};
+use fhesql::functions::{parse_average, register_functions};
+use num_bigint::BigInt;
+use paillier_rs::decrypt::paillier_decrypt;
+use paillier_rs::encrypt::paillier_encrypt;
+use paillier_rs::fixed_point::{decode_signed, encode_signed};
+use paillier_rs::keygen::{paillier_keygen, PrivateKey, PublicKey};
+use paillier_rs::keyid::KeyId;
+use paillier_rs::stats::decrypt_mean;
+use paillier_rs::error::PaillierError;
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params, Connection};
+
+struct Db {
+    conn: Connection,
+    pubkey: PublicKey,
+    privkey: PrivateKey,
+    codec: CiphertextCodec,
+}
+
+impl Db {
+    fn new() -> Self {
+        let (pubkey, privkey) = paillier_keygen(128);
+        let conn = Connection::open_in_memory().unwrap();
+        register_functions(&conn, &pubkey).unwrap();
+        let codec = CiphertextCodec::new(&pubkey);
+        Db { conn, pubkey, privkey, codec }
+    }
+
+    fn encrypt(&self, m: i64) -> Vec<u8> {
+        self.codec.encode(&paillier_encrypt(&self.pubkey, &encode_signed(&BigInt::from(m), &self.pubkey.0)))
+    }
+
+    /// Encrypts in the base-10 TEXT format of older databases.
+    fn encrypt_text(&self, m: i64) -> String {
+        paillier_encrypt(&self.pubkey, &encode_signed(&BigInt::from(m), &self.pubkey.0)).to_str_radix(10)
+    }
+
+    fn decrypt(&self, c: &Value) -> BigInt {
+        let c = self.codec.decode(c.into()).unwrap().unwrap();
+        decode_signed(&paillier_decrypt(&self.privkey, &self.pubkey, c.as_biguint()), &self.pubkey.0)
+    }
+
+    fn query(&self, sql: &str, args: impl rusqlite::Params) -> rusqlite::Result<Option<Value>> {
+        self.conn.query_row(sql, args, |row| row.get(0))
+    }
+
+    fn eval(&self, sql: &str, args: impl rusqlite::Params) -> BigInt {
+        self.decrypt(&self.query(sql, args).unwrap().expect("unexpected NULL"))
+    }
+}
+
+#[test]
+fn arithmetic_functions() {
+    let db = Db::new();
+    let (a, b) = (db.encrypt(20), db.encrypt(-7));
+    assert_eq!(db.eval("SELECT FHEADD(?1, ?2)", params![a, b]), 13.into());
+    assert_eq!(db.eval("SELECT FHESUB(?1, ?2)", params![a, b]), 27.into());
+    assert_eq!(db.eval("SELECT FHENEG(?1)", params![b]), 7.into());
+    assert_eq!(db.eval("SELECT FHEADDCONST(?1, -50)", params![a]), (-30).into());
+    assert_eq!(db.eval("SELECT FHEMULCONST(?1, -3)", params![b]), 21.into());
+
+    let rerandomized = db.query("SELECT FHERERAND(?1)", params![a]).unwrap().unwrap();
+    assert_ne!(rerandomized, Value::Blob(a));
+    assert_eq!(db.decrypt(&rerandomized), 20.into());
+}
+
+#[test]
+fn balance_update_and_weighted_sum() {
+    let db = Db::new();
+    db.conn
+        .execute_batch("CREATE TABLE accounts (id INTEGER PRIMARY KEY, balance TEXT, weight INTEGER)")
+        .unwrap();
+    for (balance, weight) in [(100, 2), (250, -1), (40, 5)] {
+        db.conn
+            .execute("INSERT INTO accounts (balance, weight) VALUES (?1, ?2)", params![db.encrypt(balance), weight])
+            .unwrap();
+    }
+
+    db.conn.execute("UPDATE accounts SET balance = FHEADDCONST(balance, -30) WHERE id = 2", []).unwrap();
+    assert_eq!(db.eval("SELECT balance FROM accounts WHERE id = 2", []), 220.into());
+
+    // Σ weight · balance = 2·100 - 220 + 5·40
+    let weighted = db.eval(
+        "SELECT FHEADD(FHEADD(a.w, b.w), c.w) FROM
+            (SELECT FHEMULCONST(balance, weight) AS w FROM accounts WHERE id = 1) a,
+            (SELECT FHEMULCONST(balance, weight) AS w FROM accounts WHERE id = 2) b,
+            (SELECT FHEMULCONST(balance, weight) AS w FROM accounts WHERE id = 3) c",
+        [],
+    );
+    assert_eq!(weighted, 180.into());
+}
+
+#[test]
+fn null_arguments_propagate() {
+    let db = Db::new();
+    let c = db.encrypt(1);
+    for sql in [
+        "SELECT FHEADD(?1, NULL)",
+        "SELECT FHEADD(NULL, ?1)",
+        "SELECT FHESUB(NULL, ?1)",
+        "SELECT FHEADDCONST(?1, NULL)",
+        "SELECT FHEMULCONST(?1, NULL)",
+    ] {
+        assert_eq!(db.query(sql, params![c]).unwrap(), None, "{}", sql);
+    }
+    for sql in [
+        "SELECT FHENEG(NULL)",
+        "SELECT FHERERAND(NULL)",
+        "SELECT FHEADDCONST(NULL, 3)",
+        "SELECT FHEMULCONST(NULL, 3)",
+    ] {
+        assert_eq!(db.query(sql, []).unwrap(), None, "{}", sql);
+    }
+}
+
+#[test]
+fn invalid_arguments_name_function_and_position() {
+    let db = Db::new();
+    let c = db.encrypt(1);
+    let n_sq = (&db.pubkey.0 * &db.pubkey.0).to_str_radix(10);
+    let cases = [
+        ("SELECT FHEADD(?1, 'abc')", "FHEADD: argument 2: invalid ciphertext"),
+        ("SELECT FHESUB('0', ?1)", "FHESUB: argument 1: invalid ciphertext"),
+        ("SELECT FHEMULCONST(?1, 'x')", "FHEMULCONST: argument 2: expected an integer"),
+        ("SELECT FHEADDCONST(?1, 1.5)", "FHEADDCONST: argument 2: expected an integer"),
+        ("SELECT FHENEG(length(?1))", "FHENEG: argument 1: expected a ciphertext blob or string"),
+        ("SELECT FHENEG(substr(?1, 1, 17))", "FHENEG: argument 1: invalid ciphertext"),
+    ];
+    for (sql, expected) in cases {
+        let err = db.query(sql, params![c]).unwrap_err().to_string();
+        assert!(err.contains(expected), "{}: {}", sql, err);
+    }
+    let err = db.query("SELECT FHERERAND(?1)", params![n_sq]).unwrap_err().to_string();
+    assert!(err.contains("FHERERAND: argument 1: invalid ciphertext"), "{}", err);
+}
+
+fn salaries(db: &Db) {
+    db.conn.execute_batch("CREATE TABLE salaries (dept TEXT, salary TEXT)").unwrap();
+    for (dept, salary) in [("eng", Some(120)), ("eng", Some(-20)), ("eng", None), ("ops", Some(70)), ("hr", None)] {
+        db.conn
+            .execute("INSERT INTO salaries VALUES (?1, ?2)", params![dept, salary.map(|m| db.encrypt(m))])
+            .unwrap();
+    }
+}
+
+#[test]
+fn sum_and_average_per_group() {
+    let db = Db::new();
+    salaries(&db);
+
+    let mut stmt = db
+        .conn
+        .prepare("SELECT dept, FHESUM(salary), FHEAVG(salary), COUNT(salary) FROM salaries GROUP BY dept ORDER BY dept")
+        .unwrap();
+    let rows: Vec<(String, Option<Value>, Option<Value>, i64)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(rows.len(), 3);
+    let (dept, sum, avg, count) = &rows[0];
+    assert_eq!((dept.as_str(), count), ("eng", &2));
+    assert_eq!(db.decrypt(sum.as_ref().unwrap()), 100.into());
+    let avg = parse_average(&db.codec, avg.as_ref().unwrap().into()).unwrap().unwrap();
+    assert_eq!(avg.count, 2);
+    assert_eq!(decrypt_mean(&db.privkey, &db.pubkey, &avg, 1), (100.0, 50.0));
+
+    // A group with only NULLs yields NULL, like SUM and AVG.
+    assert_eq!(rows[1], ("hr".to_string(), None, None, 0));
+
+    let (_, sum, avg, _) = &rows[2];
+    assert_eq!(db.decrypt(sum.as_ref().unwrap()), 70.into());
+    let avg = parse_average(&db.codec, avg.as_ref().unwrap().into()).unwrap().unwrap();
+    assert_eq!(decrypt_mean(&db.privkey, &db.pubkey, &avg, 1).1, 70.0);
+}
+
+#[test]
+fn aggregates_over_no_rows() {
+    let db = Db::new();
+    salaries(&db);
+    for sql in [
+        "SELECT FHESUM(salary) FROM salaries WHERE dept = 'none'",
+        "SELECT FHEAVG(salary) FROM salaries WHERE 0",
+    ] {
+        assert_eq!(db.query(sql, []).unwrap(), None, "{}", sql);
+    }
+    assert_eq!(db.eval("SELECT FHESUM(FHEMULCONST(salary, 2)) FROM salaries", []), 340.into());
+}
+
+#[test]
+fn aggregates_reject_invalid_ciphertexts() {
+    let db = Db::new();
+    salaries(&db);
+    db.conn.execute("INSERT INTO salaries VALUES ('ops', 'garbage')", []).unwrap();
+    for (sql, expected) in [
+        ("SELECT FHESUM(salary) FROM salaries", "FHESUM: argument 1: invalid ciphertext"),
+        ("SELECT FHEAVG(salary) FROM salaries", "FHEAVG: argument 1: invalid ciphertext"),
+    ] {
+        let err = db.query(sql, []).unwrap_err().to_string();
+        assert!(err.contains(expected), "{}: {}", sql, err);
+    }
+}
+
+#[test]
+fn average_is_a_blob_under_the_key() {
+    let db = Db::new();
+    salaries(&db);
+    let avg = db.query("SELECT FHEAVG(salary) FROM salaries", []).unwrap().unwrap();
+    let Value::Blob(blob) = &avg else { panic!("FHEAVG returned {:?}", avg) };
+    // The sum is the ciphertext FHESUM returns, but under its own version
+    // byte, so neither blob is taken for the other.
+    let sum = db.query("SELECT FHESUM(salary) FROM salaries", []).unwrap().unwrap();
+    let Value::Blob(sum) = &sum else { panic!("FHESUM returned {:?}", sum) };
+    assert_eq!(blob[0], AVERAGE_BLOB_VERSION);
+    let (key_id, count, c) = decode_average_blob(blob).unwrap();
+    assert_eq!((key_id, count), (KeyId::of(&db.pubkey), 3));
+    assert_eq!(db.codec.encode(&c), *sum);
+    assert!(db.codec.decode(ValueRef::Blob(blob)).is_err());
+    assert!(parse_average(&db.codec, ValueRef::Blob(sum)).is_err());
+    let err = db.query("SELECT FHEADD(FHEAVG(salary), FHEAVG(salary)) FROM salaries", []).unwrap_err();
+    assert!(err.to_string().contains("FHEADD: argument 1: invalid ciphertext"), "{}", err);
+    let parsed = parse_average(&db.codec, (&avg).into()).unwrap().unwrap();
+    assert_eq!(decrypt_mean(&db.privkey, &db.pubkey, &parsed, 1), (170.0, 170.0 / 3.0));
+    assert_eq!(parse_average(&db.codec, ValueRef::Null), Ok(None));
+
+    // The header names the key, so another key's codec rejects it.
+    let (other, _) = paillier_keygen(128);
+    let err = parse_average(&CiphertextCodec::new(&other), (&avg).into()).unwrap_err();
+    assert!(matches!(err, DecodeError::Invalid(PaillierError::WrongKey { .. })), "{:?}", err);
+
+    // Too short for a count, or a count with no sum after it.
+    for malformed in [&blob[..0], &blob[..17], &blob[..25]] {
+        assert!(parse_average(&db.codec, ValueRef::Blob(malformed)).is_err(), "{:?}", malformed);
+    }
+    assert_eq!(parse_average(&db.codec, ValueRef::Text(b"100/2")), Err(DecodeError::NotACiphertext));
+    assert_eq!(parse_average(&db.codec, ValueRef::Integer(50)), Err(DecodeError::NotACiphertext));
+}
+
+#[test]
+fn text_and_blob_ciphertexts_mix() {
+    let db = Db::new();
+    let (blob, text) = (db.encrypt(5), db.encrypt_text(-12));
+    assert_eq!(db.eval("SELECT FHEADD(?1, ?2)", params![blob, text]), (-7).into());
+    assert_eq!(db.eval("SELECT FHENEG(?1)", params![text]), 12.into());
+    // Results are BLOBs either way.
+    assert!(matches!(db.query("SELECT FHEADDCONST(?1, 1)", params![text]).unwrap(), Some(Value::Blob(_))));
+
+    db.conn.execute_batch("CREATE TABLE t (c)").unwrap();
+    db.conn.execute("INSERT INTO t VALUES (?1), (?2)", params![blob, text]).unwrap();
+    assert_eq!(db.eval("SELECT FHESUM(c) FROM t", []), (-7).into());
+
+    let other = Db::new();
+    let err = db.query("SELECT FHENEG(?1)", params![other.encrypt(1)]).unwrap_err().to_string();
+    assert!(err.contains("FHENEG: argument 1: invalid ciphertext: ciphertext belongs to key"), "{}", err);
+}
+
+#[test]
+fn repeated_and_constant_arguments() {
+    let db = Db::new();
+    db.conn.execute_batch("CREATE TABLE t (id INTEGER PRIMARY KEY, c)").unwrap();
+    for m in [3, -4, 5] {
+        db.conn.execute("INSERT INTO t (c) VALUES (?1)", params![db.encrypt(m)]).unwrap();
+    }
+    let column = |sql: &str, args: &[&dyn rusqlite::ToSql]| -> Vec<BigInt> {
+        let mut stmt = db.conn.prepare(sql).unwrap();
+        let rows = stmt.query_map(args, |row| row.get::<_, Value>(0)).unwrap();
+        rows.map(|c| db.decrypt(&c.unwrap())).collect()
+    };
+
+    // The same ciphertext twice in a row, and a bound ciphertext in every row.
+    assert_eq!(column("SELECT FHEADD(c, c) FROM t ORDER BY id", &[]), [6, -8, 10].map(BigInt::from));
+    let ten = db.encrypt(10);
+    assert_eq!(column("SELECT FHESUB(c, ?1) FROM t ORDER BY id", &[&ten]), [-7, -14, -5].map(BigInt::from));
+    assert_eq!(column("SELECT FHEMULCONST(c, 0) FROM t ORDER BY id", &[]), [0, 0, 0].map(BigInt::from));
+    assert_eq!(db.eval("SELECT FHEADD(FHESUM(c), FHENEG(FHESUM(c))) FROM t", []), 0.into());
+
+    // A constant that fails to parse fails in every row.
+    let err = db.query("SELECT count(FHEADD(c, x'01')) FROM t", []).unwrap_err().to_string();
+    assert!(err.contains("FHEADD: argument 2: invalid ciphertext"), "{}", err);
+}
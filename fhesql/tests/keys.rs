@@ -0,0 +1,290 @@
+use fhesql::codec::{parse_stored, CiphertextCodec};
+use fhesql::keys::{attach_key, check_new_key, open_keys, rotate_key};
+use fhesql::metadata::{active_key, key_id, public_key};
+use fhesql::reencrypt::{ensure_key_version_column, reencrypt_columns, reencrypt_table};
+use fhesql::schema::declare_encrypted_column;
+use fhesql::tag::TagKey;
+use num_bigint::{BigInt, BigUint};
+use paillier_rs::fixed_point::{decode_signed, encode_signed};
+use paillier_rs::decrypt::paillier_decrypt;
+use paillier_rs::encrypt::paillier_encrypt;
+use paillier_rs::keygen::{paillier_keygen_with_proof, PrivateKey, PublicKey};
+use paillier_rs::keyid::KeyId;
+use paillier_rs::keyproof::KeyProof;
+use paillier_rs::rotation::{Checkpoint, Reencryptor};
+use rusqlite::types::Value;
+use rusqlite::{params, Connection};
+
+fn key(bits: usize) -> (PublicKey, PrivateKey, KeyProof) {
+    paillier_keygen_with_proof(bits)
+}
+
+fn database() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("CREATE TABLE encrypted_table (id INTEGER PRIMARY KEY, ciphertext TEXT NOT NULL)").unwrap();
+    ensure_key_version_column(&conn).unwrap();
+    conn
+}
+
+/// Inserts a row in the TEXT format of older databases.
+fn insert(conn: &Connection, pubkey: &PublicKey, version: i64, m: u32) {
+    let c = paillier_encrypt(pubkey, &BigUint::from(m)).to_str_radix(10);
+    conn.execute("INSERT INTO encrypted_table (ciphertext, key_version) VALUES (?1, ?2)", params![c, version])
+        .unwrap();
+}
+
+fn rows(conn: &Connection) -> Vec<(Value, i64)> {
+    let mut stmt = conn.prepare("SELECT ciphertext, key_version FROM encrypted_table ORDER BY id").unwrap();
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+    rows.collect::<Result<_, _>>().unwrap()
+}
+
+#[test]
+fn attached_key_survives_reopening() {
+    let (pubkey, privkey, proof) = key(128);
+    let conn = database();
+    assert!(active_key(&conn).unwrap().is_none());
+    assert!(open_keys(&conn, privkey.clone()).err().unwrap().to_string().contains("no key"));
+
+    let version = attach_key(&conn, &pubkey, &proof, &privkey).unwrap();
+    assert_eq!(attach_key(&conn, &pubkey, &proof, &privkey).unwrap(), version);
+    insert(&conn, &pubkey, version, 42);
+
+    let keys = open_keys(&conn, privkey).unwrap();
+    assert_eq!((keys.version, &keys.pubkey), (version, &pubkey));
+    let (c, _) = &rows(&conn)[0];
+    let (_, c) = parse_stored(c.into()).unwrap();
+    assert_eq!(paillier_decrypt(&keys.privkey, &keys.pubkey, &c), BigUint::from(42u32));
+}
+
+#[test]
+fn mismatched_keys_are_rejected() {
+    let (pubkey, privkey, proof) = key(128);
+    let (other_pubkey, other_privkey, other_proof) = key(128);
+    let conn = database();
+
+    assert!(check_new_key(&pubkey, &proof, &other_privkey).is_err());
+    assert!(check_new_key(&pubkey, &other_proof, &privkey).is_err());
+    assert!(attach_key(&conn, &pubkey, &proof, &other_privkey).is_err());
+    assert!(active_key(&conn).unwrap().is_none());
+
+    let version = attach_key(&conn, &pubkey, &proof, &privkey).unwrap();
+    let err = open_keys(&conn, other_privkey.clone()).err().unwrap().to_string();
+    assert!(err.contains("does not match") && err.contains(&format!("version {}", version)), "{}", err);
+
+    let err = attach_key(&conn, &other_pubkey, &other_proof, &other_privkey).unwrap_err().to_string();
+    assert!(err.contains("use rotate"), "{}", err);
+}
+
+#[test]
+fn rotation_moves_rows_and_resumes() {
+    let (old_pubkey, old_privkey, old_proof) = key(128);
+    let (new_pubkey, new_privkey, new_proof) = key(160);
+    let mut conn = database();
+    // A row from before keys were stored; it must not be touched.
+    insert(&conn, &old_pubkey, 1, 7);
+    let old_version = attach_key(&conn, &old_pubkey, &old_proof, &old_privkey).unwrap();
+    assert_eq!(old_version, 2);
+    for m in [10, 20, 30] {
+        insert(&conn, &old_pubkey, old_version, m);
+    }
+
+    let mut checkpoint = Checkpoint::default();
+    let version =
+        rotate_key(&mut conn, &old_privkey, &new_pubkey, &new_proof, None, 2, &mut checkpoint, None).unwrap();
+    assert_eq!((version, checkpoint.rows_done), (3, 3));
+    assert!(open_keys(&conn, old_privkey.clone()).is_err());
+    let keys = open_keys(&conn, new_privkey).unwrap();
+    assert_eq!(keys.version, version);
+
+    let decrypted: Vec<(u32, i64)> = rows(&conn)[1..]
+        .iter()
+        .map(|(c, v)| {
+            // Re-encrypted rows are stored as BLOBs recording the new key.
+            let (key_id, c) = parse_stored(c.into()).unwrap();
+            assert_eq!(key_id, Some(KeyId::of(&new_pubkey)));
+            (paillier_decrypt(&keys.privkey, &keys.pubkey, &c).try_into().unwrap(), *v)
+        })
+        .collect();
+    assert_eq!(decrypted, vec![(10, 3), (20, 3), (30, 3)]);
+    assert!(matches!(rows(&conn)[0], (Value::Text(_), 1)));
+
+    // Running the same rotation again finds nothing left to move.
+    let mut checkpoint = Checkpoint::default();
+    rotate_key(&mut conn, &old_privkey, &new_pubkey, &new_proof, None, 2, &mut checkpoint, None).unwrap();
+    assert_eq!(checkpoint.rows_done, 0);
+
+    // Rotating back to a retired key is refused.
+    let err =
+        rotate_key(&mut conn, &keys.privkey, &old_pubkey, &old_proof, None, 2, &mut checkpoint, None).unwrap_err();
+    assert!(err.to_string().contains("not newer"), "{}", err);
+    assert_eq!(public_key(&conn, old_version).unwrap(), Some(old_pubkey));
+}
+
+#[test]
+fn key_table_without_public_key_column_is_upgraded() {
+    let (pubkey, privkey, proof) = key(128);
+    let conn = database();
+    conn.execute_batch(
+        "CREATE TABLE paillier_keys (
+            version      INTEGER PRIMARY KEY,
+            key_id       TEXT NOT NULL UNIQUE,
+            modulus_bits INTEGER NOT NULL
+        );
+        INSERT INTO paillier_keys VALUES (1, '00000000000000000000000000000000', 128);",
+    )
+    .unwrap();
+    assert_eq!(public_key(&conn, 1).unwrap(), None);
+    assert_eq!(attach_key(&conn, &pubkey, &proof, &privkey).unwrap(), 2);
+    assert_eq!(active_key(&conn).unwrap(), Some((2, pubkey)));
+}
+
+#[test]
+fn reencryption_moves_only_the_old_version() {
+    let (old_pubkey, old_privkey, _) = key(128);
+    let (other_pubkey, _, _) = key(128);
+    let (new_pubkey, new_privkey, _) = key(160);
+    let mut conn = database();
+    // Rows from before keys were recorded, under two unrecorded versions.
+    insert(&conn, &old_pubkey, 1, 10);
+    insert(&conn, &other_pubkey, 2, 20);
+    insert(&conn, &old_pubkey, 1, 30);
+
+    let reencryptor = Reencryptor::new(&old_pubkey, &old_privkey, &new_pubkey).unwrap();
+    let err = reencrypt_table(&mut conn, &reencryptor, 3, 3, 10, &mut Checkpoint::default(), None).unwrap_err();
+    assert!(err.to_string().contains("both 3"), "{}", err);
+    let mut checkpoint = Checkpoint::default();
+    reencrypt_table(&mut conn, &reencryptor, 1, 3, 1, &mut checkpoint, None).unwrap();
+    assert_eq!(checkpoint.rows_done, 2);
+    assert_eq!(key_id(&conn, 1).unwrap(), Some(KeyId::of(&old_pubkey)));
+
+    let rows = rows(&conn);
+    assert_eq!(rows.iter().map(|(_, v)| *v).collect::<Vec<_>>(), [3, 2, 3]);
+    assert!(matches!(rows[1].0, Value::Text(_)));
+    for ((c, _), m) in [&rows[0], &rows[2]].into_iter().zip([10u32, 30]) {
+        let (_, c) = parse_stored(c.into()).unwrap();
+        assert_eq!(paillier_decrypt(&new_privkey, &new_pubkey, &c), BigUint::from(m));
+    }
+
+    // Version 1 is now recorded as the old key, so a different key is refused.
+    let (_, wrong_privkey, _) = key(128);
+    let reencryptor = Reencryptor::new(&other_pubkey, &wrong_privkey, &new_pubkey).unwrap();
+    let err = reencrypt_table(&mut conn, &reencryptor, 1, 3, 1, &mut Checkpoint::default(), None).unwrap_err();
+    assert!(err.to_string().contains("Key version 1 belongs to key"), "{}", err);
+}
+
+#[test]
+fn rotation_moves_declared_columns() {
+    let (old_pubkey, old_privkey, old_proof) = key(128);
+    let (new_pubkey, new_privkey, new_proof) = key(160);
+    let mut conn = database();
+    attach_key(&conn, &old_pubkey, &old_proof, &old_privkey).unwrap();
+    insert(&conn, &old_pubkey, 1, 5);
+    conn.execute_batch("CREATE TABLE emp (name TEXT, salary BLOB)").unwrap();
+    declare_encrypted_column(&conn, "emp", "salary", None).unwrap();
+    let old_codec = CiphertextCodec::new(&old_pubkey);
+    let salary = |m: u32| old_codec.encode(&paillier_encrypt(&old_pubkey, &BigUint::from(m)));
+    conn.execute("INSERT INTO emp VALUES ('ann', ?1), ('bob', NULL), ('cid', ?2)", params![salary(5000), salary(6000)])
+        .unwrap();
+    // A TEXT ciphertext from before BLOBs is taken to be under the old key.
+    let text = paillier_encrypt(&old_pubkey, &BigUint::from(4000u32)).to_str_radix(10);
+    conn.execute("INSERT INTO emp VALUES ('dan', ?1)", params![text]).unwrap();
+
+    let mut checkpoint = Checkpoint::default();
+    rotate_key(&mut conn, &old_privkey, &new_pubkey, &new_proof, None, 2, &mut checkpoint, None).unwrap();
+    let keys = open_keys(&conn, new_privkey).unwrap();
+    let new_codec = CiphertextCodec::new(&keys.pubkey);
+    let salaries = |conn: &Connection| -> Vec<Option<u32>> {
+        let mut stmt = conn.prepare("SELECT salary FROM emp ORDER BY rowid").unwrap();
+        let values = stmt.query_map([], |row| row.get::<_, Value>(0)).unwrap();
+        values
+            .map(|value| {
+                let c = new_codec.decode((&value.unwrap()).into()).unwrap()?;
+                Some(paillier_decrypt(&keys.privkey, &keys.pubkey, c.as_biguint()).try_into().unwrap())
+            })
+            .collect()
+    };
+    assert_eq!(salaries(&conn), [Some(5000), None, Some(6000), Some(4000)]);
+
+    // Running it again leaves the moved values alone, and a value under a
+    // third key is reported instead of being decrypted with the wrong key.
+    let mut checkpoint = Checkpoint::default();
+    rotate_key(&mut conn, &old_privkey, &new_pubkey, &new_proof, None, 2, &mut checkpoint, None).unwrap();
+    assert_eq!(salaries(&conn), [Some(5000), None, Some(6000), Some(4000)]);
+    let reencryptor = Reencryptor::new(&old_pubkey, &old_privkey, &new_pubkey).unwrap();
+    assert_eq!(reencrypt_columns(&mut conn, &reencryptor, None, 2).unwrap(), 0);
+    let (other_pubkey, _, _) = key(128);
+    let stray = CiphertextCodec::new(&other_pubkey).encode(&paillier_encrypt(&other_pubkey, &BigUint::from(1u32)));
+    conn.execute("INSERT INTO emp VALUES ('eve', ?1)", params![stray]).unwrap();
+    let err = reencrypt_columns(&mut conn, &reencryptor, None, 2).unwrap_err().to_string();
+    assert!(err.starts_with("emp.salary: row 5 is encrypted under key"), "{}", err);
+}
+
+#[test]
+fn rotation_of_tagged_columns_needs_the_new_private_key() {
+    let (old_pubkey, old_privkey, old_proof) = key(128);
+    let (new_pubkey, new_privkey, new_proof) = key(160);
+    let mut conn = database();
+    attach_key(&conn, &old_pubkey, &old_proof, &old_privkey).unwrap();
+    insert(&conn, &old_pubkey, 1, 5);
+    conn.execute_batch("CREATE TABLE emp (name TEXT, salary BLOB, salary_tag TEXT)").unwrap();
+    declare_encrypted_column(&conn, "emp", "salary", Some("salary_tag")).unwrap();
+    let salary = CiphertextCodec::new(&old_pubkey).encode(&paillier_encrypt(&old_pubkey, &BigUint::from(5000u32)));
+    conn.execute("INSERT INTO emp VALUES ('ann', ?1, 'old tag')", params![salary]).unwrap();
+    let before = rows(&conn);
+
+    // Without the new private key the tags cannot be recomputed, so nothing
+    // is changed: the key is not stored and no row is touched.
+    let mut checkpoint = Checkpoint::default();
+    let err = rotate_key(&mut conn, &old_privkey, &new_pubkey, &new_proof, None, 2, &mut checkpoint, None)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(err, "emp.salary has tags in salary_tag, which need the new private key to be recomputed");
+    assert_eq!(active_key(&conn).unwrap().unwrap().1, old_pubkey);
+    assert_eq!(rows(&conn), before);
+    let reencryptor = Reencryptor::new(&old_pubkey, &old_privkey, &new_pubkey).unwrap();
+    assert!(reencrypt_columns(&mut conn, &reencryptor, None, 2).is_err());
+    let stored: Value = conn.query_row("SELECT salary FROM emp", [], |row| row.get(0)).unwrap();
+    assert_eq!(stored, Value::Blob(salary));
+
+    // A new private key that does not belong to the new public key is refused
+    // too, since its tags would not match the client's.
+    let (_, wrong_privkey, _) = key(160);
+    let wrong = Some(&wrong_privkey);
+    assert!(rotate_key(&mut conn, &old_privkey, &new_pubkey, &new_proof, wrong, 2, &mut checkpoint, None).is_err());
+    assert_eq!(active_key(&conn).unwrap().unwrap().1, old_pubkey);
+
+    rotate_key(&mut conn, &old_privkey, &new_pubkey, &new_proof, Some(&new_privkey), 2, &mut checkpoint, None).unwrap();
+    let tag: String = conn.query_row("SELECT salary_tag FROM emp", [], |row| row.get(0)).unwrap();
+    assert_eq!(tag, TagKey::derive(&new_privkey).tag(&5000.into()));
+}
+
+#[test]
+fn rotation_keeps_negative_rows_negative() {
+    let (old_pubkey, old_privkey, old_proof) = key(128);
+    let (new_pubkey, new_privkey, new_proof) = key(160);
+    let mut conn = database();
+    let version = attach_key(&conn, &old_pubkey, &old_proof, &old_privkey).unwrap();
+    // As left by FHESUB or FHENEG: -40 is held as n_old - 40.
+    let c = paillier_encrypt(&old_pubkey, &encode_signed(&BigInt::from(-40), &old_pubkey.0));
+    conn.execute(
+        "INSERT INTO encrypted_table (ciphertext, key_version) VALUES (?1, ?2)",
+        params![c.to_str_radix(10), version],
+    )
+    .unwrap();
+
+    rotate_key(&mut conn, &old_privkey, &new_pubkey, &new_proof, None, 2, &mut Checkpoint::default(), None).unwrap();
+    let (_, c) = parse_stored((&rows(&conn)[0].0).into()).unwrap();
+    let m = paillier_decrypt(&new_privkey, &new_pubkey, &c);
+    assert_eq!(decode_signed(&m, &new_pubkey.0), BigInt::from(-40));
+
+    // A row that is not a valid ciphertext is reported, not decrypted.
+    conn.execute("INSERT INTO encrypted_table (ciphertext, key_version) VALUES ('0', ?1)", params![version + 1])
+        .unwrap();
+    let (newer_pubkey, _, newer_proof) = key(192);
+    let mut checkpoint = Checkpoint::default();
+    let err = rotate_key(&mut conn, &new_privkey, &newer_pubkey, &newer_proof, None, 2, &mut checkpoint, None)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(err, "Row 2: ciphertext is not a unit modulo n^2");
+}
@@ -0,0 +1,80 @@
+use fhesql::codec::{parse_stored, CiphertextCodec};
+use fhesql::functions::register_functions;
+use fhesql::keys::attach_key;
+use fhesql::metadata::key_id;
+use fhesql::migrate::migrate_to_blobs;
+use fhesql::schema::declare_encrypted_column;
+use num_bigint::{BigInt, BigUint};
+use paillier_rs::decrypt::paillier_decrypt;
+use paillier_rs::encrypt::paillier_encrypt;
+use paillier_rs::fixed_point::{decode_signed, encode_signed};
+use paillier_rs::keygen::{paillier_keygen_with_proof, PublicKey};
+use paillier_rs::keyid::KeyId;
+use rusqlite::types::Value;
+use rusqlite::{params, Connection};
+
+fn encrypt_text(pubkey: &PublicKey, m: i64) -> String {
+    paillier_encrypt(pubkey, &encode_signed(&BigInt::from(m), &pubkey.0)).to_str_radix(10)
+}
+
+fn column(conn: &Connection, sql: &str) -> Vec<Value> {
+    let mut stmt = conn.prepare(sql).unwrap();
+    let values = stmt.query_map([], |row| row.get(0)).unwrap();
+    values.collect::<Result<_, _>>().unwrap()
+}
+
+#[test]
+fn database_without_key_table_needs_its_key() {
+    let (pubkey, privkey, _) = paillier_keygen_with_proof(128);
+    let mut conn = Connection::open_in_memory().unwrap();
+    // The layout of the original example.db.
+    conn.execute_batch("CREATE TABLE encrypted_table (id INTEGER PRIMARY KEY, ciphertext TEXT NOT NULL)").unwrap();
+    for m in [10, 20, 30, 40, 50] {
+        conn.execute("INSERT INTO encrypted_table (ciphertext) VALUES (?1)", params![encrypt_text(&pubkey, m)])
+            .unwrap();
+    }
+
+    let err = migrate_to_blobs(&mut conn, Some(&pubkey), 0).unwrap_err().to_string();
+    assert_eq!(err, "Batch size must be positive");
+    let err = migrate_to_blobs(&mut conn, None, 2).unwrap_err().to_string();
+    assert!(err.contains("Key version 1 has no recorded key"), "{}", err);
+    assert_eq!(migrate_to_blobs(&mut conn, Some(&pubkey), 2).unwrap(), 5);
+    assert_eq!(key_id(&conn, 1).unwrap(), Some(KeyId::of(&pubkey)));
+
+    let decrypted: Vec<BigUint> = column(&conn, "SELECT ciphertext FROM encrypted_table ORDER BY id")
+        .iter()
+        .map(|c| {
+            let (found, c) = parse_stored(c.into()).unwrap();
+            assert_eq!(found, Some(KeyId::of(&pubkey)));
+            paillier_decrypt(&privkey, &pubkey, &c)
+        })
+        .collect();
+    assert_eq!(decrypted, [10u32, 20, 30, 40, 50].map(BigUint::from));
+
+    // Nothing is left to convert.
+    assert_eq!(migrate_to_blobs(&mut conn, None, 2).unwrap(), 0);
+}
+
+#[test]
+fn declared_columns_move_to_the_active_key() {
+    let (pubkey, privkey, proof) = paillier_keygen_with_proof(128);
+    let mut conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("CREATE TABLE emp (dept TEXT, salary)").unwrap();
+    declare_encrypted_column(&conn, "emp", "salary", None).unwrap();
+    let codec = CiphertextCodec::new(&pubkey);
+    let blob = codec.encode(&paillier_encrypt(&pubkey, &encode_signed(&BigInt::from(5), &pubkey.0)));
+    let insert = "INSERT INTO emp VALUES ('eng', ?1), ('eng', ?2), ('ops', NULL)";
+    conn.execute(insert, params![encrypt_text(&pubkey, -20), blob]).unwrap();
+
+    let err = migrate_to_blobs(&mut conn, None, 10).unwrap_err().to_string();
+    assert!(err.contains("Database has no key"), "{}", err);
+    attach_key(&conn, &pubkey, &proof, &privkey).unwrap();
+    assert_eq!(migrate_to_blobs(&mut conn, None, 10).unwrap(), 1);
+
+    let types = column(&conn, "SELECT typeof(salary) FROM emp ORDER BY rowid");
+    assert_eq!(types, ["blob", "blob", "null"].map(|t| Value::Text(t.into())));
+    register_functions(&conn, &pubkey).unwrap();
+    let sum: Value = conn.query_row("SELECT FHESUM(salary) FROM emp", [], |row| row.get(0)).unwrap();
+    let sum = codec.decode((&sum).into()).unwrap().unwrap();
+    assert_eq!(decode_signed(&paillier_decrypt(&privkey, &pubkey, sum.as_biguint()), &pubkey.0), (-15).into());
+}
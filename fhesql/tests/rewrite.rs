@@ -0,0 +1,240 @@
+use fhesql::rewrite::{rewrite, ColumnKind, Rewritten};
+use fhesql::schema::{declare_encrypted_column, Schema};
+use rusqlite::Connection;
+use std::error::Error;
+
+fn schema() -> Schema {
+    let mut schema = Schema::new();
+    schema.declare("emp", "salary");
+    schema.declare("EMP", "Bonus");
+    schema
+}
+
+/// `salary` of `emp` and of `grades` have tag columns, `bonus` has none.
+fn tagged_schema() -> Schema {
+    let mut schema = schema();
+    schema.declare_tagged("emp", "salary", "salary_tag");
+    schema.declare_tagged("grades", "salary", "salary_tag");
+    schema
+}
+
+/// Rewrites with stand-ins for encryption (the plaintext's bytes) and tags,
+/// so the output stays readable.
+fn rewrite_with(sql: &str, schema: &Schema) -> Result<Rewritten, Box<dyn Error>> {
+    rewrite(sql, schema, &mut |m| m.to_signed_bytes_be(), &|m| format!("tag({})", m))
+}
+
+fn rewritten(sql: &str) -> Rewritten {
+    rewrite_with(sql, &schema()).unwrap()
+}
+
+fn error(sql: &str) -> String {
+    rewrite_with(sql, &schema()).unwrap_err().to_string()
+}
+
+#[test]
+fn aggregates_and_arithmetic() {
+    let r = rewritten("SELECT dept, SUM(salary) FROM emp GROUP BY dept");
+    assert_eq!(r.sql, "SELECT dept, FHESUM(salary) AS \"SUM(salary)\" FROM emp GROUP BY dept");
+    assert!(r.returns_rows);
+    assert_eq!(r.columns, [ColumnKind::Plain, ColumnKind::Encrypted]);
+
+    let r = rewritten("SELECT AVG(e.salary) AS mean, COUNT(salary) AS n FROM emp AS e WHERE e.dept = 'eng'");
+    assert_eq!(r.sql, "SELECT FHEAVG(e.salary) AS mean, COUNT(salary) AS n FROM emp AS e WHERE e.dept = 'eng'");
+    assert_eq!(r.columns, [ColumnKind::Average, ColumnKind::Plain]);
+
+    let r = rewritten("SELECT salary + bonus, salary - 10, 3 * salary, -bonus, salary * weight FROM emp");
+    assert_eq!(
+        r.sql,
+        "SELECT FHEADD(salary, bonus) AS \"salary + bonus\", FHEADDCONST(salary, -(10)) AS \"salary - 10\", \
+         FHEMULCONST(salary, 3) AS \"3 * salary\", FHENEG(bonus) AS \"-bonus\", \
+         FHEMULCONST(salary, weight) AS \"salary * weight\" FROM emp"
+    );
+    assert_eq!(r.columns, [ColumnKind::Encrypted; 5]);
+
+    let r = rewritten("SELECT SUM(2 * salary - bonus) AS total FROM emp ORDER BY dept LIMIT 3");
+    assert_eq!(r.sql, "SELECT FHESUM(FHESUB(FHEMULCONST(salary, 2), bonus)) AS total FROM emp ORDER BY dept LIMIT 3");
+}
+
+#[test]
+fn inserts_and_updates_encrypt_values() {
+    let r = rewritten("INSERT INTO emp (dept, salary, bonus) VALUES ('eng', 120, -5), (?, ?, NULL)");
+    assert_eq!(r.sql, "INSERT INTO emp (dept, salary, bonus) VALUES ('eng', X'78', X'fb'), (?1, ?2, NULL)");
+    assert!(!r.returns_rows);
+    assert_eq!(r.encrypted_params, [2]);
+
+    let r = rewritten("UPDATE emp SET salary = salary + ?2, bonus = 7, dept = ?1 WHERE id = ?3");
+    assert_eq!(r.sql, "UPDATE emp SET salary = FHEADDCONST(salary, ?2), bonus = X'07', dept = ?1 WHERE id = ?3");
+    assert!(r.encrypted_params.is_empty());
+
+    let r = rewritten("UPDATE emp SET bonus = ? WHERE id = ?");
+    assert_eq!(r.sql, "UPDATE emp SET bonus = ?1 WHERE id = ?2");
+    assert_eq!(r.encrypted_params, [1]);
+}
+
+#[test]
+fn untouched_statements_pass_through() {
+    for sql in [
+        "SELECT name FROM dept WHERE id > 3 ORDER BY name",
+        "CREATE TABLE emp2 (id INTEGER, salary TEXT)",
+        "DELETE FROM emp WHERE dept = 'ops'",
+    ] {
+        assert_eq!(rewritten(sql).sql, sql);
+    }
+    assert_eq!(rewritten("SELECT salary FROM emp").columns, [ColumnKind::Encrypted]);
+}
+
+#[test]
+fn unsupported_operations_are_explained() {
+    let cases = [
+        ("SELECT dept FROM emp WHERE salary LIKE '1%'", "WHERE on encrypted column salary"),
+        ("SELECT dept FROM emp ORDER BY salary", "ORDER BY on encrypted column salary"),
+        ("SELECT SUM(salary) AS s FROM emp GROUP BY dept ORDER BY s", "ORDER BY on encrypted column s"),
+        ("SELECT salary FROM emp GROUP BY salary", "GROUP BY on encrypted column salary"),
+        ("SELECT dept FROM emp e WHERE e.bonus = 1", "WHERE on encrypted column e.bonus"),
+        ("SELECT DISTINCT salary FROM emp", "DISTINCT on encrypted columns"),
+        ("SELECT salary * bonus FROM emp", "Multiplying two encrypted values"),
+        ("SELECT salary / 2 FROM emp", "Operator / on encrypted values"),
+        ("SELECT MAX(salary) FROM emp", "MAX() on encrypted column salary"),
+        ("SELECT SUM(DISTINCT salary) FROM emp", "SUM with DISTINCT"),
+        ("SELECT AVG(salary) + 1 FROM emp", "The result of AVG"),
+        ("SELECT * FROM emp", "SELECT * is not supported"),
+        ("INSERT INTO emp VALUES (1, 'eng', 5)", "needs a column list"),
+        ("INSERT INTO emp (salary) VALUES ('abc')", "Value for encrypted column salary"),
+        ("UPDATE emp SET dept = salary", "SET dept on encrypted column salary"),
+        ("DELETE FROM emp WHERE bonus = 0", "WHERE on encrypted column bonus"),
+        ("SELECT dept FROM emp WHERE id IN (SELECT id FROM emp)", "Subqueries are not supported"),
+        ("SELECT 1; SELECT 2", "single SQL statement"),
+    ];
+    for (sql, expected) in cases {
+        let err = error(sql);
+        assert!(err.contains(expected), "{}: {}", sql, err);
+    }
+}
+
+#[test]
+fn equality_on_tagged_columns_compares_tags() {
+    let schema = tagged_schema();
+    let tagged = |sql| rewrite_with(sql, &schema).unwrap();
+
+    let r = tagged("SELECT dept FROM emp WHERE salary = 100 OR 7 = emp.salary");
+    assert_eq!(r.sql, "SELECT dept FROM emp WHERE salary_tag = 'tag(100)' OR 'tag(7)' = emp.salary_tag");
+
+    let r = tagged("SELECT dept FROM emp WHERE salary <> ? AND dept = ?");
+    assert_eq!(r.sql, "SELECT dept FROM emp WHERE salary_tag <> ?3 AND dept = ?2");
+    assert_eq!(r.tag_params, [(3, 1)]);
+
+    let r = tagged("SELECT salary, COUNT(*) AS n FROM emp WHERE salary IN (1, -2) GROUP BY salary");
+    assert_eq!(
+        r.sql,
+        "SELECT salary, COUNT(*) AS n FROM emp WHERE salary_tag IN ('tag(1)', 'tag(-2)') GROUP BY salary_tag"
+    );
+    assert_eq!(r.columns, [ColumnKind::Encrypted, ColumnKind::Plain]);
+
+    let r = tagged("SELECT e.dept, g.grade FROM emp AS e JOIN grades AS g ON e.salary = g.salary");
+    assert_eq!(r.sql, "SELECT e.dept, g.grade FROM emp AS e JOIN grades AS g ON e.salary_tag = g.salary_tag");
+
+    let r = tagged("DELETE FROM emp WHERE salary = ?");
+    assert_eq!(r.sql, "DELETE FROM emp WHERE salary_tag = ?2");
+    assert_eq!(r.tag_params, [(2, 1)]);
+}
+
+#[test]
+fn writes_to_tagged_columns_store_tags() {
+    let schema = tagged_schema();
+    let tagged = |sql| rewrite_with(sql, &schema).unwrap();
+
+    let r = tagged("INSERT INTO emp (dept, salary, bonus) VALUES ('eng', 120, ?), ('ops', ?, NULL), ('hr', NULL, 1)");
+    assert_eq!(
+        r.sql,
+        "INSERT INTO emp (dept, salary, bonus, salary_tag) VALUES ('eng', X'78', ?1, 'tag(120)'), \
+         ('ops', ?2, NULL, ?3), ('hr', NULL, X'01', NULL)"
+    );
+    assert_eq!(r.encrypted_params, [1, 2]);
+    assert_eq!(r.tag_params, [(3, 2)]);
+
+    let r = tagged("UPDATE emp SET salary = ?, bonus = bonus + 1 WHERE salary = 5");
+    assert_eq!(
+        r.sql,
+        "UPDATE emp SET salary = ?1, bonus = FHEADDCONST(bonus, 1), salary_tag = ?2 WHERE salary_tag = 'tag(5)'"
+    );
+    assert_eq!(r.encrypted_params, [1]);
+    assert_eq!(r.tag_params, [(2, 1)]);
+
+    for (sql, expected) in [
+        ("UPDATE emp SET salary = salary + 1", "Value for tagged column salary must be"),
+        ("INSERT INTO emp (salary, salary_tag) VALUES (1, 'x')", "salary_tag holds the tags of salary"),
+        ("UPDATE emp SET salary_tag = NULL", "salary_tag holds the tags of salary"),
+        ("INSERT INTO emp (salary) SELECT salary FROM grades", "INSERT ... SELECT into tagged column salary"),
+        ("SELECT dept FROM emp WHERE salary LIKE '5'", "WHERE on encrypted column salary"),
+        ("SELECT dept FROM emp WHERE salary = bonus", "WHERE on encrypted column"),
+        ("SELECT dept FROM emp GROUP BY bonus", "GROUP BY on encrypted column bonus"),
+    ] {
+        let err = rewrite_with(sql, &schema).unwrap_err().to_string();
+        assert!(err.contains(expected), "{}: {}", sql, err);
+    }
+}
+
+#[test]
+fn range_predicates_become_comparisons() {
+    let r = rewritten("SELECT dept FROM emp WHERE salary > 50000 AND dept = ?");
+    assert_eq!(r.sql, "SELECT dept FROM emp WHERE FHECMP(0, emp.rowid) AND dept = ?1");
+    assert_eq!(
+        r.comparisons,
+        ["SELECT emp.rowid, FHEADDCONST(FHEMULCONST(FHEADDCONST(salary, -(50000)), 2), -1) FROM emp"]
+    );
+
+    let r = rewritten("SELECT e.dept FROM emp AS e WHERE ? <= e.salary OR e.salary < e.bonus");
+    assert_eq!(r.sql, "SELECT e.dept FROM emp AS e WHERE FHECMP(0, e.rowid) OR FHECMP(1, e.rowid)");
+    assert_eq!(
+        r.comparisons,
+        [
+            "SELECT e.rowid, FHEADDCONST(FHEMULCONST(FHEADDCONST(FHENEG(e.salary), ?1), -2), 1) FROM emp AS e",
+            "SELECT e.rowid, FHEADDCONST(FHEMULCONST(FHESUB(e.salary, e.bonus), -2), -1) FROM emp AS e",
+        ]
+    );
+
+    let r = rewritten("DELETE FROM emp WHERE salary NOT BETWEEN 10 AND ?");
+    assert_eq!(r.sql, "DELETE FROM emp WHERE NOT (FHECMP(0, emp.rowid) AND FHECMP(1, emp.rowid))");
+    assert_eq!(r.comparisons.len(), 2);
+
+    let r = rewritten("UPDATE emp SET dept = 'top' WHERE 2 * salary >= 100");
+    assert_eq!(r.sql, "UPDATE emp SET dept = 'top' WHERE FHECMP(0, emp.rowid)");
+    assert_eq!(
+        r.comparisons,
+        ["SELECT emp.rowid, FHEADDCONST(FHEMULCONST(FHEADDCONST(FHEMULCONST(salary, 2), -(100)), 2), 1) FROM emp"]
+    );
+
+    // Comparisons of plaintext values are left to SQLite.
+    let r = rewritten("SELECT dept FROM emp WHERE id BETWEEN 1 AND 5 AND id > 0");
+    assert_eq!(r.sql, "SELECT dept FROM emp WHERE id BETWEEN 1 AND 5 AND id > 0");
+    assert!(r.comparisons.is_empty());
+
+    for (sql, expected) in [
+        ("SELECT e.dept FROM emp e, emp f WHERE e.salary > 1", "only supported on a single table"),
+        ("SELECT dept FROM emp GROUP BY dept HAVING SUM(salary) > 10", "HAVING on encrypted column salary"),
+        ("SELECT dept FROM emp WHERE salary * bonus > 10", "Multiplying two encrypted values"),
+        ("SELECT dept FROM emp ORDER BY salary > 10", "ORDER BY on encrypted column salary"),
+    ] {
+        let err = error(sql);
+        assert!(err.contains(expected), "{}: {}", sql, err);
+    }
+}
+
+#[test]
+fn schema_is_stored_in_the_database() {
+    let conn = Connection::open_in_memory().unwrap();
+    declare_encrypted_column(&conn, "Emp", "SALARY", None).unwrap();
+    declare_encrypted_column(&conn, "emp", "salary", None).unwrap();
+    let schema = Schema::load(&conn).unwrap();
+    assert!(schema.is_encrypted("EMP", "Salary"));
+    assert!(!schema.is_encrypted("emp", "dept"));
+    assert!(!schema.has_encrypted_columns("dept"));
+    assert_eq!(schema.tag_column("emp", "salary"), None);
+
+    declare_encrypted_column(&conn, "emp", "salary", Some("Salary_Tag")).unwrap();
+    declare_encrypted_column(&conn, "emp", "salary", None).unwrap();
+    let schema = Schema::load(&conn).unwrap();
+    assert_eq!(schema.tag_column("EMP", "salary"), Some("salary_tag"));
+    assert_eq!(schema.tagged_by("emp", "SALARY_TAG"), Some("salary"));
+}
@@ -0,0 +1,124 @@
+use fhesql::codec::CiphertextCodec;
+use fhesql::functions::register_functions;
+use fhesql::keys::{attach_key, open_keys, DatabaseKeys};
+use fhesql::schema::{declare_encrypted_column, Schema};
+use fhesql::transfer::{export, import, Format};
+use paillier_rs::decrypt::paillier_decrypt;
+use paillier_rs::fixed_point::{decode_f64, decode_signed};
+use paillier_rs::keygen::paillier_keygen_with_proof;
+use rusqlite::types::Value;
+use rusqlite::Connection;
+
+fn database() -> (Connection, DatabaseKeys) {
+    let (pubkey, privkey, proof) = paillier_keygen_with_proof(128);
+    let conn = Connection::open_in_memory().unwrap();
+    attach_key(&conn, &pubkey, &proof, &privkey).unwrap();
+    let keys = open_keys(&conn, privkey).unwrap();
+    (conn, keys)
+}
+
+/// Decrypts the value of `sql`, a single ciphertext.
+fn decrypt_query(conn: &Connection, keys: &DatabaseKeys, sql: &str) -> num_bigint::BigUint {
+    register_functions(conn, &keys.pubkey).unwrap();
+    let value: Value = conn.query_row(sql, [], |row| row.get(0)).unwrap();
+    let c = CiphertextCodec::new(&keys.pubkey).decode((&value).into()).unwrap().unwrap();
+    paillier_decrypt(&keys.privkey, &keys.pubkey, c.as_biguint())
+}
+
+fn export_string(conn: &Connection, keys: Option<&DatabaseKeys>, scale: Option<u64>, format: Format) -> String {
+    let mut out = Vec::new();
+    export(conn, "emp", keys, scale, &mut out, format, 2).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn csv_round_trip() {
+    let (mut conn, keys) = database();
+    let csv = "name,dept,salary,bonus\nann,eng,5000,-200\nbob,ops,4000,\ncid,eng,6000,300\n";
+    let imported = import(&mut conn, "emp", csv.as_bytes(), Format::Csv, &["salary", "Bonus"], None, 2).unwrap();
+    assert_eq!(imported, 3);
+
+    let schema = Schema::load(&conn).unwrap();
+    assert!(schema.is_encrypted("emp", "salary") && schema.is_encrypted("emp", "bonus"));
+    assert!(!schema.is_encrypted("emp", "dept"));
+    let types: String = conn
+        .query_row("SELECT group_concat(typeof(salary) || '/' || typeof(bonus), ' ') FROM emp", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(types, "blob/blob blob/null blob/blob");
+    let sum = decrypt_query(&conn, &keys, "SELECT FHESUM(salary) FROM emp WHERE dept = 'eng'");
+    assert_eq!(sum, 11000u32.into());
+    let bonus = decrypt_query(&conn, &keys, "SELECT FHESUM(bonus) FROM emp");
+    assert_eq!(decode_signed(&bonus, &keys.pubkey.0), 100.into());
+
+    assert_eq!(export_string(&conn, Some(&keys), None, Format::Csv), csv);
+    // Without the key, ciphertexts are written as hex.
+    let hidden = export_string(&conn, None, None, Format::Csv);
+    assert!(hidden.starts_with("name,dept,salary,bonus\nann,eng,01"), "{}", hidden);
+    assert!(!hidden.contains("5000"), "{}", hidden);
+}
+
+#[test]
+fn json_fixed_point_round_trip() {
+    let (mut conn, keys) = database();
+    let json = "{\"item\":\"tea\",\"price\":2.5,\"qty\":3}\n\
+                {\"item\":\"cake\",\"price\":-0.25,\"qty\":1}\n\
+                {\"item\":\"water\",\"price\":null,\"qty\":2}\n";
+    assert_eq!(import(&mut conn, "emp", json.as_bytes(), Format::Json, &["price"], Some(100), 10).unwrap(), 3);
+
+    let sum = decrypt_query(&conn, &keys, "SELECT FHESUM(price) FROM emp");
+    assert_eq!(decode_f64(&sum, 100, &keys.pubkey.0), 2.25);
+    let qty: i64 = conn.query_row("SELECT sum(qty) FROM emp", [], |row| row.get(0)).unwrap();
+    assert_eq!(qty, 6);
+    assert_eq!(export_string(&conn, Some(&keys), Some(100), Format::Json), json);
+
+    // Rows appended later follow the columns declared by the first import.
+    let more = "{\"item\":\"soup\",\"price\":4}\n";
+    assert_eq!(import(&mut conn, "emp", more.as_bytes(), Format::Json, &[], Some(100), 10).unwrap(), 1);
+    let sum = decrypt_query(&conn, &keys, "SELECT FHESUM(price) FROM emp");
+    assert_eq!(decode_f64(&sum, 100, &keys.pubkey.0), 6.25);
+}
+
+#[test]
+fn invalid_imports_are_rejected() {
+    let (mut conn, _) = database();
+    let import_csv = |conn: &mut Connection, csv: &str, encrypt: &[&str]| {
+        import(conn, "emp", csv.as_bytes(), Format::Csv, encrypt, None, 10).unwrap_err().to_string()
+    };
+
+    let err = import_csv(&mut conn, "name,salary\nann,5000\n", &["bonus"]);
+    assert_eq!(err, "No column bonus in the input");
+    let err = import_csv(&mut conn, "name,salary\nann,5000\nbob,4000.5\n", &["salary"]);
+    assert_eq!(err, "record 2: salary: 4000.5 is not an integer; pass a scale");
+    let err = import_csv(&mut conn, "name,salary\nann,lots\n", &["salary"]);
+    assert_eq!(err, "record 1: salary: \"lots\" is not a number");
+    // Finite, but infinite once scaled.
+    let err = import(&mut conn, "emp", "name,salary\nann,1e308\n".as_bytes(), Format::Csv, &["salary"], Some(100), 10)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(err, "record 1: salary: 1e308 is out of range");
+
+    // The table was created by the import that failed on its records.
+    let err = import_csv(&mut conn, "name,age\nann,30\n", &[]);
+    assert_eq!(err, "No column age in table emp");
+    conn.execute_batch("ALTER TABLE emp ADD COLUMN salary_tag TEXT").unwrap();
+    declare_encrypted_column(&conn, "emp", "salary", Some("salary_tag")).unwrap();
+    let err = import_csv(&mut conn, "name,salary\ncid,6000\n", &[]);
+    assert_eq!(err, "emp.salary has tags in salary_tag, which need the private key");
+
+    let json = "{\"name\":\"ann\"}\n{\"name\":\"bob\",\"age\":30}\n";
+    let err = import(&mut conn, "emp", json.as_bytes(), Format::Json, &[], None, 10).unwrap_err().to_string();
+    assert_eq!(err, "record 2: unknown column age");
+}
+
+#[test]
+fn zero_batch_size_moves_every_row() {
+    let (mut conn, keys) = database();
+    let csv = "name,salary\nann,5000\nbob,4000\ncid,6000\n";
+    assert_eq!(import(&mut conn, "emp", csv.as_bytes(), Format::Csv, &["salary"], None, 0).unwrap(), 3);
+    let count: i64 = conn.query_row("SELECT count(*) FROM emp", [], |row| row.get(0)).unwrap();
+    assert_eq!(count, 3);
+
+    let mut out = Vec::new();
+    assert_eq!(export(&conn, "emp", Some(&keys), None, &mut out, Format::Csv, 0).unwrap(), 3);
+    assert_eq!(String::from_utf8(out).unwrap(), csv);
+}
@@ -0,0 +1,166 @@
+//! Criterion benchmarks for the Paillier operations across modulus sizes.
+//!
+//! Run with `cargo bench -p paillier_rs`. To compare two commits, save a
+//! baseline on the first and compare against it on the second:
+//!
+//! ```text
+//! cargo bench -p paillier_rs -- --save-baseline before
+//! git checkout <other commit>
+//! cargo bench -p paillier_rs -- --baseline before
+//! ```
+//!
+//! Criterion prints the change per benchmark and writes HTML reports to
+//! `target/criterion/report/index.html`. Modulus sizes default to 512, 1024,
+//! 2048 and 4096 bits; set `PAILLIER_BENCH_BITS=512,1024` to run a subset.
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use num_bigint::{BigInt, BigUint};
+use paillier_rs::arithmetic::{paillier_add, paillier_scalar_mul};
+use paillier_rs::decrypt::paillier_decrypt;
+use paillier_rs::encrypt::paillier_encrypt;
+use paillier_rs::gcd::{extended_gcd_int, extended_gcd_lehmer, modinv};
+use paillier_rs::keygen::{paillier_keygen, PrivateKey, PublicKey};
+use rand::Rng;
+use std::hint::black_box;
+use std::sync::OnceLock;
+
+const DEFAULT_MODULUS_BITS: [usize; 4] = [512, 1024, 2048, 4096];
+
+/// Ciphertexts per batch benchmark.
+const BATCH: usize = 16;
+
+/// Length of an MNIST image, and so of the dot products in `mnist/`.
+const MNIST_INPUT: usize = 28 * 28;
+
+fn modulus_bits() -> Vec<usize> {
+    match std::env::var("PAILLIER_BENCH_BITS") {
+        Ok(list) => list
+            .split(',')
+            .map(|b| b.trim().parse().expect("PAILLIER_BENCH_BITS must list integers"))
+            .collect(),
+        Err(_) => DEFAULT_MODULUS_BITS.to_vec(),
+    }
+}
+
+/// One key pair per modulus size, shared by all benchmarks except keygen.
+fn keys() -> &'static [(usize, PublicKey, PrivateKey)] {
+    static KEYS: OnceLock<Vec<(usize, PublicKey, PrivateKey)>> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        modulus_bits()
+            .into_iter()
+            .map(|bits| {
+                let (pubkey, privkey) = paillier_keygen(bits / 2);
+                (bits, pubkey, privkey)
+            })
+            .collect()
+    })
+}
+
+fn random_plaintexts(count: usize, max: u32) -> Vec<BigUint> {
+    let mut rng = rand::thread_rng();
+    (0..count).map(|_| BigUint::from(rng.gen_range(0..max))).collect()
+}
+
+fn bench_keygen(c: &mut Criterion) {
+    let mut group = c.benchmark_group("keygen");
+    group.sample_size(10);
+    for bits in modulus_bits() {
+        group.bench_with_input(BenchmarkId::from_parameter(bits), &bits, |b, &bits| {
+            b.iter(|| paillier_keygen(black_box(bits / 2)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_single(c: &mut Criterion) {
+    let mut group = c.benchmark_group("single");
+    group.sample_size(10);
+    for (bits, pubkey, privkey) in keys() {
+        let m = BigUint::from(123_456u32);
+        let c1 = paillier_encrypt(pubkey, &m);
+        let c2 = paillier_encrypt(pubkey, &m);
+        let k = BigUint::from(1_000u32);
+        group.bench_with_input(BenchmarkId::new("encrypt", bits), &m, |b, m| {
+            b.iter(|| paillier_encrypt(pubkey, black_box(m)))
+        });
+        group.bench_with_input(BenchmarkId::new("decrypt", bits), &c1, |b, c| {
+            b.iter(|| paillier_decrypt(privkey, pubkey, black_box(c)))
+        });
+        group.bench_with_input(BenchmarkId::new("add", bits), &(&c1, &c2), |b, (c1, c2)| {
+            b.iter(|| paillier_add(black_box(c1), black_box(c2), pubkey))
+        });
+        group.bench_with_input(BenchmarkId::new("scalar_mul", bits), &c1, |b, c| {
+            b.iter(|| paillier_scalar_mul(black_box(c), &k, pubkey))
+        });
+    }
+    group.finish();
+}
+
+fn bench_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(BATCH as u64));
+    for (bits, pubkey, privkey) in keys() {
+        let plaintexts = random_plaintexts(BATCH, u32::MAX);
+        let ciphertexts: Vec<BigUint> = plaintexts.iter().map(|m| paillier_encrypt(pubkey, m)).collect();
+        group.bench_with_input(BenchmarkId::new("encrypt", bits), &plaintexts, |b, ms| {
+            b.iter(|| ms.iter().map(|m| paillier_encrypt(pubkey, m)).collect::<Vec<_>>())
+        });
+        group.bench_with_input(BenchmarkId::new("decrypt", bits), &ciphertexts, |b, cs| {
+            b.iter(|| cs.iter().map(|c| paillier_decrypt(privkey, pubkey, c)).collect::<Vec<_>>())
+        });
+        group.bench_with_input(BenchmarkId::new("add", bits), &ciphertexts, |b, cs| {
+            b.iter_batched(
+                || cs[0].clone(),
+                |acc| cs[1..].iter().fold(acc, |acc, c| paillier_add(&acc, c, pubkey)),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// \(\mu = \varphi(n)^{-1} \mod n\) with each extended GCD, as computed by keygen.
+fn bench_modinv(c: &mut Criterion) {
+    let mut group = c.benchmark_group("modinv");
+    for (bits, pubkey, privkey) in keys() {
+        let (n, phi) = (&pubkey.0, &privkey.0);
+        let (n_int, phi_int) = (BigInt::from(n.clone()), BigInt::from(phi.clone()));
+        group.bench_with_input(BenchmarkId::new("euclid", bits), &(&phi_int, &n_int), |b, (phi, n)| {
+            b.iter(|| extended_gcd_int(black_box(phi), n))
+        });
+        group.bench_with_input(BenchmarkId::new("lehmer", bits), &(phi, n), |b, (phi, n)| {
+            b.iter(|| extended_gcd_lehmer(black_box(phi), n))
+        });
+        group.bench_with_input(BenchmarkId::new("modinv", bits), &(phi, n), |b, (phi, n)| {
+            b.iter(|| modinv(black_box(phi), n))
+        });
+    }
+    group.finish();
+}
+
+/// Encrypted pixels times plaintext weights, summed: one class score of the
+/// homomorphic MNIST classifier in `mnist/`.
+fn bench_mnist_dot(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mnist_dot");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(MNIST_INPUT as u64));
+    for (bits, pubkey, _) in keys() {
+        let pixels: Vec<BigUint> = random_plaintexts(MNIST_INPUT, 256)
+            .iter()
+            .map(|px| paillier_encrypt(pubkey, px))
+            .collect();
+        let weights = random_plaintexts(MNIST_INPUT, 1_000);
+        let bias = paillier_encrypt(pubkey, &BigUint::from(0u32));
+        group.bench_with_input(BenchmarkId::from_parameter(bits), &(&pixels, &weights), |b, (pixels, weights)| {
+            b.iter(|| {
+                pixels.iter().zip(weights.iter()).fold(bias.clone(), |acc, (px, w)| {
+                    paillier_add(&acc, &paillier_scalar_mul(px, w, pubkey), pubkey)
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_keygen, bench_single, bench_batch, bench_modinv, bench_mnist_dot);
+criterion_main!(benches);
@@ -1,8 +1,16 @@
-use crate::decrypt::paillier_decrypt;
+use crate::backend::BigNumBackend;
+use crate::decrypt::{check_ciphertext, paillier_decrypt};
+use crate::error::PaillierError;
+#[cfg(feature = "std")]
 use crate::encrypt::paillier_encrypt; // Assumes an encryption function is provided.
-use crate::keygen::{PublicKey, PrivateKey};
-use num_bigint::{BigInt, BigUint, ToBigInt};
+use crate::encrypt::sample_randomness_with_rng;
+use crate::fixed_point::decode_signed;
+use crate::keygen::{check_key_pair, PublicKey, PrivateKey};
+use num_bigint::{BigInt, BigUint};
 use num_traits::One;
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "std")]
+use rand::thread_rng;
 
 /// Homomorphic addition of two ciphertexts.
 /// Given ciphertexts `c1` and `c2`, returns the ciphertext corresponding to
@@ -38,6 +46,57 @@ pub fn paillier_subtract(c1: &BigUint, c2: &BigUint, pubkey: &PublicKey) -> BigU
     (c1 * c2_inv) % n_sq
 }
 
+/// Re-randomizes a ciphertext by multiplying it with a fresh encryption of
+/// zero, \(r^n \mod n^2\). The plaintext is unchanged, but the result cannot
+/// be linked to `c`.
+#[cfg(feature = "std")]
+pub fn paillier_rerandomize(c: &BigUint, pubkey: &PublicKey) -> BigUint {
+    paillier_rerandomize_with_rng(c, pubkey, &mut thread_rng())
+}
+
+/// Like [`paillier_rerandomize`], drawing \(r\) from `rng`.
+pub fn paillier_rerandomize_with_rng<R: RngCore + CryptoRng + ?Sized>(c: &BigUint, pubkey: &PublicKey, rng: &mut R) -> BigUint {
+    let (n, _) = pubkey;
+    let n_sq = n * n;
+    let r = sample_randomness_with_rng(n, rng);
+    (c * r.modpow(n, &n_sq)) % n_sq
+}
+
+/// [`paillier_add`] with the modular arithmetic done by `backend`.
+pub fn paillier_add_with_backend<B: BigNumBackend + ?Sized>(
+    backend: &B,
+    c1: &BigUint,
+    c2: &BigUint,
+    pubkey: &PublicKey,
+) -> BigUint {
+    let (n, _) = pubkey;
+    backend.mul_mod(c1, c2, &(n * n))
+}
+
+/// [`paillier_scalar_mul`] with the modular arithmetic done by `backend`.
+pub fn paillier_scalar_mul_with_backend<B: BigNumBackend + ?Sized>(
+    backend: &B,
+    c: &BigUint,
+    k: &BigUint,
+    pubkey: &PublicKey,
+) -> BigUint {
+    let (n, _) = pubkey;
+    backend.pow_mod(c, k, &(n * n))
+}
+
+/// [`paillier_subtract`] with the modular arithmetic done by `backend`.
+pub fn paillier_subtract_with_backend<B: BigNumBackend + ?Sized>(
+    backend: &B,
+    c1: &BigUint,
+    c2: &BigUint,
+    pubkey: &PublicKey,
+) -> BigUint {
+    let (n, _) = pubkey;
+    let n_sq = n * n;
+    let c2_inv = backend.pow_mod(c2, &(n - BigUint::one()), &n_sq);
+    backend.mul_mod(c1, &c2_inv, &n_sq)
+}
+
 /// Convenience function that computes the difference of two ciphertexts,
 /// decrypts it, and converts the result into a signed integer.
 /// 
@@ -52,12 +111,7 @@ pub fn paillier_difference(
 ) -> BigInt {
     let diff_cipher = paillier_subtract(c1, c2, pubkey);
     let diff_mod = paillier_decrypt(privkey, pubkey, &diff_cipher);
-    let half_n = &pubkey.0 >> 1;
-    if diff_mod > half_n {
-        diff_mod.to_bigint().unwrap() - pubkey.0.to_bigint().unwrap()
-    } else {
-        diff_mod.to_bigint().unwrap()
-    }
+    decode_signed(&diff_mod, &pubkey.0)
 }
 
 /// Secure comparison of two encrypted values without decrypting the full difference.
@@ -74,6 +128,7 @@ pub fn paillier_difference(
 /// then \(m-n\) is negative (i.e. \(m < n\)); otherwise, it is nonnegative.
 /// 
 /// Returns `true` if \(m < n\) (i.e. the sign of \(m-n\) is negative), and `false` otherwise.
+#[cfg(feature = "std")]
 pub fn paillier_compare(
     c1: &BigUint,
     c2: &BigUint,
@@ -85,7 +140,7 @@ pub fn paillier_compare(
     let c_diff = paillier_subtract(c1, c2, pubkey);
 
     // Step 2: Encrypt the random mask r to obtain Enc(r)
-    let c_r = paillier_encrypt(&pubkey, r);
+    let c_r = paillier_encrypt(pubkey, r);
 
     // Step 3: Compute the masked ciphertext Enc((m - n) + r)
     let c_masked = paillier_add(&c_diff, &c_r, pubkey);
@@ -97,3 +152,57 @@ pub fn paillier_compare(
     // If masked_value < r, then m - n is negative (i.e. m < n).
     masked_value < *r
 }
+
+/// Fallible version of [`paillier_add`]; both operands must be valid
+/// ciphertexts under `pubkey`.
+pub fn try_paillier_add(c1: &BigUint, c2: &BigUint, pubkey: &PublicKey) -> Result<BigUint, PaillierError> {
+    check_ciphertext(c1, pubkey)?;
+    check_ciphertext(c2, pubkey)?;
+    Ok(paillier_add(c1, c2, pubkey))
+}
+
+/// Fallible version of [`paillier_scalar_mul`].
+pub fn try_paillier_scalar_mul(c: &BigUint, k: &BigUint, pubkey: &PublicKey) -> Result<BigUint, PaillierError> {
+    check_ciphertext(c, pubkey)?;
+    Ok(paillier_scalar_mul(c, k, pubkey))
+}
+
+/// Fallible version of [`paillier_subtract`].
+pub fn try_paillier_subtract(c1: &BigUint, c2: &BigUint, pubkey: &PublicKey) -> Result<BigUint, PaillierError> {
+    check_ciphertext(c1, pubkey)?;
+    check_ciphertext(c2, pubkey)?;
+    Ok(paillier_subtract(c1, c2, pubkey))
+}
+
+/// Fallible version of [`paillier_difference`] that also checks that the
+/// private key belongs to `pubkey`.
+pub fn try_paillier_difference(
+    c1: &BigUint,
+    c2: &BigUint,
+    pubkey: &PublicKey,
+    privkey: &PrivateKey,
+) -> Result<BigInt, PaillierError> {
+    check_key_pair(pubkey, privkey)?;
+    check_ciphertext(c1, pubkey)?;
+    check_ciphertext(c2, pubkey)?;
+    Ok(paillier_difference(c1, c2, pubkey, privkey))
+}
+
+/// Fallible version of [`paillier_compare`]. The mask `r` must be a valid
+/// plaintext, i.e. smaller than n.
+#[cfg(feature = "std")]
+pub fn try_paillier_compare(
+    c1: &BigUint,
+    c2: &BigUint,
+    pubkey: &PublicKey,
+    privkey: &PrivateKey,
+    r: &BigUint,
+) -> Result<bool, PaillierError> {
+    check_key_pair(pubkey, privkey)?;
+    check_ciphertext(c1, pubkey)?;
+    check_ciphertext(c2, pubkey)?;
+    if *r >= pubkey.0 {
+        return Err(PaillierError::PlaintextOutOfRange);
+    }
+    Ok(paillier_compare(c1, c2, pubkey, privkey, r))
+}
@@ -0,0 +1,132 @@
+use num_bigint::BigUint;
+use num_traits::One;
+
+/// Modular arithmetic used by the Paillier operations.
+///
+/// Decryption and scalar multiplication spend almost all of their time in
+/// modular exponentiation, so swapping the backend is how the SP1 guest gets
+/// its precompiles into the hot path. The `*_with_backend` functions in
+/// [`decrypt`](crate::decrypt) and [`arithmetic`](crate::arithmetic) take a
+/// backend; the plain functions always use [`SoftwareBackend`].
+pub trait BigNumBackend {
+    /// Computes \(a \cdot b \mod m\).
+    fn mul_mod(&self, a: &BigUint, b: &BigUint, m: &BigUint) -> BigUint;
+
+    /// Computes \(base^{exp} \mod m\) by left-to-right square-and-multiply on
+    /// top of [`mul_mod`](BigNumBackend::mul_mod).
+    fn pow_mod(&self, base: &BigUint, exp: &BigUint, m: &BigUint) -> BigUint {
+        square_and_multiply(self, base, exp, m)
+    }
+}
+
+fn square_and_multiply<B: BigNumBackend + ?Sized>(backend: &B, base: &BigUint, exp: &BigUint, m: &BigUint) -> BigUint {
+    let base = base % m;
+    let mut acc = BigUint::one() % m;
+    for i in (0..exp.bits()).rev() {
+        acc = backend.mul_mod(&acc, &acc, m);
+        if exp.bit(i) {
+            acc = backend.mul_mod(&acc, &base, m);
+        }
+    }
+    acc
+}
+
+/// Pure `num-bigint` backend.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SoftwareBackend;
+
+impl BigNumBackend for SoftwareBackend {
+    fn mul_mod(&self, a: &BigUint, b: &BigUint, m: &BigUint) -> BigUint {
+        (a * b) % m
+    }
+
+    fn pow_mod(&self, base: &BigUint, exp: &BigUint, m: &BigUint) -> BigUint {
+        base.modpow(exp, m)
+    }
+}
+
+/// Backend that routes modular multiplication through the SP1 zkVM's
+/// `uint256_mulmod` precompile.
+///
+/// The precompile works on 256-bit operands, so it is used whenever the
+/// modulus fits in 256 bits, which covers \(n^2\) for 64-bit primes. Larger
+/// moduli, and every build outside the zkVM (`target_os = "zkvm"` with the
+/// `sp1` feature), fall back to [`SoftwareBackend`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sp1Backend;
+
+impl Sp1Backend {
+    /// Largest modulus size handled by the precompile.
+    pub const PRECOMPILE_BITS: u64 = 256;
+
+    /// Returns true if operations modulo `m` use the precompile.
+    pub fn accelerates(m: &BigUint) -> bool {
+        cfg!(all(target_os = "zkvm", feature = "sp1")) && m.bits() <= Self::PRECOMPILE_BITS
+    }
+}
+
+impl BigNumBackend for Sp1Backend {
+    fn mul_mod(&self, a: &BigUint, b: &BigUint, m: &BigUint) -> BigUint {
+        if Self::accelerates(m) {
+            precompile::mul_mod_256(a, b, m)
+        } else {
+            SoftwareBackend.mul_mod(a, b, m)
+        }
+    }
+
+    fn pow_mod(&self, base: &BigUint, exp: &BigUint, m: &BigUint) -> BigUint {
+        if Self::accelerates(m) {
+            square_and_multiply(self, base, exp, m)
+        } else {
+            SoftwareBackend.pow_mod(base, exp, m)
+        }
+    }
+}
+
+#[cfg(all(target_os = "zkvm", feature = "sp1"))]
+mod precompile {
+    use num_bigint::BigUint;
+
+    const LIMBS: usize = 8;
+
+    // Defined by `sp1-zkvm`, which every guest links; declared here the same
+    // way `sp1-lib` does so that paillier_rs needs no SP1 dependency.
+    extern "C" {
+        fn syscall_uint256_mulmod(x: *mut [u32; LIMBS], y: *const [u32; LIMBS]);
+    }
+
+    /// Little-endian 32-bit limbs of a value below 2^256.
+    fn to_limbs(x: &BigUint) -> [u32; LIMBS] {
+        let mut limbs = [0u32; LIMBS];
+        for (limb, digit) in limbs.iter_mut().zip(x.iter_u32_digits()) {
+            *limb = digit;
+        }
+        limbs
+    }
+
+    /// \(a \cdot b \mod m\) for \(m < 2^{256}\) via `syscall_uint256_mulmod`,
+    /// which overwrites its first operand with the result and expects the
+    /// modulus to follow the second operand in memory.
+    pub fn mul_mod_256(a: &BigUint, b: &BigUint, m: &BigUint) -> BigUint {
+        let mut x = to_limbs(&(a % m));
+        let mut y_and_modulus = [0u32; 2 * LIMBS];
+        y_and_modulus[..LIMBS].copy_from_slice(&to_limbs(&(b % m)));
+        y_and_modulus[LIMBS..].copy_from_slice(&to_limbs(m));
+        unsafe {
+            syscall_uint256_mulmod(&mut x, y_and_modulus.as_ptr() as *const [u32; LIMBS]);
+        }
+        BigUint::from_slice(&x)
+    }
+}
+
+#[cfg(not(all(target_os = "zkvm", feature = "sp1")))]
+mod precompile {
+    use super::{BigNumBackend, SoftwareBackend};
+    use num_bigint::BigUint;
+
+    /// Never reached: [`Sp1Backend::accelerates`](super::Sp1Backend::accelerates)
+    /// is false outside the zkVM.
+    pub fn mul_mod_256(a: &BigUint, b: &BigUint, m: &BigUint) -> BigUint {
+        SoftwareBackend.mul_mod(a, b, m)
+    }
+}
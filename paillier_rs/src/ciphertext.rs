@@ -0,0 +1,75 @@
+use crate::decrypt::check_ciphertext;
+use crate::encoding::{decode_ciphertext_blob, decode_ciphertext_container};
+use crate::error::PaillierError;
+use crate::keygen::PublicKey;
+use crate::keyid::KeyId;
+use num_bigint::BigUint;
+
+/// A ciphertext known to lie in \(\mathbb{Z}_{n^2}^*\) for the key it was
+/// checked against.
+///
+/// Ciphertexts received from another party must go through
+/// [`Ciphertext::from_untrusted`] before any homomorphic operation. A value
+/// sharing a factor with n is not a valid encryption, and operating on it
+/// can expose that factor, e.g. through \(\gcd(c, n)\) of a decrypted or
+/// combined result.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ciphertext(BigUint);
+
+impl Ciphertext {
+    /// Validates an untrusted value: it must be nonzero, smaller than
+    /// \(n^2\) and coprime to n.
+    pub fn from_untrusted(value: BigUint, pubkey: &PublicKey) -> Result<Self, PaillierError> {
+        check_ciphertext(&value, pubkey)?;
+        Ok(Ciphertext(value))
+    }
+
+    /// Parses a base-10 string (the format older `fhesql` databases store)
+    /// and validates it.
+    pub fn from_untrusted_str(s: &str, pubkey: &PublicKey) -> Result<Self, PaillierError> {
+        let value = BigUint::parse_bytes(s.trim().as_bytes(), 10).ok_or(PaillierError::InvalidCiphertext)?;
+        Ciphertext::from_untrusted(value, pubkey)
+    }
+
+    /// Parses a ciphertext container, checks that it names `pubkey` and
+    /// validates the ciphertext.
+    pub fn from_untrusted_container(encoded: &str, pubkey: &PublicKey) -> Result<Self, PaillierError> {
+        let (found, value) = decode_ciphertext_container(encoded).ok_or(PaillierError::InvalidCiphertext)?;
+        Ciphertext::from_untrusted_under(found, value, pubkey)
+    }
+
+    /// Parses a ciphertext blob, checks that it names `pubkey` and validates
+    /// the ciphertext.
+    pub fn from_untrusted_blob(blob: &[u8], pubkey: &PublicKey) -> Result<Self, PaillierError> {
+        let (found, value) = decode_ciphertext_blob(blob).ok_or(PaillierError::InvalidCiphertext)?;
+        Ciphertext::from_untrusted_under(found, value, pubkey)
+    }
+
+    /// Validates a value that was recorded as encrypted under key `found`.
+    fn from_untrusted_under(found: KeyId, value: BigUint, pubkey: &PublicKey) -> Result<Self, PaillierError> {
+        let expected = KeyId::of(pubkey);
+        if found != expected {
+            return Err(PaillierError::WrongKey { expected, found });
+        }
+        Ciphertext::from_untrusted(value, pubkey)
+    }
+
+    /// Parses big-endian bytes and validates them.
+    pub fn from_untrusted_bytes(bytes: &[u8], pubkey: &PublicKey) -> Result<Self, PaillierError> {
+        Ciphertext::from_untrusted(BigUint::from_bytes_be(bytes), pubkey)
+    }
+
+    pub fn as_biguint(&self) -> &BigUint {
+        &self.0
+    }
+
+    pub fn into_biguint(self) -> BigUint {
+        self.0
+    }
+}
+
+impl AsRef<BigUint> for Ciphertext {
+    fn as_ref(&self) -> &BigUint {
+        &self.0
+    }
+}
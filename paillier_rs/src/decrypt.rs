@@ -1,6 +1,20 @@
-use crate::keygen::{PublicKey, PrivateKey};
+use crate::backend::BigNumBackend;
+use crate::error::PaillierError;
+use crate::keygen::{check_key_pair, PublicKey, PrivateKey};
 use num_bigint::BigUint;
-use num_traits::One;
+use num_integer::Integer;
+use num_traits::{One, Zero};
+
+/// Checks that `c` lies in \(\mathbb{Z}_{n^2}^*\), i.e. \(0 < c < n^2\) and
+/// \(\gcd(c, n) = 1\).
+pub fn check_ciphertext(c: &BigUint, pubkey: &PublicKey) -> Result<(), PaillierError> {
+    let (n, _) = pubkey;
+    if c.is_zero() || *c >= n * n || !c.gcd(n).is_one() {
+        Err(PaillierError::InvalidCiphertext)
+    } else {
+        Ok(())
+    }
+}
 
 /// Decrypts a ciphertext `c` using the private key (λ, μ) and public key (n, g).
 /// It computes:
@@ -17,3 +31,26 @@ pub fn paillier_decrypt(privkey: &PrivateKey, pubkey: &PublicKey, c: &BigUint) -
     let l_u = (&u - &one) / n;
     (&l_u * mu) % n
 }
+
+/// [`paillier_decrypt`] with the modular arithmetic done by `backend`.
+pub fn paillier_decrypt_with_backend<B: BigNumBackend + ?Sized>(
+    backend: &B,
+    privkey: &PrivateKey,
+    pubkey: &PublicKey,
+    c: &BigUint,
+) -> BigUint {
+    let (n, _g) = pubkey;
+    let (lambda, mu) = privkey;
+    let n_sq = n * n;
+    let u = backend.pow_mod(c, lambda, &n_sq);
+    let l_u = (&u - BigUint::one()) / n;
+    backend.mul_mod(&l_u, mu, n)
+}
+
+/// Fallible version of [`paillier_decrypt`] that checks the key pair and the
+/// ciphertext before decrypting.
+pub fn try_paillier_decrypt(privkey: &PrivateKey, pubkey: &PublicKey, c: &BigUint) -> Result<BigUint, PaillierError> {
+    check_key_pair(pubkey, privkey)?;
+    check_ciphertext(c, pubkey)?;
+    Ok(paillier_decrypt(privkey, pubkey, c))
+}
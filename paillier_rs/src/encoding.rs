@@ -0,0 +1,149 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::keygen::{PrivateKey, PublicKey};
+use crate::keyid::KeyId;
+use crate::keyproof::KeyProof;
+use num_bigint::BigUint;
+
+/// Header line of an encoded public key.
+pub const PUBLIC_KEY_HEADER: &str = "paillier-public-key v1";
+/// Header line of an encoded private key.
+pub const PRIVATE_KEY_HEADER: &str = "paillier-private-key v1";
+/// Header line of a ciphertext container.
+pub const CIPHERTEXT_HEADER: &str = "paillier-ciphertext v1";
+/// First byte of a ciphertext blob.
+pub const CIPHERTEXT_BLOB_VERSION: u8 = 1;
+
+fn parse_decimal(s: &str) -> Option<BigUint> {
+    BigUint::parse_bytes(s.trim().as_bytes(), 10)
+}
+
+/// Looks up `key=value` in the lines following `header`.
+fn fields<'a>(encoded: &'a str, header: &str) -> Option<Vec<(&'a str, &'a str)>> {
+    let mut lines = encoded.lines().map(str::trim).filter(|l| !l.is_empty());
+    if lines.next()? != header {
+        return None;
+    }
+    lines.map(|l| l.split_once('=')).collect()
+}
+
+fn field<'a>(fields: &[(&'a str, &'a str)], name: &str) -> Option<&'a str> {
+    fields.iter().find(|(k, _)| *k == name).map(|(_, v)| *v)
+}
+
+/// Encodes a public key and its [`KeyProof`] as text:
+///
+/// ```text
+/// paillier-public-key v1
+/// n=<decimal>
+/// g=<decimal>
+/// proof=<decimal>,<decimal>,...
+/// ```
+pub fn encode_public_key(pubkey: &PublicKey, proof: &KeyProof) -> String {
+    let (n, g) = pubkey;
+    let sigmas: Vec<String> = proof.sigmas.iter().map(|s| s.to_str_radix(10)).collect();
+    format!(
+        "{}\nn={}\ng={}\nproof={}\n",
+        PUBLIC_KEY_HEADER,
+        n.to_str_radix(10),
+        g.to_str_radix(10),
+        sigmas.join(",")
+    )
+}
+
+/// Decodes the output of [`encode_public_key`] without verifying the proof.
+/// Use [`import_public_key`](crate::keyproof::import_public_key) for keys
+/// received from another party.
+pub fn decode_public_key(encoded: &str) -> Option<(PublicKey, KeyProof)> {
+    let fields = fields(encoded, PUBLIC_KEY_HEADER)?;
+    let n = parse_decimal(field(&fields, "n")?)?;
+    let g = parse_decimal(field(&fields, "g")?)?;
+    let sigmas = field(&fields, "proof")?
+        .split(',')
+        .map(parse_decimal)
+        .collect::<Option<Vec<_>>>()?;
+    Some(((n, g), KeyProof { sigmas }))
+}
+
+/// Encodes a private key (λ, μ) as text, in the same layout as public keys.
+pub fn encode_private_key(privkey: &PrivateKey) -> String {
+    let (lambda, mu) = privkey;
+    format!(
+        "{}\nlambda={}\nmu={}\n",
+        PRIVATE_KEY_HEADER,
+        lambda.to_str_radix(10),
+        mu.to_str_radix(10)
+    )
+}
+
+/// Decodes the output of [`encode_private_key`].
+pub fn decode_private_key(encoded: &str) -> Option<PrivateKey> {
+    let fields = fields(encoded, PRIVATE_KEY_HEADER)?;
+    let lambda = parse_decimal(field(&fields, "lambda")?)?;
+    let mu = parse_decimal(field(&fields, "mu")?)?;
+    Some((lambda, mu))
+}
+
+/// Encodes a ciphertext as a base-10 string, the format older `fhesql`
+/// databases store.
+pub fn encode_ciphertext(c: &BigUint) -> String {
+    c.to_str_radix(10)
+}
+
+/// Decodes a base-10 ciphertext string.
+pub fn decode_ciphertext(encoded: &str) -> Option<BigUint> {
+    parse_decimal(encoded)
+}
+
+/// Encodes a ciphertext together with the [`KeyId`] of the key it was
+/// produced under:
+///
+/// ```text
+/// paillier-ciphertext v1
+/// key=<hex key id>
+/// c=<decimal>
+/// ```
+pub fn encode_ciphertext_container(c: &BigUint, key_id: &KeyId) -> String {
+    format!("{}\nkey={}\nc={}\n", CIPHERTEXT_HEADER, key_id, c.to_str_radix(10))
+}
+
+/// Decodes the output of [`encode_ciphertext_container`] without checking
+/// the ciphertext; see
+/// [`Ciphertext::from_untrusted_container`](crate::ciphertext::Ciphertext::from_untrusted_container).
+pub fn decode_ciphertext_container(encoded: &str) -> Option<(KeyId, BigUint)> {
+    let fields = fields(encoded, CIPHERTEXT_HEADER)?;
+    let key_id = KeyId::from_hex(field(&fields, "key")?)?;
+    let c = parse_decimal(field(&fields, "c")?)?;
+    Some((key_id, c))
+}
+
+/// Encodes a ciphertext together with the [`KeyId`] of the key it was
+/// produced under as bytes, the compact counterpart of
+/// [`encode_ciphertext_container`] and the format stored by `fhesql`:
+///
+/// ```text
+/// version (1 byte) | key id (KeyId::LEN bytes) | c (big-endian)
+/// ```
+///
+/// The version is [`CIPHERTEXT_BLOB_VERSION`].
+pub fn encode_ciphertext_blob(c: &BigUint, key_id: &KeyId) -> Vec<u8> {
+    let mut blob = Vec::with_capacity(1 + KeyId::LEN + (c.bits() as usize).div_ceil(8));
+    blob.push(CIPHERTEXT_BLOB_VERSION);
+    blob.extend_from_slice(key_id.as_bytes());
+    blob.extend_from_slice(&c.to_bytes_be());
+    blob
+}
+
+/// Decodes the output of [`encode_ciphertext_blob`] without checking the
+/// ciphertext; see
+/// [`Ciphertext::from_untrusted_blob`](crate::ciphertext::Ciphertext::from_untrusted_blob).
+/// Blobs of another version are rejected.
+pub fn decode_ciphertext_blob(blob: &[u8]) -> Option<(KeyId, BigUint)> {
+    let (&version, rest) = blob.split_first()?;
+    if version != CIPHERTEXT_BLOB_VERSION || rest.len() <= KeyId::LEN {
+        return None;
+    }
+    let (key_id, c) = rest.split_at(KeyId::LEN);
+    Some((KeyId::from_slice(key_id)?, BigUint::from_bytes_be(c)))
+}
@@ -1,26 +1,73 @@
+use crate::error::PaillierError;
 use crate::keygen::PublicKey;
 use num_bigint::{BigUint, RandBigInt};
 use num_traits::One;
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "std")]
 use rand::thread_rng;
 use num_integer::Integer;
 
-/// Encrypts a message `m` (with \(0 \le m < n\)) using the public key (n, g).
-/// A random \(r\) is chosen (with \(0 < r < n\) and \(\gcd(r,n)=1\)) and
-/// the ciphertext is computed as:
-/// 
-/// \[ c = g^m \cdot r^n \mod n^2. \]
-pub fn paillier_encrypt(pubkey: &PublicKey, m: &BigUint) -> BigUint {
-    let (n, g) = pubkey;
-    let n_sq = n * n;
-    let mut rng = thread_rng();
+/// Samples encryption randomness \(r\) with \(1 < r < n\) and \(\gcd(r,n)=1\).
+#[cfg(feature = "std")]
+pub fn sample_randomness(n: &BigUint) -> BigUint {
+    sample_randomness_with_rng(n, &mut thread_rng())
+}
+
+/// Like [`sample_randomness`], drawing from `rng`.
+pub fn sample_randomness_with_rng<R: RngCore + CryptoRng + ?Sized>(n: &BigUint, rng: &mut R) -> BigUint {
     let one = BigUint::one();
-    let r = loop {
+    loop {
         let candidate = rng.gen_biguint_below(n);
         if candidate > one && candidate.gcd(n) == one {
-            break candidate;
+            return candidate;
         }
-    };
+    }
+}
+
+/// Encrypts `m` with caller-chosen randomness `r`:
+///
+/// \[ c = g^m \cdot r^n \mod n^2. \]
+///
+/// Useful when `r` is needed later, e.g. as the witness of a proof.
+pub fn paillier_encrypt_with_randomness(pubkey: &PublicKey, m: &BigUint, r: &BigUint) -> BigUint {
+    let (n, g) = pubkey;
+    let n_sq = n * n;
     let gm = g.modpow(m, &n_sq);
     let rn = r.modpow(n, &n_sq);
     (&gm * &rn) % &n_sq
 }
+
+/// Encrypts a message `m` (with \(0 \le m < n\)) using the public key (n, g).
+/// A random \(r\) is chosen (with \(0 < r < n\) and \(\gcd(r,n)=1\)) and
+/// the ciphertext is computed as:
+/// 
+/// \[ c = g^m \cdot r^n \mod n^2. \]
+#[cfg(feature = "std")]
+pub fn paillier_encrypt(pubkey: &PublicKey, m: &BigUint) -> BigUint {
+    paillier_encrypt_with_rng(pubkey, m, &mut thread_rng())
+}
+
+/// Like [`paillier_encrypt`], drawing the randomness \(r\) from `rng`.
+pub fn paillier_encrypt_with_rng<R: RngCore + CryptoRng + ?Sized>(pubkey: &PublicKey, m: &BigUint, rng: &mut R) -> BigUint {
+    let r = sample_randomness_with_rng(&pubkey.0, rng);
+    paillier_encrypt_with_randomness(pubkey, m, &r)
+}
+
+/// Fallible version of [`paillier_encrypt`] that rejects plaintexts outside
+/// \([0, n)\), which would otherwise silently wrap around.
+#[cfg(feature = "std")]
+pub fn try_paillier_encrypt(pubkey: &PublicKey, m: &BigUint) -> Result<BigUint, PaillierError> {
+    try_paillier_encrypt_with_rng(pubkey, m, &mut thread_rng())
+}
+
+/// Fallible version of [`paillier_encrypt_with_rng`].
+pub fn try_paillier_encrypt_with_rng<R: RngCore + CryptoRng + ?Sized>(
+    pubkey: &PublicKey,
+    m: &BigUint,
+    rng: &mut R,
+) -> Result<BigUint, PaillierError> {
+    if *m >= pubkey.0 {
+        return Err(PaillierError::PlaintextOutOfRange);
+    }
+    Ok(paillier_encrypt_with_rng(pubkey, m, rng))
+}
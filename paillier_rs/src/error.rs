@@ -0,0 +1,61 @@
+use crate::keyid::KeyId;
+use core::fmt;
+
+/// Errors returned by the fallible (`try_`) Paillier operations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PaillierError {
+    /// The requested prime size is below [`MIN_PRIME_BITS`](crate::keygen::MIN_PRIME_BITS).
+    InvalidKeySize(usize),
+    /// The plaintext is not in \([0, n)\).
+    PlaintextOutOfRange,
+    /// The ciphertext is not in \(\mathbb{Z}_{n^2}^*\): it is zero, at least
+    /// \(n^2\) (usually because it was produced under a different key), or
+    /// shares a factor with n.
+    InvalidCiphertext,
+    /// The private key does not belong to the public key.
+    KeyMismatch,
+    /// The value has no inverse because it shares a factor with the modulus.
+    NotInvertible,
+    /// The ciphertext container names a different key than the one it is
+    /// used with.
+    WrongKey { expected: KeyId, found: KeyId },
+    /// A protocol message does not match the request it answers.
+    MalformedMessage,
+    /// A re-encryption target key has a smaller modulus than the key it
+    /// replaces, so large plaintexts would not survive the move.
+    ModulusTooSmall,
+    /// Inputs that must correspond one to one differ in length.
+    LengthMismatch { expected: usize, found: usize },
+    /// A polynomial of the given degree was evaluated on fewer encrypted
+    /// powers.
+    MissingPowers { degree: usize, found: usize },
+    /// A real number is infinite or NaN once scaled to fixed point.
+    NotFinite,
+}
+
+impl fmt::Display for PaillierError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaillierError::InvalidKeySize(bits) => write!(f, "invalid key size: {} bits per prime", bits),
+            PaillierError::PlaintextOutOfRange => write!(f, "plaintext is not smaller than the modulus"),
+            PaillierError::InvalidCiphertext => write!(f, "ciphertext is not a unit modulo n^2"),
+            PaillierError::KeyMismatch => write!(f, "private key does not match public key"),
+            PaillierError::NotInvertible => write!(f, "value is not invertible modulo the modulus"),
+            PaillierError::WrongKey { expected, found } => {
+                write!(f, "ciphertext belongs to key {}, not {}", found, expected)
+            }
+            PaillierError::MalformedMessage => write!(f, "protocol message does not match the request"),
+            PaillierError::ModulusTooSmall => write!(f, "new modulus is smaller than the old one"),
+            PaillierError::LengthMismatch { expected, found } => {
+                write!(f, "expected {} inputs, found {}", expected, found)
+            }
+            PaillierError::MissingPowers { degree, found } => {
+                write!(f, "degree {} needs as many encrypted powers, found {}", degree, found)
+            }
+            PaillierError::NotFinite => write!(f, "value is not a finite number"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PaillierError {}
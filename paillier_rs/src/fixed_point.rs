@@ -0,0 +1,54 @@
+use num_bigint::{BigInt, BigUint, Sign, ToBigInt};
+use num_traits::float::FloatCore;
+use num_traits::{FromPrimitive, Signed, ToPrimitive};
+use crate::error::PaillierError;
+
+/// Maps a signed integer into \(\mathbb{Z}_n\), representing a negative
+/// value \(-v\) as \(n - v\). Values must satisfy \(|v| < n/2\).
+pub fn encode_signed(v: &BigInt, n: &BigUint) -> BigUint {
+    let n_int = n.to_bigint().unwrap();
+    let reduced = ((v % &n_int) + &n_int) % &n_int;
+    reduced.to_biguint().unwrap()
+}
+
+/// Inverse of [`encode_signed`]: plaintexts above \(n/2\) are interpreted as
+/// negative, matching [`paillier_difference`](crate::arithmetic::paillier_difference).
+pub fn decode_signed(m: &BigUint, n: &BigUint) -> BigInt {
+    let half_n = n >> 1;
+    if *m > half_n {
+        m.to_bigint().unwrap() - n.to_bigint().unwrap()
+    } else {
+        m.to_bigint().unwrap()
+    }
+}
+
+/// \(\mathrm{round}(x \cdot scale)\), or [`PaillierError::NotFinite`].
+pub(crate) fn scaled(x: f64, scale: u64) -> Result<BigInt, PaillierError> {
+    BigInt::from_f64(FloatCore::round(x * scale as f64)).ok_or(PaillierError::NotFinite)
+}
+
+/// Encodes `x` as the fixed-point integer `round(x * scale)`, mapped into
+/// \(\mathbb{Z}_n\) with [`encode_signed`]. Fails with
+/// [`PaillierError::NotFinite`] if `x * scale` is not finite.
+pub fn encode_f64(x: f64, scale: u64, n: &BigUint) -> Result<BigUint, PaillierError> {
+    Ok(encode_signed(&scaled(x, scale)?, n))
+}
+
+/// Decodes a fixed-point plaintext produced with [`encode_f64`] (or a sum of
+/// such plaintexts) back to `f64`.
+pub fn decode_f64(m: &BigUint, scale: u64, n: &BigUint) -> f64 {
+    ratio_to_f64(&decode_signed(m, n), &BigInt::from(scale))
+}
+
+/// Divides two big integers as `f64`, staying accurate when both exceed the
+/// `f64` range by first dropping low-order bits.
+pub(crate) fn ratio_to_f64(num: &BigInt, den: &BigInt) -> f64 {
+    let shift = num.bits().max(den.bits()).saturating_sub(1000);
+    let (num, den) = (num >> shift, den >> shift);
+    let value = num.abs().to_f64().unwrap_or(f64::INFINITY) / den.abs().to_f64().unwrap_or(f64::INFINITY);
+    if (num.sign() == Sign::Minus) != (den.sign() == Sign::Minus) {
+        -value
+    } else {
+        value
+    }
+}
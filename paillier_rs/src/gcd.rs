@@ -0,0 +1,122 @@
+use crate::error::PaillierError;
+use core::mem;
+use num_bigint::{BigInt, BigUint, Sign};
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive, Zero};
+
+/// Operands below this size go straight to [`extended_gcd_int`]; above it,
+/// [`extended_gcd_lehmer`] replaces most big-number divisions with word-sized
+/// steps.
+pub const LEHMER_THRESHOLD_BITS: u64 = 256;
+
+/// Bits of each operand that Lehmer's algorithm simulates per outer step.
+/// Keeping them below 63 bits lets the cofactors and the sums `x̂ + A` fit in
+/// an `i128` without overflow.
+const LEHMER_DIGIT_BITS: u64 = 62;
+
+/// Extended Euclidean Algorithm for BigInts.
+/// Returns (g, x, y) such that a*x + b*y = g = gcd(a, b), up to the sign of g.
+///
+/// Iterative, so the stack depth does not grow with the number of division
+/// steps; only the cofactor of `a` is tracked and `y` is recovered at the end
+/// with one exact division.
+pub fn extended_gcd_int(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    let (mut r0, mut r1) = (a.clone(), b.clone());
+    let (mut s0, mut s1) = (BigInt::one(), BigInt::zero());
+    while !r1.is_zero() {
+        let (q, r) = r0.div_rem(&r1);
+        r0 = mem::replace(&mut r1, r);
+        let s = s0 - &q * &s1;
+        s0 = mem::replace(&mut s1, s);
+    }
+    let t = if b.is_zero() { BigInt::zero() } else { (&r0 - a * &s0) / b };
+    (r0, s0, t)
+}
+
+/// Extended GCD of two unsigned operands using Lehmer's algorithm (Knuth,
+/// TAOCP vol. 2, Algorithm 4.5.2L, with cofactors as in HAC 14.57).
+///
+/// Each outer step runs Euclid on the leading 62 bits of both operands and
+/// applies the accumulated 2×2 matrix to the full values, so most quotients
+/// never touch a `BigUint`. Returns (g, x, y) with a*x + b*y = g = gcd(a, b)
+/// and g ≥ 0.
+pub fn extended_gcd_lehmer(a: &BigUint, b: &BigUint) -> (BigUint, BigInt, BigInt) {
+    let (swapped, mut x, mut y) = if a >= b { (false, a.clone(), b.clone()) } else { (true, b.clone(), a.clone()) };
+    // Cofactors of the larger operand: x = sx·max(a, b) + (…)·min(a, b).
+    let (mut sx, mut sy) = (BigInt::one(), BigInt::zero());
+
+    while y.bits() > LEHMER_DIGIT_BITS {
+        let shift = x.bits() - LEHMER_DIGIT_BITS;
+        let mut xh = (&x >> shift).to_i128().unwrap();
+        let mut yh = (&y >> shift).to_i128().unwrap();
+        let (mut ca, mut cb, mut cc, mut cd) = (1i128, 0i128, 0i128, 1i128);
+        while yh + cc != 0 && yh + cd != 0 {
+            let q = (xh + ca) / (yh + cc);
+            if q != (xh + cb) / (yh + cd) {
+                break;
+            }
+            (ca, cc) = (cc, ca - q * cc);
+            (cb, cd) = (cd, cb - q * cd);
+            (xh, yh) = (yh, xh - q * yh);
+        }
+
+        if cb == 0 {
+            // The leading digits gave no usable quotient: take one full step.
+            let (q, r) = x.div_rem(&y);
+            x = mem::replace(&mut y, r);
+            let s = sx - BigInt::from(q) * &sy;
+            sx = mem::replace(&mut sy, s);
+        } else {
+            let (xi, yi) = (BigInt::from(x), BigInt::from(y));
+            x = to_biguint(ca * &xi + cb * &yi);
+            y = to_biguint(cc * xi + cd * yi);
+            let s = cc * &sx + cd * &sy;
+            sx = ca * sx + cb * sy;
+            sy = s;
+        }
+    }
+
+    // y now fits in a machine word; finish with plain Euclid.
+    while !y.is_zero() {
+        let (q, r) = x.div_rem(&y);
+        x = mem::replace(&mut y, r);
+        let s = sx - BigInt::from(q) * &sy;
+        sx = mem::replace(&mut sy, s);
+    }
+
+    let (big, small) = if swapped { (b, a) } else { (a, b) };
+    let t = if small.is_zero() {
+        BigInt::zero()
+    } else {
+        (BigInt::from(x.clone()) - BigInt::from(big.clone()) * &sx) / BigInt::from(small.clone())
+    };
+    if swapped { (x, t, sx) } else { (x, sx, t) }
+}
+
+fn to_biguint(v: BigInt) -> BigUint {
+    debug_assert!(v.sign() != Sign::Minus, "Lehmer step produced a negative remainder");
+    v.into_parts().1
+}
+
+/// Compute the modular inverse of `a` modulo `m`.
+///
+/// Returns [`PaillierError::NotInvertible`] if \(\gcd(a, m) \neq 1\) or
+/// `m` is zero. Operands above [`LEHMER_THRESHOLD_BITS`] use
+/// [`extended_gcd_lehmer`].
+pub fn modinv(a: &BigUint, m: &BigUint) -> Result<BigUint, PaillierError> {
+    if m.is_zero() {
+        return Err(PaillierError::NotInvertible);
+    }
+    let a = a % m;
+    let (g, x) = if m.bits() > LEHMER_THRESHOLD_BITS {
+        let (g, x, _) = extended_gcd_lehmer(&a, m);
+        (g, x)
+    } else {
+        let (g, x, _) = extended_gcd_int(&BigInt::from(a), &BigInt::from(m.clone()));
+        (g.into_parts().1, x)
+    };
+    if !g.is_one() {
+        return Err(PaillierError::NotInvertible);
+    }
+    Ok(x.mod_floor(&BigInt::from(m.clone())).into_parts().1)
+}
@@ -1,10 +1,22 @@
-use num_bigint::{BigInt, BigUint, RandBigInt, ToBigInt};
+use num_bigint::{BigUint, RandBigInt};
 use num_integer::Integer;
 use num_traits::{One, Zero};
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "std")]
 use rand::thread_rng;
+use crate::error::PaillierError;
+pub use crate::gcd::{extended_gcd_int, modinv};
+#[cfg(feature = "std")]
+use crate::keyproof::{prove_key, KeyProof};
 
 /// Returns true if `n` is likely prime.
+#[cfg(feature = "std")]
 pub fn is_prime(n: &BigUint, k: u32) -> bool {
+    is_prime_with_rng(n, k, &mut thread_rng())
+}
+
+/// Miller-Rabin test with `k` random witnesses drawn from `rng`.
+pub fn is_prime_with_rng<R: RngCore + ?Sized>(n: &BigUint, k: u32, rng: &mut R) -> bool {
     let one = BigUint::one();
     let two = &one + &one;
     if n < &two {
@@ -23,7 +35,6 @@ pub fn is_prime(n: &BigUint, k: u32) -> bool {
         d /= &two;
         s += 1;
     }
-    let mut rng = thread_rng();
     'witness: for _ in 0..k {
         let a = rng.gen_biguint_range(&two, &(n - &two));
         let mut x = a.modpow(&d, n);
@@ -42,41 +53,22 @@ pub fn is_prime(n: &BigUint, k: u32) -> bool {
 }
 
 /// Generate a random prime number of approximately `bits` bits.
+#[cfg(feature = "std")]
 pub fn generate_prime(bits: usize) -> BigUint {
-    let mut rng = thread_rng();
+    generate_prime_with_rng(bits, &mut thread_rng())
+}
+
+/// Like [`generate_prime`], drawing candidates and witnesses from `rng`.
+pub fn generate_prime_with_rng<R: RngCore + CryptoRng + ?Sized>(bits: usize, rng: &mut R) -> BigUint {
     loop {
         // Ensure the candidate has the top bit set and is odd.
         let candidate = rng.gen_biguint(bits.try_into().unwrap()) | BigUint::one() | (BigUint::one() << (bits - 1));
-        if is_prime(&candidate, 20) {
+        if is_prime_with_rng(&candidate, 20, rng) {
             return candidate;
         }
     }
 }
 
-/// Extended Euclidean Algorithm for BigInts.
-/// Returns (g, x, y) such that a*x + b*y = g = gcd(a, b).
-pub fn extended_gcd_int(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
-    if b.is_zero() {
-        (a.clone(), BigInt::one(), BigInt::zero())
-    } else {
-        let (g, x, y) = extended_gcd_int(b, &(a % b));
-        (g, y.clone(), x - (a / b) * y)
-    }
-}
-
-/// Compute the modular inverse of `a` modulo `m`, if it exists.
-pub fn modinv(a: &BigUint, m: &BigUint) -> Option<BigUint> {
-    let a_int = a.to_bigint().unwrap();
-    let m_int = m.to_bigint().unwrap();
-    let (g, x, _) = extended_gcd_int(&a_int, &m_int);
-    if g != BigInt::one() {
-        None
-    } else {
-        let x = ((x % &m_int) + &m_int) % &m_int;
-        Some(x.to_biguint().unwrap())
-    }
-}
-
 /// Type aliases for clarity.
 pub type PublicKey = (BigUint, BigUint);
 pub type PrivateKey = (BigUint, BigUint);
@@ -85,18 +77,98 @@ pub type PrivateKey = (BigUint, BigUint);
 /// - Choose primes p and q.
 /// - Set n = p * q and φ(n) = (p-1)*(q-1).
 /// - Let g = n + 1, λ = φ(n) and μ = (λ)^{-1} mod n.
+///
 /// Returns (public_key, private_key).
+#[cfg(feature = "std")]
 pub fn paillier_keygen(bits: usize) -> (PublicKey, PrivateKey) {
-    println!("Generating prime p...");
-    let p = generate_prime(bits);
-    println!("Generating prime q...");
-    let q = generate_prime(bits);
-    let n = &p * &q;
+    paillier_keygen_with_rng(bits, &mut thread_rng())
+}
+
+/// Like [`paillier_keygen`], with the primes drawn from `rng`. This is the
+/// entry point for environments without `std`, such as the SP1 zkVM guest.
+pub fn paillier_keygen_with_rng<R: RngCore + CryptoRng + ?Sized>(bits: usize, rng: &mut R) -> (PublicKey, PrivateKey) {
+    let p = generate_prime_with_rng(bits, rng);
     let one = BigUint::one();
-    let phi = (&p - &one) * (&q - &one);
-    let g = &n + &one;
     // In this variant, (n+1)^φ mod n^2 = 1 + φ*n, so L(u) = (u-1)/n yields φ.
-    // Therefore, μ = (φ)^{-1} mod n.
-    let mu = modinv(&phi, &n).expect("Modular inverse should exist.");
-    ((n.clone(), g), (phi, mu))
+    // Therefore, μ = (φ)^{-1} mod n. It exists unless φ(n) shares a factor
+    // with n, as for p = q; q is drawn again until it does.
+    loop {
+        let q = generate_prime_with_rng(bits, rng);
+        let n = &p * &q;
+        let phi = (&p - &one) * (&q - &one);
+        if let Ok(mu) = modinv(&phi, &n) {
+            let g = &n + &one;
+            return ((n, g), (phi, mu));
+        }
+    }
+}
+
+/// Smallest prime size accepted by [`try_paillier_keygen`]. Below this the
+/// primes fall under the small-prime sieve of the key proof.
+pub const MIN_PRIME_BITS: usize = 32;
+
+/// Fallible version of [`paillier_keygen`] that rejects prime sizes below
+/// [`MIN_PRIME_BITS`] instead of panicking or producing a degenerate key.
+#[cfg(feature = "std")]
+pub fn try_paillier_keygen(bits: usize) -> Result<(PublicKey, PrivateKey), PaillierError> {
+    try_paillier_keygen_with_rng(bits, &mut thread_rng())
+}
+
+/// Fallible version of [`paillier_keygen_with_rng`].
+pub fn try_paillier_keygen_with_rng<R: RngCore + CryptoRng + ?Sized>(
+    bits: usize,
+    rng: &mut R,
+) -> Result<(PublicKey, PrivateKey), PaillierError> {
+    if bits < MIN_PRIME_BITS {
+        return Err(PaillierError::InvalidKeySize(bits));
+    }
+    Ok(paillier_keygen_with_rng(bits, rng))
+}
+
+/// Bases raised to λ by [`check_key_pair`].
+const CHECK_BASES: [u32; 2] = [2, 3];
+
+/// Checks that `privkey` belongs to `pubkey`, i.e. that it decrypts:
+///
+/// - λ annihilates Z_n^*, so the randomness of a ciphertext cancels out.
+///   This is checked as b^λ ≡ 1 (mod n) for a few small bases b, which the
+///   λ of another key fails with overwhelming probability.
+/// - g^λ mod n^2 = 1 + k·n with k·μ ≡ 1 (mod n), so μ undoes the exponent.
+///
+/// λ·μ ≡ 1 (mod n) alone is not enough: any pair (x, x^{-1}), such as
+/// (1, 1), satisfies it.
+pub fn check_key_pair(pubkey: &PublicKey, privkey: &PrivateKey) -> Result<(), PaillierError> {
+    let (n, g) = pubkey;
+    let (lambda, mu) = privkey;
+    let one = BigUint::one();
+    if n <= &one {
+        return Err(PaillierError::KeyMismatch);
+    }
+    if CHECK_BASES.iter().any(|&b| BigUint::from(b).modpow(lambda, n) != one) {
+        return Err(PaillierError::KeyMismatch);
+    }
+    let u = g.modpow(lambda, &(n * n));
+    if u.is_zero() || !((&u - &one) % n).is_zero() || ((&u - &one) / n * mu) % n != one {
+        return Err(PaillierError::KeyMismatch);
+    }
+    Ok(())
+}
+
+/// Key generation that also returns a [`KeyProof`] that the modulus n
+/// satisfies gcd(n, φ(n)) = 1. Publish the proof alongside the public key so that clients can
+/// check it with [`verify_key`](crate::keyproof::verify_key) before encrypting.
+#[cfg(feature = "std")]
+pub fn paillier_keygen_with_proof(bits: usize) -> (PublicKey, PrivateKey, KeyProof) {
+    let (pubkey, privkey) = paillier_keygen(bits);
+    let proof = prove_key(&pubkey, &privkey);
+    (pubkey, privkey, proof)
+}
+
+/// Fallible version of [`paillier_keygen_with_proof`] that rejects primes
+/// smaller than [`MIN_PRIME_BITS`].
+#[cfg(feature = "std")]
+pub fn try_paillier_keygen_with_proof(bits: usize) -> Result<(PublicKey, PrivateKey, KeyProof), PaillierError> {
+    let (pubkey, privkey) = try_paillier_keygen(bits)?;
+    let proof = prove_key(&pubkey, &privkey);
+    Ok((pubkey, privkey, proof))
 }
@@ -0,0 +1,80 @@
+use crate::keygen::PublicKey;
+use core::fmt;
+use sha2::{Digest, Sha256};
+
+const DOMAIN_TAG: &[u8] = b"paillier-rs/key-fingerprint/v1";
+
+/// SHA-256 fingerprint of a public key.
+///
+/// The hash covers a domain tag followed by n and g, each as a big-endian
+/// byte string with a 64-bit length prefix. The encoding depends only on the
+/// key, not on the text format it was stored in or on its proof, so the same
+/// key always yields the same fingerprint.
+pub fn key_fingerprint(pubkey: &PublicKey) -> [u8; 32] {
+    let (n, g) = pubkey;
+    let mut hasher = Sha256::new();
+    hasher.update(DOMAIN_TAG);
+    for value in [n, g] {
+        let bytes = value.to_bytes_be();
+        hasher.update((bytes.len() as u64).to_be_bytes());
+        hasher.update(&bytes);
+    }
+    hasher.finalize().into()
+}
+
+/// Short identifier of a public key: the first [`KeyId::LEN`] bytes of its
+/// [`key_fingerprint`], shown as lowercase hex.
+///
+/// Ciphertext containers, `fhesql` metadata tables and the zkVM guest's
+/// public outputs record the `KeyId` of the key a ciphertext belongs to, so
+/// a ciphertext can be matched with its key without trial decryption.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KeyId([u8; KeyId::LEN]);
+
+impl KeyId {
+    /// Length of an identifier in bytes.
+    pub const LEN: usize = 16;
+
+    /// Identifier of `pubkey`.
+    pub fn of(pubkey: &PublicKey) -> Self {
+        let mut id = [0u8; KeyId::LEN];
+        id.copy_from_slice(&key_fingerprint(pubkey)[..KeyId::LEN]);
+        KeyId(id)
+    }
+
+    pub fn from_bytes(bytes: [u8; KeyId::LEN]) -> Self {
+        KeyId(bytes)
+    }
+
+    /// Parses a byte slice of exactly [`KeyId::LEN`] bytes.
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        Some(KeyId(bytes.try_into().ok()?))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; KeyId::LEN] {
+        &self.0
+    }
+
+    /// Parses the hex form produced by `Display`.
+    pub fn from_hex(s: &str) -> Option<Self> {
+        let s = s.trim().as_bytes();
+        if s.len() != 2 * KeyId::LEN || !s.iter().all(u8::is_ascii_hexdigit) {
+            return None;
+        }
+        let mut id = [0u8; KeyId::LEN];
+        for (byte, pair) in id.iter_mut().zip(s.chunks(2)) {
+            let hex = core::str::from_utf8(pair).ok()?;
+            *byte = u8::from_str_radix(hex, 16).ok()?;
+        }
+        Some(KeyId(id))
+    }
+}
+
+impl fmt::Display for KeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
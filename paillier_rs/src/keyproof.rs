@@ -0,0 +1,136 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::encoding::decode_public_key;
+use crate::keygen::{modinv, PrivateKey, PublicKey};
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, Zero};
+use sha2::{Digest, Sha256};
+
+/// Number of n-th root challenges in a proof. Together with the small-prime
+/// sieve below 2^16, eight rounds give a soundness error of about 2^-128.
+pub const PROOF_ROUNDS: usize = 8;
+
+/// Moduli with a prime factor below this bound are rejected outright.
+pub const SMALL_PRIME_BOUND: u32 = 1 << 16;
+
+const DOMAIN_TAG: &[u8] = b"paillier-rs/paillier-n-proof/v1";
+
+/// Non-interactive proof that a Paillier modulus n satisfies
+/// \(\gcd(n, \varphi(n)) = 1\), which decryption relies on (Goldberg,
+/// Reyzin, Sagga, Baldimtsi, "Efficient Noninteractive Certification of RSA
+/// Moduli", Paillier-N protocol).
+///
+/// That, and the absence of prime factors below [`SMALL_PRIME_BOUND`] that
+/// [`verify_key`] checks, is all it establishes. It does not show that n is
+/// a product of two primes, nor that its factors are large enough to keep n
+/// from being factored.
+///
+/// For challenges \(\rho_i\) derived by hashing the public key, the prover
+/// publishes \(\sigma_i = \rho_i^{n^{-1} \bmod \varphi(n)} \mod n\). The map
+/// \(x \mapsto x^n\) is a bijection on \(\mathbb{Z}_n^*\) exactly when
+/// \(\gcd(n, \varphi(n)) = 1\), so a cheating prover can only answer a
+/// challenge with probability at most \(1/\alpha\), where \(\alpha\) is the
+/// smallest prime factor of n.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyProof {
+    pub sigmas: Vec<BigUint>,
+}
+
+/// Derives the `i`-th challenge \(\rho_i \in \mathbb{Z}_n\) from the public key.
+/// SHA-256 is run in counter mode to produce 128 bits more than the size of n,
+/// so the reduction mod n is statistically close to uniform.
+fn challenge(pubkey: &PublicKey, i: usize) -> BigUint {
+    let (n, g) = pubkey;
+    let n_bytes = n.to_bytes_be();
+    let g_bytes = g.to_bytes_be();
+    let wanted = n_bytes.len() + 16;
+    let mut out = Vec::with_capacity(wanted + 32);
+    let mut counter: u32 = 0;
+    while out.len() < wanted {
+        let mut hasher = Sha256::new();
+        hasher.update(DOMAIN_TAG);
+        hasher.update((n_bytes.len() as u64).to_be_bytes());
+        hasher.update(&n_bytes);
+        hasher.update((g_bytes.len() as u64).to_be_bytes());
+        hasher.update(&g_bytes);
+        hasher.update((i as u64).to_be_bytes());
+        hasher.update(counter.to_be_bytes());
+        out.extend_from_slice(&hasher.finalize());
+        counter += 1;
+    }
+    out.truncate(wanted);
+    BigUint::from_bytes_be(&out) % n
+}
+
+/// Returns true if `n` has a prime factor smaller than [`SMALL_PRIME_BOUND`].
+fn has_small_factor(n: &BigUint) -> bool {
+    let bound = SMALL_PRIME_BOUND as usize;
+    let mut composite = vec![false; bound];
+    for p in 2..bound {
+        if composite[p] {
+            continue;
+        }
+        if (n % p as u32).is_zero() {
+            return true;
+        }
+        let mut multiple = p * p;
+        while multiple < bound {
+            composite[multiple] = true;
+            multiple += p;
+        }
+    }
+    false
+}
+
+/// Produces a [`KeyProof`] that \(\gcd(n, \varphi(n)) = 1\) for `pubkey`.
+///
+/// Uses λ = φ(n) from the private key to compute \(n^{-1} \bmod \varphi(n)\).
+/// Panics if that inverse does not exist, i.e. if the key pair is malformed.
+pub fn prove_key(pubkey: &PublicKey, privkey: &PrivateKey) -> KeyProof {
+    let (n, _) = pubkey;
+    let (phi, _) = privkey;
+    let n_inv = modinv(n, phi).expect("n must be invertible modulo φ(n).");
+    let sigmas = (0..PROOF_ROUNDS)
+        .map(|i| challenge(pubkey, i).modpow(&n_inv, n))
+        .collect();
+    KeyProof { sigmas }
+}
+
+/// Verifies a [`KeyProof`] for `pubkey`.
+///
+/// Checks that n is odd and free of small prime factors, that g = n + 1 as
+/// produced by [`paillier_keygen`](crate::keygen::paillier_keygen), and that
+/// every \(\sigma_i\) is an n-th root of the corresponding challenge. A
+/// valid proof thus shows \(\gcd(n, \varphi(n)) = 1\) and no prime factor
+/// below [`SMALL_PRIME_BOUND`], not that n is a product of two primes.
+pub fn verify_key(pubkey: &PublicKey, proof: &KeyProof) -> bool {
+    let (n, g) = pubkey;
+    let one = BigUint::one();
+    if n.is_even() || n <= &BigUint::from(SMALL_PRIME_BOUND) {
+        return false;
+    }
+    if *g != n + &one {
+        return false;
+    }
+    if proof.sigmas.len() != PROOF_ROUNDS || has_small_factor(n) {
+        return false;
+    }
+    proof.sigmas.iter().enumerate().all(|(i, sigma)| {
+        let rho = challenge(pubkey, i);
+        // A challenge sharing a factor with n would reveal that factor,
+        // so the verifier insists on ρ ∈ Z*_n.
+        sigma < n && rho.gcd(n) == one && sigma.modpow(n, n) == rho
+    })
+}
+
+/// Parses an encoded public key together with its proof and returns the key
+/// only if the proof verifies. Keys with a missing or bad proof are rejected.
+pub fn import_public_key(encoded: &str) -> Option<PublicKey> {
+    let (pubkey, proof) = decode_public_key(encoded)?;
+    if verify_key(&pubkey, &proof) {
+        Some(pubkey)
+    } else {
+        None
+    }
+}
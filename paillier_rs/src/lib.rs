@@ -1,4 +1,23 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod error;
+pub mod backend;
+pub mod gcd;
 pub mod keygen;
 pub mod encrypt;
 pub mod decrypt;
 pub mod arithmetic;
+pub mod ciphertext;
+pub mod keyproof;
+pub mod keyid;
+pub mod encoding;
+pub mod transcript;
+pub mod proofs;
+#[cfg(feature = "std")]
+pub mod rotation;
+pub mod fixed_point;
+pub mod stats;
+pub mod polynomial;
+pub mod multiplication;
@@ -1,54 +1,371 @@
-use paillier_rs::keygen::{paillier_keygen, PublicKey, PrivateKey};
-use paillier_rs::encrypt::paillier_encrypt;
-use paillier_rs::decrypt::paillier_decrypt;
-use paillier_rs::arithmetic::{paillier_add, paillier_compare, paillier_difference, paillier_scalar_mul, paillier_subtract};
-use num_bigint::{BigUint, ToBigUint};
-
-fn main() {
-    // Use 64-bit primes for demonstration (use larger in production).
-    let bits = 64;
-    let (pubkey, privkey) = paillier_keygen(bits);
-    
-    // Encrypt two messages.
-    let m1 = 42u32.to_biguint().unwrap();
-    let m2 = 17u32.to_biguint().unwrap();
-    
-    let c1 = paillier_encrypt(&pubkey, &m1);
-    let c2 = paillier_encrypt(&pubkey, &m2);
-    
-    // Decrypt to verify correctness.
-    let m1_dec = paillier_decrypt(&privkey, &pubkey, &c1);
-    println!("Decrypted m1: {}", m1_dec);
-    
-    // Homomorphic addition: should yield m1 + m2.
-    let c_add = paillier_add(&c1, &c2, &pubkey);
-    let m_add = paillier_decrypt(&privkey, &pubkey, &c_add);
-    println!("m1 + m2 (decrypted): {}", m_add);
-    
-    // Scalar multiplication: 5 * m1.
-    let k = 5u32.to_biguint().unwrap();
-    let c_scalar = paillier_scalar_mul(&c1, &k, &pubkey);
-    let m_scalar = paillier_decrypt(&privkey, &pubkey, &c_scalar);
-    println!("5 * m1 (decrypted): {}", m_scalar);
-    
-    // Homomorphic subtraction (difference): m1 - m2.
-    let c_diff = paillier_subtract(&c1, &c2, &pubkey);
-    let m_diff = paillier_decrypt(&privkey, &pubkey, &c_diff);
-    println!("m1 - m2 (decrypted, mod n): {}", m_diff);
-    
-    // Convenience difference (interpreted as signed).
-    let diff = paillier_difference(&c1, &c2, &pubkey, &privkey);
-    println!("Signed difference m1 - m2: {}", diff);
-
-    // Secure comparison using masked difference.
-    // Choose a random mask r that is larger than any expected |m1 - m2|.
-    // In this example, m1 = 42 and m2 = 17, so |m1 - m2| = 25.
-    // We choose r = 100.
-    let r = (2<<9).to_biguint().unwrap();
-    let comparison = paillier_compare(&c1, &c2, &pubkey, &privkey, &r);
-    if comparison {
-        println!("Secure comparison: m1 < m2");
+use paillier_rs::arithmetic::{
+    paillier_rerandomize, try_paillier_add, try_paillier_scalar_mul, try_paillier_subtract,
+};
+use paillier_rs::ciphertext::Ciphertext;
+use paillier_rs::decrypt::try_paillier_decrypt;
+use paillier_rs::encoding::{
+    decode_ciphertext, decode_ciphertext_container, decode_private_key, decode_public_key,
+    encode_ciphertext_container, encode_private_key, encode_public_key, CIPHERTEXT_HEADER,
+    PRIVATE_KEY_HEADER, PUBLIC_KEY_HEADER,
+};
+use paillier_rs::encrypt::try_paillier_encrypt;
+use paillier_rs::error::PaillierError;
+use paillier_rs::fixed_point::{decode_signed, encode_signed};
+use paillier_rs::keygen::{check_key_pair, try_paillier_keygen, PrivateKey, PublicKey};
+use paillier_rs::keyid::KeyId;
+use paillier_rs::keyproof::{prove_key, verify_key, SMALL_PRIME_BOUND};
+use num_bigint::{BigInt, BigUint};
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: paillier_rs <command> [options]
+
+commands:
+  keygen --bits <bits> --out <prefix>         write <prefix>.pub and <prefix>.key
+  encrypt --public <file> [<plaintext-file>]
+  decrypt --public <file> --private <file> [--signed] [<ciphertext-file>]
+  add --public <file> <ciphertext-file> <ciphertext-file>
+  sub --public <file> <ciphertext-file> <ciphertext-file>
+  scalar-mul --public <file> <ciphertext-file> <integer>
+  rerandomize --public <file> [<ciphertext-file>]
+  inspect [--public <file>] [<file>]
+
+Files default to stdin when omitted or given as `-`; results go to stdout,
+or to the file named by --out. Plaintexts and scalars are signed decimal
+integers, encoded modulo n. Ciphertexts are written as containers recording
+the key ID of the public key; bare decimal ciphertexts are also accepted.
+
+exit status: 0 on success, 1 if an operation fails, 2 on invalid usage.";
+
+/// Failure of a CLI command, mapped to the process exit status.
+enum CliError {
+    /// Bad arguments; exit status 2.
+    Usage(String),
+    /// The command ran but failed (unreadable input, invalid key or
+    /// ciphertext, ...); exit status 1.
+    Failed(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(msg) => write!(f, "{}\n\n{}", msg, USAGE),
+            CliError::Failed(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(e: io::Error) -> Self {
+        CliError::Failed(e.to_string())
+    }
+}
+
+impl From<PaillierError> for CliError {
+    fn from(e: PaillierError) -> Self {
+        CliError::Failed(e.to_string())
+    }
+}
+
+type CliResult<T> = Result<T, CliError>;
+
+/// Command-line arguments split into `--name value` options, flags and
+/// positional arguments.
+struct Args {
+    options: Vec<(String, String)>,
+    flags: Vec<String>,
+    positional: Vec<String>,
+}
+
+/// Options that take no value.
+const FLAGS: [&str; 1] = ["signed"];
+
+impl Args {
+    fn parse(raw: &[String]) -> CliResult<Self> {
+        let mut args = Args { options: Vec::new(), flags: Vec::new(), positional: Vec::new() };
+        let mut iter = raw.iter();
+        while let Some(arg) = iter.next() {
+            match arg.strip_prefix("--") {
+                Some(name) if FLAGS.contains(&name) => args.flags.push(name.to_string()),
+                Some(name) => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| CliError::Usage(format!("missing value for --{}", name)))?;
+                    args.options.push((name.to_string(), value.clone()));
+                }
+                None => args.positional.push(arg.clone()),
+            }
+        }
+        Ok(args)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+
+    fn required(&self, name: &str) -> CliResult<&str> {
+        self.option(name).ok_or_else(|| CliError::Usage(format!("missing --{}", name)))
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f == name)
+    }
+
+    /// The `index`-th positional argument, or `-` (stdin) if absent.
+    fn input(&self, index: usize) -> &str {
+        self.positional.get(index).map(String::as_str).unwrap_or("-")
+    }
+
+    /// Rejects positional arguments beyond `max`.
+    fn expect_positional(&self, max: usize) -> CliResult<()> {
+        if self.positional.len() > max {
+            return Err(CliError::Usage(format!("unexpected argument `{}`", self.positional[max])));
+        }
+        Ok(())
+    }
+}
+
+/// Reads a file, or stdin for `-`.
+fn read_input(path: &str) -> CliResult<String> {
+    if path == "-" {
+        let mut s = String::new();
+        io::stdin().read_to_string(&mut s)?;
+        Ok(s)
     } else {
-        println!("Secure comparison: m1 >= m2");
+        fs::read_to_string(path).map_err(|e| CliError::Failed(format!("{}: {}", path, e)))
+    }
+}
+
+/// Writes the result to --out, or to stdout.
+fn write_output(args: &Args, contents: &str) -> CliResult<()> {
+    match args.option("out") {
+        Some(path) if path != "-" => fs::write(path, contents)?,
+        _ => io::stdout().write_all(contents.as_bytes())?,
+    }
+    Ok(())
+}
+
+/// Creates a file that must not exist yet, so key files are never
+/// overwritten.
+fn create_new(path: &str) -> CliResult<fs::File> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| CliError::Failed(format!("{}: {}", path, e)))
+}
+
+/// Loads a public key and verifies its [`KeyProof`](paillier_rs::keyproof::KeyProof).
+fn load_public_key(args: &Args) -> CliResult<PublicKey> {
+    let path = args.required("public")?;
+    let (pubkey, proof) = decode_public_key(&read_input(path)?)
+        .ok_or_else(|| CliError::Failed(format!("{}: not a public key file", path)))?;
+    if !verify_key(&pubkey, &proof) {
+        return Err(CliError::Failed(format!("{}: key proof does not verify", path)));
+    }
+    Ok(pubkey)
+}
+
+fn load_private_key(args: &Args, pubkey: &PublicKey) -> CliResult<PrivateKey> {
+    let path = args.required("private")?;
+    let privkey = decode_private_key(&read_input(path)?)
+        .ok_or_else(|| CliError::Failed(format!("{}: not a private key file", path)))?;
+    check_key_pair(pubkey, &privkey)?;
+    Ok(privkey)
+}
+
+/// Loads a ciphertext container, or a bare decimal ciphertext, and checks it
+/// against `pubkey`.
+fn load_ciphertext(path: &str, pubkey: &PublicKey) -> CliResult<Ciphertext> {
+    let contents = read_input(path)?;
+    let c = if is_container(&contents) {
+        Ciphertext::from_untrusted_container(&contents, pubkey)
+    } else {
+        Ciphertext::from_untrusted_str(&contents, pubkey)
+    };
+    c.map_err(|e| CliError::Failed(format!("{}: {}", path, e)))
+}
+
+fn is_container(contents: &str) -> bool {
+    contents.lines().next().map(str::trim) == Some(CIPHERTEXT_HEADER)
+}
+
+fn write_ciphertext(args: &Args, c: &BigUint, pubkey: &PublicKey) -> CliResult<()> {
+    write_output(args, &encode_ciphertext_container(c, &KeyId::of(pubkey)))
+}
+
+fn parse_integer(s: &str) -> CliResult<BigInt> {
+    s.trim()
+        .parse()
+        .map_err(|_| CliError::Failed(format!("`{}` is not an integer", s.trim())))
+}
+
+fn keygen(args: &Args) -> CliResult<()> {
+    args.expect_positional(0)?;
+    let bits: usize = args
+        .required("bits")?
+        .parse()
+        .map_err(|_| CliError::Usage("--bits must be a positive integer".into()))?;
+    let prefix = args.required("out")?;
+    let (pubkey, privkey) = try_paillier_keygen(bits)?;
+    let proof = prove_key(&pubkey, &privkey);
+    let (public_path, private_path) = (format!("{}.pub", prefix), format!("{}.key", prefix));
+    // Both files are created before writing either, so an existing key file
+    // leaves no half-written pair behind.
+    let mut public_file = create_new(&public_path)?;
+    let mut private_file = match create_new(&private_path) {
+        Ok(file) => file,
+        Err(e) => {
+            drop(public_file);
+            let _ = fs::remove_file(&public_path);
+            return Err(e);
+        }
+    };
+    public_file.write_all(encode_public_key(&pubkey, &proof).as_bytes())?;
+    private_file.write_all(encode_private_key(&privkey).as_bytes())?;
+    Ok(())
+}
+
+fn encrypt(args: &Args) -> CliResult<()> {
+    args.expect_positional(1)?;
+    let pubkey = load_public_key(args)?;
+    let m = parse_integer(&read_input(args.input(0))?)?;
+    // Signed plaintexts must stay within (-n/2, n/2] to decode unambiguously.
+    if m.magnitude() > &(&pubkey.0 >> 1) {
+        return Err(PaillierError::PlaintextOutOfRange.into());
+    }
+    let c = try_paillier_encrypt(&pubkey, &encode_signed(&m, &pubkey.0))?;
+    write_ciphertext(args, &c, &pubkey)
+}
+
+fn decrypt(args: &Args) -> CliResult<()> {
+    args.expect_positional(1)?;
+    let pubkey = load_public_key(args)?;
+    let privkey = load_private_key(args, &pubkey)?;
+    let c = load_ciphertext(args.input(0), &pubkey)?;
+    let m = try_paillier_decrypt(&privkey, &pubkey, c.as_biguint())?;
+    let m = if args.flag("signed") { decode_signed(&m, &pubkey.0) } else { m.into() };
+    write_output(args, &format!("{}\n", m))
+}
+
+fn binary_op(args: &Args, subtract: bool) -> CliResult<()> {
+    args.expect_positional(2)?;
+    if args.positional.len() < 2 {
+        return Err(CliError::Usage("expected two ciphertext files".into()));
+    }
+    if args.input(0) == "-" && args.input(1) == "-" {
+        return Err(CliError::Usage("only one ciphertext can be read from stdin".into()));
+    }
+    let pubkey = load_public_key(args)?;
+    let c1 = load_ciphertext(args.input(0), &pubkey)?;
+    let c2 = load_ciphertext(args.input(1), &pubkey)?;
+    let c = if subtract {
+        try_paillier_subtract(c1.as_biguint(), c2.as_biguint(), &pubkey)?
+    } else {
+        try_paillier_add(c1.as_biguint(), c2.as_biguint(), &pubkey)?
+    };
+    write_ciphertext(args, &c, &pubkey)
+}
+
+fn scalar_mul(args: &Args) -> CliResult<()> {
+    args.expect_positional(2)?;
+    let k = args
+        .positional
+        .get(1)
+        .ok_or_else(|| CliError::Usage("expected a ciphertext file and a scalar".into()))?;
+    let pubkey = load_public_key(args)?;
+    let c = load_ciphertext(args.input(0), &pubkey)?;
+    let k = encode_signed(&parse_integer(k)?, &pubkey.0);
+    let c = try_paillier_scalar_mul(c.as_biguint(), &k, &pubkey)?;
+    write_ciphertext(args, &c, &pubkey)
+}
+
+fn rerandomize(args: &Args) -> CliResult<()> {
+    args.expect_positional(1)?;
+    let pubkey = load_public_key(args)?;
+    let c = load_ciphertext(args.input(0), &pubkey)?;
+    let c = paillier_rerandomize(c.as_biguint(), &pubkey);
+    write_ciphertext(args, &c, &pubkey)
+}
+
+/// Describes a key or ciphertext file. With --public, ciphertexts are also
+/// checked against that key; an invalid ciphertext exits with status 1.
+fn inspect(args: &Args) -> CliResult<()> {
+    args.expect_positional(1)?;
+    let contents = read_input(args.input(0))?;
+    let first_line = contents.lines().next().unwrap_or("").trim();
+    let report = if first_line == PUBLIC_KEY_HEADER {
+        let (pubkey, proof) =
+            decode_public_key(&contents).ok_or_else(|| CliError::Failed("malformed public key".into()))?;
+        format!(
+            "public key\nkey id: {}\nmodulus bits: {}\nproof of gcd(n, phi(n)) = 1, no prime factor below {}: {}\n",
+            KeyId::of(&pubkey),
+            pubkey.0.bits(),
+            SMALL_PRIME_BOUND,
+            if verify_key(&pubkey, &proof) { "valid" } else { "INVALID" }
+        )
+    } else if first_line == PRIVATE_KEY_HEADER {
+        let (lambda, _) =
+            decode_private_key(&contents).ok_or_else(|| CliError::Failed("malformed private key".into()))?;
+        format!("private key\nlambda bits: {}\n", lambda.bits())
+    } else if is_container(&contents) {
+        let (key_id, c) = decode_ciphertext_container(&contents)
+            .ok_or_else(|| CliError::Failed("malformed ciphertext container".into()))?;
+        let mut report = format!("ciphertext\nkey id: {}\nbits: {}\n", key_id, c.bits());
+        if args.option("public").is_some() {
+            let pubkey = load_public_key(args)?;
+            Ciphertext::from_untrusted_container(&contents, &pubkey)?;
+            report.push_str("valid under the given public key\n");
+        }
+        report
+    } else {
+        let c = decode_ciphertext(&contents)
+            .ok_or_else(|| CliError::Failed("not a key or ciphertext file".into()))?;
+        let mut report = format!("ciphertext\nkey id: unknown\nbits: {}\n", c.bits());
+        if args.option("public").is_some() {
+            let pubkey = load_public_key(args)?;
+            Ciphertext::from_untrusted(c, &pubkey)?;
+            report.push_str("valid under the given public key\n");
+        }
+        report
+    };
+    write_output(args, &report)
+}
+
+fn run(raw: &[String]) -> CliResult<()> {
+    let (command, rest) = raw
+        .split_first()
+        .ok_or_else(|| CliError::Usage("missing command".into()))?;
+    let args = Args::parse(rest)?;
+    match command.as_str() {
+        "keygen" => keygen(&args),
+        "encrypt" => encrypt(&args),
+        "decrypt" => decrypt(&args),
+        "add" => binary_op(&args, false),
+        "sub" => binary_op(&args, true),
+        "scalar-mul" => scalar_mul(&args),
+        "rerandomize" => rerandomize(&args),
+        "inspect" => inspect(&args),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        other => Err(CliError::Usage(format!("unknown command `{}`", other))),
+    }
+}
+
+fn main() -> ExitCode {
+    let raw: Vec<String> = std::env::args().skip(1).collect();
+    match run(&raw) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            match e {
+                CliError::Usage(_) => ExitCode::from(2),
+                CliError::Failed(_) => ExitCode::from(1),
+            }
+        }
     }
 }
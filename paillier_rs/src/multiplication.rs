@@ -0,0 +1,194 @@
+use alloc::vec::Vec;
+use crate::arithmetic::{paillier_add, paillier_scalar_mul};
+use crate::decrypt::{check_ciphertext, paillier_decrypt};
+use crate::encrypt::{paillier_encrypt_with_randomness, paillier_encrypt_with_rng};
+use crate::error::PaillierError;
+use crate::keygen::{PrivateKey, PublicKey};
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::One;
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+/// Server → key holder: masked operand pairs \((Enc(a_i + r_i), Enc(b_i + s_i))\).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultiplyRequest {
+    pub pairs: Vec<(BigUint, BigUint)>,
+}
+
+/// Key holder → server: \(Enc((a_i + r_i)(b_i + s_i))\) for each requested pair.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultiplyResponse {
+    pub products: Vec<BigUint>,
+}
+
+/// Server state between [`MultiplicationServer::mask_with_rng`] and
+/// [`MultiplicationServer::unmask`]. The masks must stay secret from the key
+/// holder.
+#[derive(Clone, Debug)]
+pub struct PendingMultiplication {
+    operands: Vec<(BigUint, BigUint)>,
+    masks: Vec<(BigUint, BigUint)>,
+}
+
+/// Transport between the two roles.
+pub trait Channel {
+    /// Sends a request to the key holder and waits for its response.
+    fn exchange(&mut self, request: &MultiplyRequest) -> Result<MultiplyResponse, PaillierError>;
+}
+
+/// The party holding ciphertexts but not the private key, in a two-party
+/// multiplication protocol.
+///
+/// Paillier is only additively homomorphic, so \(Enc(a \cdot b)\) cannot be
+/// computed from \(Enc(a)\) and \(Enc(b)\) alone. With one round trip to the
+/// key holder it can, without revealing \(a\), \(b\) or \(ab\) to either side:
+///
+/// 1. The server draws \(r, s \in \mathbb{Z}_n\) uniformly and sends
+///    \(Enc(a + r)\) and \(Enc(b + s)\) ([`MultiplicationServer::mask_with_rng`]).
+/// 2. The key holder decrypts both, multiplies them and returns
+///    \(Enc((a + r)(b + s))\) ([`MultiplicationClient::respond_with_rng`]).
+/// 3. The server removes the masks ([`MultiplicationServer::unmask`]):
+///    \[ Enc(ab) = Enc((a + r)(b + s)) \cdot Enc(a)^{-s} \cdot Enc(b)^{-r} \cdot Enc(-rs). \]
+///
+/// The masked plaintexts are uniform in \(\mathbb{Z}_n\), so the key holder
+/// learns nothing about \(a\) and \(b\); the server only ever sees
+/// ciphertexts. Security holds against semi-honest parties: a key holder
+/// that returns a wrong product goes undetected. Requests are batched so a
+/// whole layer of products costs a single round trip.
+pub struct MultiplicationServer<'a> {
+    pubkey: &'a PublicKey,
+}
+
+impl<'a> MultiplicationServer<'a> {
+    pub fn new(pubkey: &'a PublicKey) -> Self {
+        MultiplicationServer { pubkey }
+    }
+
+    /// Masks each pair \((Enc(a_i), Enc(b_i))\) with fresh \(r_i, s_i\).
+    pub fn mask_with_rng<R: RngCore + CryptoRng + ?Sized>(
+        &self,
+        pairs: &[(BigUint, BigUint)],
+        rng: &mut R,
+    ) -> Result<(MultiplyRequest, PendingMultiplication), PaillierError> {
+        let n = &self.pubkey.0;
+        let mut masked = Vec::with_capacity(pairs.len());
+        let mut masks = Vec::with_capacity(pairs.len());
+        for (ca, cb) in pairs {
+            check_ciphertext(ca, self.pubkey)?;
+            check_ciphertext(cb, self.pubkey)?;
+            let r = rng.gen_biguint_below(n);
+            let s = rng.gen_biguint_below(n);
+            let ca_masked = paillier_add(ca, &paillier_encrypt_with_rng(self.pubkey, &r, rng), self.pubkey);
+            let cb_masked = paillier_add(cb, &paillier_encrypt_with_rng(self.pubkey, &s, rng), self.pubkey);
+            masked.push((ca_masked, cb_masked));
+            masks.push((r, s));
+        }
+        let pending = PendingMultiplication { operands: pairs.to_vec(), masks };
+        Ok((MultiplyRequest { pairs: masked }, pending))
+    }
+
+    /// Removes the masks from the key holder's response, yielding
+    /// \(Enc(a_i b_i)\) for each pair.
+    pub fn unmask(&self, pending: PendingMultiplication, response: &MultiplyResponse) -> Result<Vec<BigUint>, PaillierError> {
+        if response.products.len() != pending.operands.len() {
+            return Err(PaillierError::MalformedMessage);
+        }
+        let n = &self.pubkey.0;
+        let mut products = Vec::with_capacity(response.products.len());
+        for ((product, (ca, cb)), (r, s)) in response.products.iter().zip(&pending.operands).zip(&pending.masks) {
+            check_ciphertext(product, self.pubkey)?;
+            let neg_s = (n - s) % n;
+            let neg_r = (n - r) % n;
+            let neg_rs = (n - (r * s) % n) % n;
+            let mut c = paillier_add(product, &paillier_scalar_mul(ca, &neg_s, self.pubkey), self.pubkey);
+            c = paillier_add(&c, &paillier_scalar_mul(cb, &neg_r, self.pubkey), self.pubkey);
+            // Trivial encryption g^{-rs}; the product already carries fresh randomness.
+            c = paillier_add(&c, &paillier_encrypt_with_randomness(self.pubkey, &neg_rs, &BigUint::one()), self.pubkey);
+            products.push(c);
+        }
+        Ok(products)
+    }
+
+    /// Runs the whole protocol over `channel`.
+    pub fn multiply_with_rng<C: Channel + ?Sized, R: RngCore + CryptoRng + ?Sized>(
+        &self,
+        channel: &mut C,
+        pairs: &[(BigUint, BigUint)],
+        rng: &mut R,
+    ) -> Result<Vec<BigUint>, PaillierError> {
+        let (request, pending) = self.mask_with_rng(pairs, rng)?;
+        let response = channel.exchange(&request)?;
+        self.unmask(pending, &response)
+    }
+
+    /// Like [`multiply_with_rng`](Self::multiply_with_rng), drawing the
+    /// masks from `thread_rng`.
+    #[cfg(feature = "std")]
+    pub fn multiply<C: Channel + ?Sized>(
+        &self,
+        channel: &mut C,
+        pairs: &[(BigUint, BigUint)],
+    ) -> Result<Vec<BigUint>, PaillierError> {
+        self.multiply_with_rng(channel, pairs, &mut thread_rng())
+    }
+}
+
+/// The key holder, answering [`MultiplyRequest`]s.
+pub struct MultiplicationClient<'a> {
+    pubkey: &'a PublicKey,
+    privkey: &'a PrivateKey,
+}
+
+impl<'a> MultiplicationClient<'a> {
+    pub fn new(pubkey: &'a PublicKey, privkey: &'a PrivateKey) -> Self {
+        MultiplicationClient { pubkey, privkey }
+    }
+
+    /// Decrypts each masked pair, multiplies the plaintexts modulo n and
+    /// returns fresh encryptions of the products.
+    pub fn respond_with_rng<R: RngCore + CryptoRng + ?Sized>(
+        &self,
+        request: &MultiplyRequest,
+        rng: &mut R,
+    ) -> Result<MultiplyResponse, PaillierError> {
+        let n = &self.pubkey.0;
+        let mut products = Vec::with_capacity(request.pairs.len());
+        for (ca, cb) in &request.pairs {
+            check_ciphertext(ca, self.pubkey)?;
+            check_ciphertext(cb, self.pubkey)?;
+            let a = paillier_decrypt(self.privkey, self.pubkey, ca);
+            let b = paillier_decrypt(self.privkey, self.pubkey, cb);
+            products.push(paillier_encrypt_with_rng(self.pubkey, &((a * b) % n), rng));
+        }
+        Ok(MultiplyResponse { products })
+    }
+
+    #[cfg(feature = "std")]
+    pub fn respond(&self, request: &MultiplyRequest) -> Result<MultiplyResponse, PaillierError> {
+        self.respond_with_rng(request, &mut thread_rng())
+    }
+}
+
+/// Channel that hands requests straight to a [`MultiplicationClient`] in the
+/// same process. Meant for tests and for deployments where both roles run
+/// together.
+pub struct InMemoryChannel<'a, R> {
+    client: MultiplicationClient<'a>,
+    rng: R,
+    /// Number of round trips made so far.
+    pub round_trips: usize,
+}
+
+impl<'a, R: RngCore + CryptoRng> InMemoryChannel<'a, R> {
+    pub fn new(client: MultiplicationClient<'a>, rng: R) -> Self {
+        InMemoryChannel { client, rng, round_trips: 0 }
+    }
+}
+
+impl<R: RngCore + CryptoRng> Channel for InMemoryChannel<'_, R> {
+    fn exchange(&mut self, request: &MultiplyRequest) -> Result<MultiplyResponse, PaillierError> {
+        self.round_trips += 1;
+        self.client.respond_with_rng(request, &mut self.rng)
+    }
+}
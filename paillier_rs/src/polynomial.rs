@@ -0,0 +1,120 @@
+use alloc::vec::Vec;
+use crate::arithmetic::{paillier_add, paillier_scalar_mul};
+use crate::decrypt::paillier_decrypt;
+use crate::encrypt::{paillier_encrypt_with_randomness, paillier_encrypt_with_rng};
+use crate::error::PaillierError;
+use crate::fixed_point::{decode_signed, encode_signed, ratio_to_f64, scaled};
+use crate::keygen::{PrivateKey, PublicKey};
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, Pow, Zero};
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "std")]
+use rand::thread_rng;
+
+/// Polynomial \(\sum_i a_i x^i\) with signed fixed-point coefficients:
+/// `coefficients[i]` is \(\mathrm{round}(a_i \cdot scale)\).
+///
+/// Typical uses are low-degree approximations of activations, e.g. the
+/// sigmoid \(\sigma(x) \approx 0.5 + 0.197x - 0.004x^3\), and linear or
+/// quadratic scoring functions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polynomial {
+    pub coefficients: Vec<BigInt>,
+    pub scale: u64,
+}
+
+impl Polynomial {
+    /// Encodes real coefficients \(a_0, \dots, a_d\) at `scale`. Fails with
+    /// [`PaillierError::NotFinite`] if a scaled coefficient is not finite.
+    pub fn from_f64(coefficients: &[f64], scale: u64) -> Result<Self, PaillierError> {
+        let coefficients = coefficients.iter().map(|&a| scaled(a, scale)).collect::<Result<_, _>>()?;
+        Ok(Polynomial { coefficients, scale })
+    }
+
+    /// Degree of the polynomial, ignoring zero leading coefficients.
+    pub fn degree(&self) -> usize {
+        self.coefficients.iter().rposition(|a| !a.is_zero()).unwrap_or(0)
+    }
+}
+
+/// Ciphertext of a fixed-point value whose plaintext is \(v \cdot scale\),
+/// encoded as a signed integer modulo n.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScaledCiphertext {
+    pub c: BigUint,
+    pub scale: BigUint,
+}
+
+/// Client side: encrypts \(\tilde{x}, \tilde{x}^2, \dots, \tilde{x}^d\) for
+/// \(\tilde{x} = \mathrm{round}(x \cdot scale)\). The `i`-th power is exact
+/// at scale \(scale^{i}\), so the powers must satisfy
+/// \(|\tilde{x}|^d < n/2\). Fails with [`PaillierError::NotFinite`] if
+/// \(x \cdot scale\) is not finite.
+#[cfg(feature = "std")]
+pub fn encrypt_powers(pubkey: &PublicKey, x: f64, degree: usize, scale: u64) -> Result<Vec<BigUint>, PaillierError> {
+    encrypt_powers_with_rng(pubkey, x, degree, scale, &mut thread_rng())
+}
+
+/// Like [`encrypt_powers`], drawing the encryption randomness from `rng`.
+pub fn encrypt_powers_with_rng<R: RngCore + CryptoRng + ?Sized>(
+    pubkey: &PublicKey,
+    x: f64,
+    degree: usize,
+    scale: u64,
+    rng: &mut R,
+) -> Result<Vec<BigUint>, PaillierError> {
+    let n = &pubkey.0;
+    let x = scaled(x, scale)?;
+    let mut power = BigInt::one();
+    Ok((0..degree)
+        .map(|_| {
+            power *= &x;
+            paillier_encrypt_with_rng(pubkey, &encode_signed(&power, n), rng)
+        })
+        .collect())
+}
+
+/// Server side: evaluates `poly` on \(Enc(x), Enc(x^2), \dots\), where
+/// `powers[i - 1]` encrypts \(x^i\) at scale \(input\_scale^i\) as produced
+/// by [`encrypt_powers`].
+///
+/// Each term \(a_i x^i\) sits at scale \(poly.scale \cdot input\_scale^i\);
+/// it is lifted to the common scale \(poly.scale \cdot input\_scale^d\) by
+/// folding \(input\_scale^{d-i}\) into the plaintext multiplier, so only one
+/// scalar multiplication is needed per term. The constant term is added as
+/// the trivial encryption \(g^{a_0}\); the result is only randomized by the
+/// non-constant terms. The decoded result must stay below \(n/2\) in
+/// magnitude. Fails with [`PaillierError::MissingPowers`] if there are
+/// fewer powers than the degree of `poly`.
+pub fn evaluate_polynomial(
+    poly: &Polynomial,
+    powers: &[BigUint],
+    input_scale: u64,
+    pubkey: &PublicKey,
+) -> Result<ScaledCiphertext, PaillierError> {
+    let n = &pubkey.0;
+    let degree = poly.degree();
+    if powers.len() < degree {
+        return Err(PaillierError::MissingPowers { degree, found: powers.len() });
+    }
+
+    let input_scale = BigUint::from(input_scale);
+    let lift = |i: usize| Pow::pow(&input_scale, degree - i);
+
+    let constant = poly.coefficients.first().cloned().unwrap_or_default() * BigInt::from(lift(0));
+    let mut acc = paillier_encrypt_with_randomness(pubkey, &encode_signed(&constant, n), &BigUint::one());
+    for (i, a) in poly.coefficients.iter().enumerate().take(degree + 1).skip(1) {
+        if a.is_zero() {
+            continue;
+        }
+        let k = encode_signed(&(a * BigInt::from(lift(i))), n);
+        acc = paillier_add(&acc, &paillier_scalar_mul(&powers[i - 1], &k, pubkey), pubkey);
+    }
+    Ok(ScaledCiphertext { c: acc, scale: BigUint::from(poly.scale) * lift(0) })
+}
+
+/// Decrypts a [`ScaledCiphertext`] to `f64`.
+pub fn decrypt_scaled(privkey: &PrivateKey, pubkey: &PublicKey, value: &ScaledCiphertext) -> f64 {
+    let m = decode_signed(&paillier_decrypt(privkey, pubkey, &value.c), &pubkey.0);
+    ratio_to_f64(&m, &BigInt::from(value.scale.clone()))
+}
@@ -0,0 +1,197 @@
+use crate::arithmetic::paillier_scalar_mul;
+use crate::encrypt::{paillier_encrypt_with_randomness, sample_randomness_with_rng};
+use crate::keygen::PublicKey;
+use crate::transcript::Transcript;
+use num_bigint::{BigUint, RandBigInt};
+use num_integer::Integer;
+use num_traits::{One, Zero};
+use rand::{CryptoRng, RngCore};
+
+/// Upper bound on the challenge length in bits.
+pub const MAX_CHALLENGE_BITS: u64 = 128;
+
+/// Challenges must be smaller than the smallest prime factor of n for the
+/// proofs to be sound, so for small demo keys the challenge is shortened to
+/// just under half the modulus size. Degenerate moduli of fewer than two
+/// bits get empty challenges rather than an underflow; their proofs are
+/// rejected by the range checks anyway.
+fn challenge_bits(n: &BigUint) -> u64 {
+    MAX_CHALLENGE_BITS.min((n.bits() / 2).saturating_sub(1))
+}
+
+/// Proof that the prover knows \((m, r)\) with \(c = g^m r^n \mod n^2\).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaintextKnowledgeProof {
+    /// First message \(A = g^a b^n \mod n^2\).
+    pub commitment: BigUint,
+    /// \(z = a + e \cdot m \mod n\).
+    pub z: BigUint,
+    /// \(w = b \cdot r^e \mod n\).
+    pub w: BigUint,
+}
+
+/// Proof that \(d = c^k \mod n^2\) for the scalar \(k\) inside the commitment
+/// \(C_k = g^k s^n \mod n^2\).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScalarMulProof {
+    /// \(A = c^x u^n \mod n^2\).
+    pub a: BigUint,
+    /// \(B = g^x v^n \mod n^2\).
+    pub b: BigUint,
+    /// \(z = x + e \cdot k \mod n\).
+    pub z: BigUint,
+    /// \(w_1 = u \cdot c^t \mod n\), with \(t = \lfloor (x + e k) / n \rfloor\).
+    pub w1: BigUint,
+    /// \(w_2 = v \cdot s^e \mod n\).
+    pub w2: BigUint,
+}
+
+/// Returns true if `x` is a valid response in \(\mathbb{Z}_n^*\).
+fn is_unit(x: &BigUint, n: &BigUint) -> bool {
+    !x.is_zero() && x < n && x.gcd(n).is_one()
+}
+
+/// Proves knowledge of the plaintext `m` and randomness `r` of `c`, drawing
+/// the prover's nonces from `rng`.
+///
+/// Because g = n + 1 has order n modulo n², the exponent can be reduced
+/// mod n without a correction term in `w`.
+pub fn prove_plaintext_knowledge<R: RngCore + CryptoRng + ?Sized>(
+    pubkey: &PublicKey,
+    c: &BigUint,
+    m: &BigUint,
+    r: &BigUint,
+    transcript: &mut Transcript,
+    rng: &mut R,
+) -> PlaintextKnowledgeProof {
+    let (n, g) = pubkey;
+    let n_sq = n * n;
+    let a = rng.gen_biguint_below(n);
+    let b = sample_randomness_with_rng(n, rng);
+    let commitment = (g.modpow(&a, &n_sq) * b.modpow(n, &n_sq)) % &n_sq;
+
+    transcript.append_biguint(b"n", n);
+    transcript.append_biguint(b"c", c);
+    transcript.append_biguint(b"A", &commitment);
+    let e = transcript.challenge_biguint(b"e", challenge_bits(n));
+
+    let z = (&a + &e * m) % n;
+    let w = (&b * r.modpow(&e, n)) % n;
+    PlaintextKnowledgeProof { commitment, z, w }
+}
+
+/// Verifies a [`PlaintextKnowledgeProof`] for ciphertext `c`:
+///
+/// \[ g^z \cdot w^n \equiv A \cdot c^e \mod n^2. \]
+pub fn verify_plaintext_knowledge(
+    pubkey: &PublicKey,
+    c: &BigUint,
+    proof: &PlaintextKnowledgeProof,
+    transcript: &mut Transcript,
+) -> bool {
+    let (n, g) = pubkey;
+    let n_sq = n * n;
+    if proof.z >= *n || !is_unit(&proof.w, n) || proof.commitment >= n_sq {
+        return false;
+    }
+
+    transcript.append_biguint(b"n", n);
+    transcript.append_biguint(b"c", c);
+    transcript.append_biguint(b"A", &proof.commitment);
+    let e = transcript.challenge_biguint(b"e", challenge_bits(n));
+
+    let lhs = (g.modpow(&proof.z, &n_sq) * proof.w.modpow(n, &n_sq)) % &n_sq;
+    let rhs = (&proof.commitment * c.modpow(&e, &n_sq)) % &n_sq;
+    lhs == rhs
+}
+
+/// Commits to a scalar `k` by encrypting it. Returns the commitment
+/// \(C_k\) and the opening randomness `s`, which the prover keeps.
+pub fn commit_scalar<R: RngCore + CryptoRng + ?Sized>(pubkey: &PublicKey, k: &BigUint, rng: &mut R) -> (BigUint, BigUint) {
+    let s = sample_randomness_with_rng(&pubkey.0, rng);
+    (paillier_encrypt_with_randomness(pubkey, k, &s), s)
+}
+
+fn absorb_scalar_mul_statement(
+    transcript: &mut Transcript,
+    n: &BigUint,
+    c: &BigUint,
+    commitment: &BigUint,
+    d: &BigUint,
+) {
+    transcript.append_biguint(b"n", n);
+    transcript.append_biguint(b"c", c);
+    transcript.append_biguint(b"C_k", commitment);
+    transcript.append_biguint(b"d", d);
+}
+
+/// Proves that `paillier_scalar_mul(c, k, pubkey)` was computed with the
+/// scalar `k` hidden in `commitment` (opened by `s`).
+///
+/// The client sends `c`, the server publishes `commitment` once for each
+/// agreed weight and returns \(d = c^k\) together with this proof. Sums of
+/// such products need no proof, since the client can recompute
+/// [`paillier_add`](crate::arithmetic::paillier_add) itself.
+pub fn prove_scalar_mul<R: RngCore + CryptoRng + ?Sized>(
+    pubkey: &PublicKey,
+    c: &BigUint,
+    commitment: &BigUint,
+    k: &BigUint,
+    s: &BigUint,
+    transcript: &mut Transcript,
+    rng: &mut R,
+) -> ScalarMulProof {
+    let (n, g) = pubkey;
+    let n_sq = n * n;
+    let d = paillier_scalar_mul(c, k, pubkey);
+
+    let x = rng.gen_biguint_below(n);
+    let u = sample_randomness_with_rng(n, rng);
+    let v = sample_randomness_with_rng(n, rng);
+    let a = (c.modpow(&x, &n_sq) * u.modpow(n, &n_sq)) % &n_sq;
+    let b = (g.modpow(&x, &n_sq) * v.modpow(n, &n_sq)) % &n_sq;
+
+    absorb_scalar_mul_statement(transcript, n, c, commitment, &d);
+    transcript.append_biguint(b"A", &a);
+    transcript.append_biguint(b"B", &b);
+    let e = transcript.challenge_biguint(b"e", challenge_bits(n));
+
+    // c^(x + e·k) = c^z · (c^t)^n, and (c^t)^n only depends on c^t mod n.
+    let (t, z) = (&x + &e * k).div_rem(n);
+    let w1 = (&u * c.modpow(&t, n)) % n;
+    let w2 = (&v * s.modpow(&e, n)) % n;
+    ScalarMulProof { a, b, z, w1, w2 }
+}
+
+/// Verifies a [`ScalarMulProof`] that `d` is `c` multiplied by the scalar
+/// committed in `commitment`:
+///
+/// \[ c^z w_1^n \equiv A \cdot d^e, \quad g^z w_2^n \equiv B \cdot C_k^e \pmod{n^2}. \]
+pub fn verify_scalar_mul(
+    pubkey: &PublicKey,
+    c: &BigUint,
+    commitment: &BigUint,
+    d: &BigUint,
+    proof: &ScalarMulProof,
+    transcript: &mut Transcript,
+) -> bool {
+    let (n, g) = pubkey;
+    let n_sq = n * n;
+    if proof.z >= *n || !is_unit(&proof.w1, n) || !is_unit(&proof.w2, n) {
+        return false;
+    }
+    if proof.a >= n_sq || proof.b >= n_sq {
+        return false;
+    }
+
+    absorb_scalar_mul_statement(transcript, n, c, commitment, d);
+    transcript.append_biguint(b"A", &proof.a);
+    transcript.append_biguint(b"B", &proof.b);
+    let e = transcript.challenge_biguint(b"e", challenge_bits(n));
+
+    let lhs1 = (c.modpow(&proof.z, &n_sq) * proof.w1.modpow(n, &n_sq)) % &n_sq;
+    let rhs1 = (&proof.a * d.modpow(&e, &n_sq)) % &n_sq;
+    let lhs2 = (g.modpow(&proof.z, &n_sq) * proof.w2.modpow(n, &n_sq)) % &n_sq;
+    let rhs2 = (&proof.b * commitment.modpow(&e, &n_sq)) % &n_sq;
+    lhs1 == rhs1 && lhs2 == rhs2
+}
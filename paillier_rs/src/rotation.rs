@@ -0,0 +1,135 @@
+use crate::decrypt::{check_ciphertext, paillier_decrypt};
+use crate::encrypt::paillier_encrypt;
+use crate::error::PaillierError;
+use crate::fixed_point::{decode_signed, encode_signed};
+use crate::keygen::{PrivateKey, PublicKey};
+use num_bigint::{BigInt, BigUint};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Header line of an encoded [`Checkpoint`].
+pub const CHECKPOINT_HEADER: &str = "paillier-reencrypt-checkpoint v1";
+
+/// Moves ciphertexts from a retired key to its replacement.
+///
+/// Re-encryption decrypts with the old private key and encrypts the plaintext
+/// under the new public key, so it must run wherever the old private key is
+/// held. Plaintexts are signed integers encoded with
+/// [`encode_signed`]: a negative value, held as \(n_{old} - |m|\), is
+/// re-encoded modulo the new n rather than copied as a residue. The new
+/// modulus must be at least as large as the old one for values to survive
+/// the move.
+pub struct Reencryptor<'a> {
+    pub old_pubkey: &'a PublicKey,
+    pub old_privkey: &'a PrivateKey,
+    pub new_pubkey: &'a PublicKey,
+}
+
+impl<'a> Reencryptor<'a> {
+    /// Fails with [`PaillierError::ModulusTooSmall`] if the new modulus is
+    /// smaller than the old one.
+    pub fn new(
+        old_pubkey: &'a PublicKey,
+        old_privkey: &'a PrivateKey,
+        new_pubkey: &'a PublicKey,
+    ) -> Result<Self, PaillierError> {
+        if new_pubkey.0 < old_pubkey.0 {
+            return Err(PaillierError::ModulusTooSmall);
+        }
+        Ok(Reencryptor { old_pubkey, old_privkey, new_pubkey })
+    }
+
+    /// Decrypts `c` under the old key to a signed integer. Fails with
+    /// [`PaillierError::InvalidCiphertext`] unless `c` is a unit modulo
+    /// \(n_{old}^2\).
+    pub fn decrypt_signed(&self, c: &BigUint) -> Result<BigInt, PaillierError> {
+        check_ciphertext(c, self.old_pubkey)?;
+        let m = paillier_decrypt(self.old_privkey, self.old_pubkey, c);
+        Ok(decode_signed(&m, &self.old_pubkey.0))
+    }
+
+    /// Encrypts the signed integer `m` under the new key.
+    pub fn encrypt_signed(&self, m: &BigInt) -> BigUint {
+        paillier_encrypt(self.new_pubkey, &encode_signed(m, &self.new_pubkey.0))
+    }
+
+    /// Re-encrypts a single ciphertext, see [`decrypt_signed`](Self::decrypt_signed).
+    pub fn reencrypt(&self, c: &BigUint) -> Result<BigUint, PaillierError> {
+        Ok(self.encrypt_signed(&self.decrypt_signed(c)?))
+    }
+
+    /// Re-encrypts a batch of `(row id, ciphertext)` pairs, keeping the ids.
+    /// Fails on the first invalid ciphertext.
+    pub fn reencrypt_batch(&self, batch: &[(i64, BigUint)]) -> Result<Vec<(i64, BigUint)>, PaillierError> {
+        batch.iter().map(|(id, c)| Ok((*id, self.reencrypt(c)?))).collect()
+    }
+}
+
+/// Progress of a re-encryption run. Rows are processed in increasing id
+/// order, so a run interrupted after a batch was committed resumes from
+/// `last_id + 1`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Largest row id whose re-encryption has been committed.
+    pub last_id: i64,
+    /// Number of rows re-encrypted so far.
+    pub rows_done: u64,
+}
+
+impl Checkpoint {
+    /// Records a committed batch.
+    pub fn advance(&mut self, batch: &[(i64, BigUint)]) {
+        if let Some(max_id) = batch.iter().map(|(id, _)| *id).max() {
+            self.last_id = self.last_id.max(max_id);
+        }
+        self.rows_done += batch.len() as u64;
+    }
+
+    /// Encodes the checkpoint as text:
+    ///
+    /// ```text
+    /// paillier-reencrypt-checkpoint v1
+    /// last_id=<integer>
+    /// rows_done=<integer>
+    /// ```
+    pub fn encode(&self) -> String {
+        format!("{}\nlast_id={}\nrows_done={}\n", CHECKPOINT_HEADER, self.last_id, self.rows_done)
+    }
+
+    /// Decodes the output of [`Checkpoint::encode`].
+    pub fn decode(encoded: &str) -> Option<Self> {
+        let mut lines = encoded.lines().map(str::trim).filter(|l| !l.is_empty());
+        if lines.next()? != CHECKPOINT_HEADER {
+            return None;
+        }
+        let mut checkpoint = Checkpoint::default();
+        for line in lines {
+            match line.split_once('=')? {
+                ("last_id", v) => checkpoint.last_id = v.parse().ok()?,
+                ("rows_done", v) => checkpoint.rows_done = v.parse().ok()?,
+                _ => return None,
+            }
+        }
+        Some(checkpoint)
+    }
+
+    /// Loads a checkpoint file, returning `None` if it does not exist yet.
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(s) => Checkpoint::decode(&s)
+                .map(Some)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed checkpoint file")),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes the checkpoint atomically (write to a temporary file, then
+    /// rename), so a crash never leaves a truncated checkpoint behind.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, self.encode())?;
+        fs::rename(&tmp, path)
+    }
+}
@@ -0,0 +1,111 @@
+use crate::arithmetic::paillier_add;
+use crate::decrypt::paillier_decrypt;
+use crate::error::PaillierError;
+use crate::fixed_point::{decode_signed, ratio_to_f64};
+use crate::keygen::{PrivateKey, PublicKey};
+use num_bigint::{BigInt, BigUint};
+use num_traits::One;
+
+/// Encrypted sum of a column together with its (public) number of elements.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncryptedSum {
+    pub sum: BigUint,
+    pub count: u64,
+}
+
+/// Encrypted first and second moments of a column. `sum_sq` is the sum of
+/// client-supplied encryptions of \(x_i^2\), since Paillier cannot square an
+/// encrypted value on its own.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncryptedMoments {
+    pub sum: BigUint,
+    pub sum_sq: BigUint,
+    pub count: u64,
+}
+
+/// Decrypted statistics of a column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Statistics {
+    pub count: u64,
+    pub sum: f64,
+    pub mean: f64,
+    /// Population variance \(\frac{1}{N}\sum x_i^2 - \mu^2\).
+    pub variance: f64,
+}
+
+/// Folds ciphertexts with [`paillier_add`]. The empty sum is the trivial
+/// encryption of 0, namely 1.
+fn fold_add(values: &[BigUint], pubkey: &PublicKey) -> BigUint {
+    values
+        .iter()
+        .fold(BigUint::one(), |acc, c| paillier_add(&acc, c, pubkey))
+}
+
+/// Computes the encrypted sum and the count of `values`.
+pub fn encrypted_sum(values: &[BigUint], pubkey: &PublicKey) -> EncryptedSum {
+    EncryptedSum {
+        sum: fold_add(values, pubkey),
+        count: values.len() as u64,
+    }
+}
+
+/// Computes encrypted sums of `values` and of `squares`, where `squares[i]`
+/// encrypts the square of the plaintext behind `values[i]` at the squared
+/// fixed-point scale. Fails with [`PaillierError::LengthMismatch`] unless
+/// every value has its square.
+pub fn encrypted_moments(
+    values: &[BigUint],
+    squares: &[BigUint],
+    pubkey: &PublicKey,
+) -> Result<EncryptedMoments, PaillierError> {
+    if values.len() != squares.len() {
+        return Err(PaillierError::LengthMismatch { expected: values.len(), found: squares.len() });
+    }
+    Ok(EncryptedMoments {
+        sum: fold_add(values, pubkey),
+        sum_sq: fold_add(squares, pubkey),
+        count: values.len() as u64,
+    })
+}
+
+/// Decrypts an [`EncryptedSum`] into `(sum, mean)` for plaintexts encoded as
+/// fixed-point integers with the given `scale` (see
+/// [`encode_f64`](crate::fixed_point::encode_f64)). The mean of an empty
+/// column is NaN.
+pub fn decrypt_mean(privkey: &PrivateKey, pubkey: &PublicKey, enc: &EncryptedSum, scale: u64) -> (f64, f64) {
+    let n = &pubkey.0;
+    let sum = decode_signed(&paillier_decrypt(privkey, pubkey, &enc.sum), n);
+    let scale = BigInt::from(scale);
+    let mean = if enc.count == 0 {
+        f64::NAN
+    } else {
+        ratio_to_f64(&sum, &(&scale * enc.count))
+    };
+    (ratio_to_f64(&sum, &scale), mean)
+}
+
+/// Decrypts [`EncryptedMoments`] into [`Statistics`].
+///
+/// The variance is computed exactly over the integers as
+/// \(\frac{N \sum x_i^2 - (\sum x_i)^2}{N^2 \cdot scale^2}\) and only then
+/// converted to `f64`, so it does not suffer from cancellation.
+pub fn decrypt_statistics(privkey: &PrivateKey, pubkey: &PublicKey, enc: &EncryptedMoments, scale: u64) -> Statistics {
+    let n = &pubkey.0;
+    let sum = decode_signed(&paillier_decrypt(privkey, pubkey, &enc.sum), n);
+    let sum_sq = decode_signed(&paillier_decrypt(privkey, pubkey, &enc.sum_sq), n);
+    let scale = BigInt::from(scale);
+    let count = BigInt::from(enc.count);
+    let (mean, variance) = if enc.count == 0 {
+        (f64::NAN, f64::NAN)
+    } else {
+        let numerator = &count * &sum_sq - &sum * &sum;
+        let denominator = &count * &count * &scale * &scale;
+        (ratio_to_f64(&sum, &(&scale * &count)), ratio_to_f64(&numerator, &denominator))
+    };
+    Statistics {
+        count: enc.count,
+        sum: ratio_to_f64(&sum, &scale),
+        mean,
+        variance,
+    }
+}
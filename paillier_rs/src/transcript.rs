@@ -0,0 +1,62 @@
+use alloc::vec::Vec;
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+/// A Fiat-Shamir transcript built on SHA-256.
+///
+/// Prover and verifier feed the same public values into the transcript in the
+/// same order; challenges are then derived from everything absorbed so far, so
+/// a proof cannot be replayed for a different statement. Every message is
+/// length-prefixed and labelled to keep the encoding unambiguous.
+#[derive(Clone, Debug)]
+pub struct Transcript {
+    state: [u8; 32],
+}
+
+impl Transcript {
+    /// Starts a transcript bound to a protocol-specific `label`.
+    pub fn new(label: &[u8]) -> Self {
+        let mut transcript = Transcript { state: [0u8; 32] };
+        transcript.append_message(b"dom-sep", label);
+        transcript
+    }
+
+    /// Absorbs a labelled byte string.
+    pub fn append_message(&mut self, label: &[u8], message: &[u8]) {
+        let mut hasher = Sha256::new();
+        hasher.update(self.state);
+        hasher.update((label.len() as u64).to_be_bytes());
+        hasher.update(label);
+        hasher.update((message.len() as u64).to_be_bytes());
+        hasher.update(message);
+        self.state = hasher.finalize().into();
+    }
+
+    /// Absorbs a labelled big integer in big-endian form.
+    pub fn append_biguint(&mut self, label: &[u8], value: &BigUint) {
+        self.append_message(label, &value.to_bytes_be());
+    }
+
+    /// Derives a challenge of `bits` bits and absorbs it, so that later
+    /// challenges depend on earlier ones.
+    pub fn challenge_biguint(&mut self, label: &[u8], bits: u64) -> BigUint {
+        let len = bits.div_ceil(8) as usize;
+        let mut out = Vec::with_capacity(len + 32);
+        let mut counter: u32 = 0;
+        while out.len() < len {
+            let mut hasher = Sha256::new();
+            hasher.update(self.state);
+            hasher.update(b"challenge");
+            hasher.update((label.len() as u64).to_be_bytes());
+            hasher.update(label);
+            hasher.update(counter.to_be_bytes());
+            out.extend_from_slice(&hasher.finalize());
+            counter += 1;
+        }
+        out.truncate(len);
+        let mut challenge = BigUint::from_bytes_be(&out);
+        challenge &= (BigUint::from(1u32) << bits) - 1u32;
+        self.append_biguint(label, &challenge);
+        challenge
+    }
+}
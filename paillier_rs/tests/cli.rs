@@ -0,0 +1,134 @@
+#![cfg(feature = "std")]
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// A fresh directory in the temporary directory for one test's files.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("paillier-rs-cli-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Runs the CLI with `args`, feeding `stdin` to it.
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_paillier_rs"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Runs the CLI and returns its stdout, failing the test on a non-zero exit.
+fn succeed(args: &[&str], stdin: &str) -> String {
+    let output = run(args, stdin);
+    assert!(output.status.success(), "{:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn path_str(path: &Path) -> &str {
+    path.to_str().unwrap()
+}
+
+#[test]
+fn keygen_encrypt_add_decrypt_round_trip() {
+    let dir = temp_dir("round-trip");
+    let prefix = dir.join("key");
+    succeed(&["keygen", "--bits", "64", "--out", path_str(&prefix)], "");
+    let public = path_str(&prefix.with_extension("pub")).to_string();
+    let private = path_str(&prefix.with_extension("key")).to_string();
+
+    let a = dir.join("a.ct");
+    let b = dir.join("b.ct");
+    succeed(&["encrypt", "--public", &public, "--out", path_str(&a)], "1234\n");
+    // The ciphertext can also go to stdout.
+    fs::write(&b, succeed(&["encrypt", "--public", &public], "-34")).unwrap();
+
+    let sum = succeed(&["add", "--public", &public, path_str(&a), path_str(&b)], "");
+    let decrypted = succeed(&["decrypt", "--public", &public, "--private", &private, "--signed"], &sum);
+    assert_eq!(decrypted, "1200\n");
+
+    let difference = succeed(&["sub", "--public", &public, path_str(&b), path_str(&a)], "");
+    let decrypted = succeed(&["decrypt", "--public", &public, "--private", &private, "--signed"], &difference);
+    assert_eq!(decrypted, "-1268\n");
+
+    let report = succeed(&["inspect", &public], "");
+    assert!(report.starts_with("public key\nkey id: "), "{}", report);
+    assert!(report.ends_with("\nproof of gcd(n, phi(n)) = 1, no prime factor below 65536: valid\n"), "{}", report);
+
+    let report = succeed(&["inspect", "--public", &public], &sum);
+    assert!(report.starts_with("ciphertext\nkey id: "), "{}", report);
+    assert!(report.ends_with("valid under the given public key\n"), "{}", report);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn keygen_never_overwrites_key_files() {
+    let dir = temp_dir("overwrite");
+    let prefix = dir.join("key");
+    succeed(&["keygen", "--bits", "64", "--out", path_str(&prefix)], "");
+    let public = fs::read_to_string(prefix.with_extension("pub")).unwrap();
+    let private = fs::read_to_string(prefix.with_extension("key")).unwrap();
+
+    let output = run(&["keygen", "--bits", "64", "--out", path_str(&prefix)], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(fs::read_to_string(prefix.with_extension("pub")).unwrap(), public);
+    assert_eq!(fs::read_to_string(prefix.with_extension("key")).unwrap(), private);
+
+    // A lone private key is kept too, and no public key is left behind.
+    fs::remove_file(prefix.with_extension("pub")).unwrap();
+    let output = run(&["keygen", "--bits", "64", "--out", path_str(&prefix)], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!prefix.with_extension("pub").exists());
+    assert_eq!(fs::read_to_string(prefix.with_extension("key")).unwrap(), private);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn usage_errors_exit_with_2() {
+    for args in [
+        &[][..],
+        &["frobnicate"],
+        &["keygen", "--bits"],
+        &["keygen", "--bits", "many", "--out", "key"],
+        &["encrypt", "plaintext", "extra"],
+        &["add", "--public", "key.pub", "only-one.ct"],
+        &["add", "--public", "key.pub", "-", "-"],
+    ] {
+        let output = run(args, "");
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.starts_with("error: ") && stderr.contains("usage: paillier_rs"), "{}", stderr);
+    }
+}
+
+#[test]
+fn malformed_ciphertext_exits_with_1() {
+    let dir = temp_dir("malformed");
+    let prefix = dir.join("key");
+    succeed(&["keygen", "--bits", "64", "--out", path_str(&prefix)], "");
+    let public = path_str(&prefix.with_extension("pub")).to_string();
+    let private = path_str(&prefix.with_extension("key")).to_string();
+
+    // Not a number, zero (not a unit), and a value at least n^2.
+    let n_squared = "1".to_string() + &"0".repeat(80);
+    for ciphertext in ["not a ciphertext", "0", &n_squared] {
+        let output = run(&["decrypt", "--public", &public, "--private", &private], ciphertext);
+        assert_eq!(output.status.code(), Some(1), "{:?}", ciphertext);
+        assert!(output.stdout.is_empty());
+        let output = run(&["rerandomize", "--public", &public], ciphertext);
+        assert_eq!(output.status.code(), Some(1), "{:?}", ciphertext);
+    }
+
+    let missing = dir.join("missing.ct");
+    let output = run(&["decrypt", "--public", &public, "--private", &private, path_str(&missing)], "");
+    assert_eq!(output.status.code(), Some(1));
+    fs::remove_dir_all(&dir).unwrap();
+}
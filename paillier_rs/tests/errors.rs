@@ -0,0 +1,109 @@
+#![cfg(feature = "std")]
+
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use paillier_rs::arithmetic::{
+    try_paillier_add, try_paillier_compare, try_paillier_difference, try_paillier_scalar_mul,
+    try_paillier_subtract,
+};
+use paillier_rs::decrypt::try_paillier_decrypt;
+use paillier_rs::encrypt::try_paillier_encrypt;
+use paillier_rs::error::PaillierError;
+use paillier_rs::keygen::{
+    check_key_pair, try_paillier_keygen, try_paillier_keygen_with_proof, PrivateKey, PublicKey, MIN_PRIME_BITS,
+};
+
+fn keys() -> (PublicKey, PrivateKey) {
+    try_paillier_keygen(MIN_PRIME_BITS).unwrap()
+}
+
+/// Ciphertexts outside Z*_{n²}: zero, n² itself, and a multiple of n.
+fn invalid_ciphertexts(pubkey: &PublicKey) -> Vec<BigUint> {
+    let n = &pubkey.0;
+    vec![BigUint::zero(), n * n, n * 3u32]
+}
+
+#[test]
+fn keygen_rejects_small_primes() {
+    assert_eq!(
+        try_paillier_keygen(MIN_PRIME_BITS - 1),
+        Err(PaillierError::InvalidKeySize(MIN_PRIME_BITS - 1))
+    );
+    assert_eq!(try_paillier_keygen(0), Err(PaillierError::InvalidKeySize(0)));
+    assert_eq!(try_paillier_keygen_with_proof(8).err(), Some(PaillierError::InvalidKeySize(8)));
+}
+
+#[test]
+fn encrypt_rejects_plaintext_out_of_range() {
+    let (pubkey, privkey) = keys();
+    let n = pubkey.0.clone();
+    assert_eq!(try_paillier_encrypt(&pubkey, &n), Err(PaillierError::PlaintextOutOfRange));
+    let max = &n - 1u32;
+    let c = try_paillier_encrypt(&pubkey, &max).unwrap();
+    assert_eq!(try_paillier_decrypt(&privkey, &pubkey, &c), Ok(max));
+}
+
+#[test]
+fn decrypt_rejects_invalid_ciphertexts() {
+    let (pubkey, privkey) = keys();
+    for c in invalid_ciphertexts(&pubkey) {
+        assert_eq!(try_paillier_decrypt(&privkey, &pubkey, &c), Err(PaillierError::InvalidCiphertext));
+    }
+}
+
+#[test]
+fn decrypt_rejects_foreign_private_key() {
+    let (pubkey, _) = keys();
+    let (_, other_privkey) = keys();
+    let c = try_paillier_encrypt(&pubkey, &BigUint::one()).unwrap();
+    assert_eq!(try_paillier_decrypt(&other_privkey, &pubkey, &c), Err(PaillierError::KeyMismatch));
+}
+
+#[test]
+fn key_pair_check_rejects_keys_that_do_not_decrypt() {
+    let (pubkey, privkey) = keys();
+    assert_eq!(check_key_pair(&pubkey, &privkey), Ok(()));
+    // λ·μ ≡ 1 (mod n) holds for these, but neither decrypts.
+    let (n, _) = &pubkey;
+    for fake in [(BigUint::one(), BigUint::one()), (n - 1u32, n - 1u32)] {
+        assert_eq!((&fake.0 * &fake.1) % n, BigUint::one());
+        assert_eq!(check_key_pair(&pubkey, &fake), Err(PaillierError::KeyMismatch));
+    }
+    let (_, other_privkey) = keys();
+    assert_eq!(check_key_pair(&pubkey, &other_privkey), Err(PaillierError::KeyMismatch));
+}
+
+#[test]
+fn arithmetic_rejects_invalid_ciphertexts() {
+    let (pubkey, privkey) = keys();
+    let valid = try_paillier_encrypt(&pubkey, &BigUint::from(7u32)).unwrap();
+    let k = BigUint::from(3u32);
+    let r = BigUint::from(1000u32);
+    for bad in invalid_ciphertexts(&pubkey) {
+        let err = Err(PaillierError::InvalidCiphertext);
+        assert_eq!(try_paillier_add(&valid, &bad, &pubkey), err);
+        assert_eq!(try_paillier_add(&bad, &valid, &pubkey), err);
+        assert_eq!(try_paillier_scalar_mul(&bad, &k, &pubkey), err);
+        assert_eq!(try_paillier_subtract(&valid, &bad, &pubkey), err);
+        assert_eq!(try_paillier_subtract(&bad, &valid, &pubkey), err);
+        assert_eq!(try_paillier_difference(&valid, &bad, &pubkey, &privkey), Err(PaillierError::InvalidCiphertext));
+        assert_eq!(try_paillier_compare(&bad, &valid, &pubkey, &privkey, &r), Err(PaillierError::InvalidCiphertext));
+    }
+}
+
+#[test]
+fn arithmetic_rejects_foreign_private_key_and_mask() {
+    let (pubkey, privkey) = keys();
+    let (_, other_privkey) = keys();
+    let c1 = try_paillier_encrypt(&pubkey, &BigUint::from(7u32)).unwrap();
+    let c2 = try_paillier_encrypt(&pubkey, &BigUint::from(9u32)).unwrap();
+    let r = BigUint::from(1000u32);
+    assert_eq!(try_paillier_difference(&c1, &c2, &pubkey, &other_privkey), Err(PaillierError::KeyMismatch));
+    assert_eq!(try_paillier_compare(&c1, &c2, &pubkey, &other_privkey, &r), Err(PaillierError::KeyMismatch));
+    assert_eq!(
+        try_paillier_compare(&c1, &c2, &pubkey, &privkey, &pubkey.0),
+        Err(PaillierError::PlaintextOutOfRange)
+    );
+    assert_eq!(try_paillier_difference(&c1, &c2, &pubkey, &privkey), Ok((-2).into()));
+    assert_eq!(try_paillier_compare(&c1, &c2, &pubkey, &privkey, &r), Ok(true));
+}
@@ -0,0 +1,103 @@
+//! Fuzzes the extended GCD and modular inverse against the `num-bigint` and
+//! `num-integer` reference implementations, on operands up to 4096 bits so
+//! that [`extended_gcd_lehmer`] runs many outer steps.
+#![cfg(feature = "std")]
+
+use num_bigint::{BigInt, BigUint};
+use num_integer::Integer;
+use num_traits::{One, Zero};
+use paillier_rs::error::PaillierError;
+use paillier_rs::gcd::{extended_gcd_int, extended_gcd_lehmer, modinv, LEHMER_THRESHOLD_BITS};
+use proptest::prelude::*;
+
+/// Arbitrary value of up to `max_bits` bits, shrinking towards zero.
+fn biguint(max_bits: usize) -> impl Strategy<Value = BigUint> {
+    prop::collection::vec(any::<u32>(), 0..=max_bits / 32).prop_map(|d| BigUint::from_slice(&d))
+}
+
+fn bigint(max_bits: usize) -> impl Strategy<Value = BigInt> {
+    (any::<bool>(), biguint(max_bits)).prop_map(|(neg, v)| if neg { -BigInt::from(v) } else { BigInt::from(v) })
+}
+
+/// Two operands sharing a random common factor, so the gcd is rarely 1.
+fn with_common_factor(max_bits: usize) -> impl Strategy<Value = (BigUint, BigUint)> {
+    (biguint(max_bits), biguint(max_bits), biguint(128)).prop_map(|(a, b, f)| (a * &f, b * f))
+}
+
+fn assert_bezout(a: &BigUint, b: &BigUint, (g, x, y): &(BigUint, BigInt, BigInt)) -> Result<(), TestCaseError> {
+    prop_assert_eq!(g, &a.gcd(b));
+    prop_assert_eq!(BigInt::from(a.clone()) * x + BigInt::from(b.clone()) * y, BigInt::from(g.clone()));
+    Ok(())
+}
+
+/// Consecutive Fibonacci numbers, the worst case for Euclid: every quotient
+/// is 1, so `F(k), F(k+1)` takes k division steps.
+fn fibonacci_pair(k: usize) -> (BigUint, BigUint) {
+    let (mut a, mut b) = (BigUint::zero(), BigUint::one());
+    for _ in 0..k {
+        let next = &a + &b;
+        a = std::mem::replace(&mut b, next);
+    }
+    (a, b)
+}
+
+proptest! {
+    #[test]
+    fn lehmer_matches_reference(a in biguint(4096), b in biguint(4096)) {
+        assert_bezout(&a, &b, &extended_gcd_lehmer(&a, &b))?;
+    }
+
+    #[test]
+    fn lehmer_matches_reference_with_common_factor((a, b) in with_common_factor(2048)) {
+        assert_bezout(&a, &b, &extended_gcd_lehmer(&a, &b))?;
+    }
+
+    #[test]
+    fn iterative_matches_reference(a in bigint(2048), b in bigint(2048)) {
+        let (g, x, y) = extended_gcd_int(&a, &b);
+        let reference = a.extended_gcd(&b);
+        prop_assert_eq!(g.magnitude(), reference.gcd.magnitude());
+        prop_assert_eq!(&a * x + &b * y, g);
+    }
+
+    #[test]
+    fn modinv_matches_reference(a in biguint(4096), m in biguint(4096)) {
+        let expected = if m.is_zero() { None } else { a.modinv(&m) };
+        prop_assert_eq!(modinv(&a, &m).ok(), expected);
+    }
+
+    #[test]
+    fn modinv_matches_reference_near_threshold(
+        a in biguint(2 * LEHMER_THRESHOLD_BITS as usize),
+        m in biguint(2 * LEHMER_THRESHOLD_BITS as usize),
+    ) {
+        let m = m | BigUint::one();
+        prop_assert_eq!(modinv(&a, &m).ok(), a.modinv(&m));
+    }
+}
+
+#[test]
+fn edge_cases() {
+    let zero = BigUint::zero();
+    let one = BigUint::one();
+    let big = (BigUint::one() << 3000u32) - 1u32;
+    assert_eq!(extended_gcd_lehmer(&zero, &zero).0, zero);
+    assert_eq!(extended_gcd_lehmer(&big, &zero), (big.clone(), BigInt::one(), BigInt::zero()));
+    assert_eq!(extended_gcd_lehmer(&zero, &big), (big.clone(), BigInt::zero(), BigInt::one()));
+    assert_eq!(extended_gcd_lehmer(&big, &big).0, big);
+    assert_eq!(modinv(&big, &zero), Err(PaillierError::NotInvertible));
+    assert_eq!(modinv(&big, &one), Ok(zero.clone()));
+    assert_eq!(modinv(&big, &big), Err(PaillierError::NotInvertible));
+}
+
+#[test]
+fn fibonacci_operands_do_not_recurse() {
+    let (a, b) = fibonacci_pair(20_000);
+    let lehmer = extended_gcd_lehmer(&a, &b);
+    assert!(lehmer.0.is_one());
+    assert_eq!(BigInt::from(a.clone()) * &lehmer.1 + BigInt::from(b.clone()) * &lehmer.2, BigInt::one());
+
+    let (g, x, y) = extended_gcd_int(&a.clone().into(), &b.clone().into());
+    assert_eq!(BigInt::from(a.clone()) * x + BigInt::from(b.clone()) * y, g);
+    assert_eq!(modinv(&a, &b).ok(), a.modinv(&b));
+}
@@ -0,0 +1,123 @@
+//! Exercises the API available to the SP1 zkVM guest. Run with
+//! `cargo test -p paillier_rs --no-default-features --test guest` to build
+//! the library itself as `no_std` + `alloc`.
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use num_bigint::{BigInt, BigUint};
+use paillier_rs::arithmetic::{
+    paillier_add, paillier_add_with_backend, paillier_difference, paillier_scalar_mul,
+    paillier_scalar_mul_with_backend, paillier_subtract, paillier_subtract_with_backend,
+    try_paillier_add,
+};
+use paillier_rs::backend::{BigNumBackend, Sp1Backend};
+use paillier_rs::decrypt::{paillier_decrypt, paillier_decrypt_with_backend, try_paillier_decrypt};
+use paillier_rs::encrypt::{paillier_encrypt_with_rng, try_paillier_encrypt_with_rng};
+use paillier_rs::keygen::{paillier_keygen_with_rng, PrivateKey, PublicKey};
+use rand::{CryptoRng, Error, RngCore};
+
+/// Deterministic xorshift64* generator standing in for a guest-provided RNG.
+/// Not cryptographically secure; only used to exercise the API.
+struct TestRng(u64);
+
+impl RngCore for TestRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for TestRng {}
+
+fn keys(rng: &mut TestRng) -> (PublicKey, PrivateKey) {
+    paillier_keygen_with_rng(64, rng)
+}
+
+#[test]
+fn encrypt_decrypt_round_trip() {
+    let mut rng = TestRng(0x9e37_79b9_7f4a_7c15);
+    let (pubkey, privkey) = keys(&mut rng);
+    let messages: Vec<BigUint> = [0u32, 1, 42, 65_535].iter().map(|&m| BigUint::from(m)).collect();
+    for m in &messages {
+        let c = paillier_encrypt_with_rng(&pubkey, m, &mut rng);
+        assert_eq!(paillier_decrypt(&privkey, &pubkey, &c), *m);
+        assert_eq!(try_paillier_decrypt(&privkey, &pubkey, &c).as_ref(), Ok(m));
+    }
+}
+
+#[test]
+fn homomorphic_arithmetic() {
+    let mut rng = TestRng(0x0123_4567_89ab_cdef);
+    let (pubkey, privkey) = keys(&mut rng);
+    let m1 = BigUint::from(42u32);
+    let m2 = BigUint::from(17u32);
+    let c1 = try_paillier_encrypt_with_rng(&pubkey, &m1, &mut rng).unwrap();
+    let c2 = try_paillier_encrypt_with_rng(&pubkey, &m2, &mut rng).unwrap();
+
+    let sum = try_paillier_add(&c1, &c2, &pubkey).unwrap();
+    assert_eq!(paillier_decrypt(&privkey, &pubkey, &sum), BigUint::from(59u32));
+    assert_eq!(paillier_add(&c1, &c2, &pubkey), sum);
+
+    let product = paillier_scalar_mul(&c1, &BigUint::from(5u32), &pubkey);
+    assert_eq!(paillier_decrypt(&privkey, &pubkey, &product), BigUint::from(210u32));
+
+    let diff = paillier_subtract(&c1, &c2, &pubkey);
+    assert_eq!(paillier_decrypt(&privkey, &pubkey, &diff), BigUint::from(25u32));
+    assert_eq!(paillier_difference(&c2, &c1, &pubkey, &privkey), BigInt::from(-25));
+}
+
+/// Backend that only provides `mul_mod`, so `pow_mod` runs the trait's
+/// square-and-multiply, as on the SP1 precompile path.
+struct MulModOnly;
+
+impl BigNumBackend for MulModOnly {
+    fn mul_mod(&self, a: &BigUint, b: &BigUint, m: &BigUint) -> BigUint {
+        (a * b) % m
+    }
+}
+
+#[test]
+fn backends_agree_with_software() {
+    let mut rng = TestRng(0xdead_beef_cafe_f00d);
+    let (pubkey, privkey) = keys(&mut rng);
+    let c1 = paillier_encrypt_with_rng(&pubkey, &BigUint::from(1234u32), &mut rng);
+    let c2 = paillier_encrypt_with_rng(&pubkey, &BigUint::from(99u32), &mut rng);
+    let k = BigUint::from(31u32);
+
+    let backends: [&dyn BigNumBackend; 2] = [&MulModOnly, &Sp1Backend];
+    for backend in backends {
+        assert_eq!(paillier_add_with_backend(backend, &c1, &c2, &pubkey), paillier_add(&c1, &c2, &pubkey));
+        assert_eq!(
+            paillier_scalar_mul_with_backend(backend, &c1, &k, &pubkey),
+            paillier_scalar_mul(&c1, &k, &pubkey)
+        );
+        assert_eq!(
+            paillier_subtract_with_backend(backend, &c1, &c2, &pubkey),
+            paillier_subtract(&c1, &c2, &pubkey)
+        );
+        assert_eq!(
+            paillier_decrypt_with_backend(backend, &privkey, &pubkey, &c1),
+            paillier_decrypt(&privkey, &pubkey, &c1)
+        );
+    }
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "std")]
+
+use num_bigint::BigUint;
+use paillier_rs::ciphertext::Ciphertext;
+use paillier_rs::encoding::{
+    decode_ciphertext_blob, decode_ciphertext_container, decode_public_key, encode_ciphertext_blob,
+    encode_ciphertext_container, encode_public_key, CIPHERTEXT_BLOB_VERSION,
+};
+use paillier_rs::encrypt::paillier_encrypt;
+use paillier_rs::error::PaillierError;
+use paillier_rs::keygen::{paillier_keygen_with_proof, MIN_PRIME_BITS};
+use paillier_rs::keyid::{key_fingerprint, KeyId};
+
+#[test]
+fn key_id_is_stable_across_encodings() {
+    let (pubkey, _, proof) = paillier_keygen_with_proof(MIN_PRIME_BITS);
+    let (decoded, _) = decode_public_key(&encode_public_key(&pubkey, &proof)).unwrap();
+    assert_eq!(key_fingerprint(&decoded), key_fingerprint(&pubkey));
+    assert_eq!(KeyId::of(&decoded), KeyId::of(&pubkey));
+    assert_eq!(&KeyId::of(&pubkey).as_bytes()[..], &key_fingerprint(&pubkey)[..KeyId::LEN]);
+
+    let (other, _, _) = paillier_keygen_with_proof(MIN_PRIME_BITS);
+    assert_ne!(KeyId::of(&other), KeyId::of(&pubkey));
+}
+
+#[test]
+fn key_id_hex_round_trip() {
+    let id = KeyId::from_bytes(*b"\x00\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0a\x0b\x0c\x0d\xfe\xff");
+    assert_eq!(id.to_string(), "000102030405060708090a0b0c0dfeff");
+    assert_eq!(KeyId::from_hex(&id.to_string()), Some(id));
+    assert_eq!(KeyId::from_hex("000102030405060708090a0b0c0dfeFF"), Some(id));
+    assert_eq!(KeyId::from_hex("000102030405060708090a0b0c0dfe"), None);
+    assert_eq!(KeyId::from_hex("+00102030405060708090a0b0c0dfeff"), None);
+    assert_eq!(KeyId::from_slice(&id.as_bytes()[1..]), None);
+}
+
+#[test]
+fn container_records_and_checks_key() {
+    let (pubkey, _, _) = paillier_keygen_with_proof(MIN_PRIME_BITS);
+    let (other, _, _) = paillier_keygen_with_proof(MIN_PRIME_BITS);
+    let c = paillier_encrypt(&pubkey, &BigUint::from(42u32));
+    let encoded = encode_ciphertext_container(&c, &KeyId::of(&pubkey));
+
+    assert_eq!(decode_ciphertext_container(&encoded), Some((KeyId::of(&pubkey), c.clone())));
+    assert_eq!(Ciphertext::from_untrusted_container(&encoded, &pubkey).unwrap().into_biguint(), c);
+    assert_eq!(
+        Ciphertext::from_untrusted_container(&encoded, &other),
+        Err(PaillierError::WrongKey { expected: KeyId::of(&other), found: KeyId::of(&pubkey) })
+    );
+    assert_eq!(
+        Ciphertext::from_untrusted_container(&c.to_str_radix(10), &pubkey),
+        Err(PaillierError::InvalidCiphertext)
+    );
+}
+
+#[test]
+fn blob_records_and_checks_key() {
+    let (pubkey, _, _) = paillier_keygen_with_proof(MIN_PRIME_BITS);
+    let (other, _, _) = paillier_keygen_with_proof(MIN_PRIME_BITS);
+    let c = paillier_encrypt(&pubkey, &BigUint::from(42u32));
+    let blob = encode_ciphertext_blob(&c, &KeyId::of(&pubkey));
+
+    assert_eq!(blob[0], CIPHERTEXT_BLOB_VERSION);
+    assert_eq!(&blob[1..1 + KeyId::LEN], KeyId::of(&pubkey).as_bytes());
+    assert!(blob.len() < c.to_str_radix(10).len());
+    assert_eq!(decode_ciphertext_blob(&blob), Some((KeyId::of(&pubkey), c.clone())));
+    assert_eq!(Ciphertext::from_untrusted_blob(&blob, &pubkey).unwrap().into_biguint(), c);
+    assert_eq!(
+        Ciphertext::from_untrusted_blob(&blob, &other),
+        Err(PaillierError::WrongKey { expected: KeyId::of(&other), found: KeyId::of(&pubkey) })
+    );
+
+    let mut future = blob.clone();
+    future[0] = CIPHERTEXT_BLOB_VERSION + 1;
+    assert_eq!(decode_ciphertext_blob(&future), None);
+    assert_eq!(decode_ciphertext_blob(&blob[..1 + KeyId::LEN]), None);
+    assert_eq!(decode_ciphertext_blob(&[]), None);
+}
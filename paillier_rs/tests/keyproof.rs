@@ -0,0 +1,90 @@
+#![cfg(feature = "std")]
+
+use num_bigint::BigUint;
+use num_traits::One;
+use paillier_rs::encoding::encode_public_key;
+use paillier_rs::keygen::{modinv, paillier_keygen, paillier_keygen_with_proof, PublicKey};
+use paillier_rs::keyproof::{import_public_key, prove_key, verify_key, KeyProof, PROOF_ROUNDS, SMALL_PRIME_BOUND};
+
+/// A modulus n·r with its factor r put in, and an honest proof for it: with
+/// φ(n·r) at hand the prover can answer every challenge as long as
+/// gcd(n·r, φ(n·r)) = 1, so only the verifier's other checks can reject it.
+fn with_factor(r: u32) -> (PublicKey, KeyProof) {
+    loop {
+        let ((n, _), (phi, _)) = paillier_keygen(64);
+        let n = n * r;
+        let phi = phi * (r - 1);
+        if modinv(&n, &phi).is_ok() {
+            let pubkey = (n.clone(), n + 1u32);
+            let proof = prove_key(&pubkey, &(phi, BigUint::one()));
+            return (pubkey, proof);
+        }
+    }
+}
+
+#[test]
+fn honest_proof_verifies() {
+    let (pubkey, _, proof) = paillier_keygen_with_proof(64);
+    assert_eq!(proof.sigmas.len(), PROOF_ROUNDS);
+    assert!(verify_key(&pubkey, &proof));
+    assert_eq!(import_public_key(&encode_public_key(&pubkey, &proof)), Some(pubkey));
+}
+
+#[test]
+fn tampered_proof_is_rejected() {
+    let (pubkey, _, proof) = paillier_keygen_with_proof(64);
+    let n = &pubkey.0;
+
+    for i in [0, PROOF_ROUNDS - 1] {
+        let mut tampered = proof.clone();
+        tampered.sigmas[i] = (&tampered.sigmas[i] + 1u32) % n;
+        assert!(!verify_key(&pubkey, &tampered));
+        assert_eq!(import_public_key(&encode_public_key(&pubkey, &tampered)), None);
+    }
+    // σ + n has the same n-th power modulo n but is not reduced.
+    let mut unreduced = proof.clone();
+    unreduced.sigmas[0] += n;
+    assert!(!verify_key(&pubkey, &unreduced));
+    // A proof for another key.
+    let (_, _, other) = paillier_keygen_with_proof(64);
+    assert!(!verify_key(&pubkey, &other));
+
+    let mut short = proof.clone();
+    short.sigmas.pop();
+    assert!(!verify_key(&pubkey, &short));
+    let mut long = proof.clone();
+    long.sigmas.push(proof.sigmas[0].clone());
+    assert!(!verify_key(&pubkey, &long));
+    assert!(!verify_key(&pubkey, &KeyProof { sigmas: Vec::new() }));
+}
+
+#[test]
+fn generator_must_be_n_plus_one() {
+    let ((n, g), privkey) = paillier_keygen(64);
+    for bad_g in [&n + 2u32, BigUint::from(2u32), g.clone() * 2u32] {
+        let pubkey = (n.clone(), bad_g);
+        // Even a proof computed for this g is rejected.
+        let proof = prove_key(&pubkey, &privkey);
+        assert!(!verify_key(&pubkey, &proof));
+    }
+}
+
+#[test]
+fn moduli_with_small_factors_are_rejected() {
+    // 65521 is the largest prime below the bound: the proof itself is
+    // valid, and only the sieve rejects the modulus.
+    let (pubkey, proof) = with_factor(65521);
+    assert!(!verify_key(&pubkey, &proof));
+    // 65537 is the smallest prime above it, so the same construction passes;
+    // the soundness error of a round is at most 1/65537.
+    let (pubkey, proof) = with_factor(65537);
+    assert!(verify_key(&pubkey, &proof));
+
+    let (pubkey, _, proof) = paillier_keygen_with_proof(64);
+    let even = &pubkey.0 * 2u32;
+    assert!(!verify_key(&(even.clone(), even + 1u32), &proof));
+    for tiny in [1u32, 3, 15, SMALL_PRIME_BOUND] {
+        let n = BigUint::from(tiny);
+        assert!(!verify_key(&(n.clone(), n + 1u32), &proof));
+    }
+}
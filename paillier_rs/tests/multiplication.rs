@@ -0,0 +1,85 @@
+#![cfg(feature = "std")]
+
+use num_bigint::{BigInt, BigUint};
+use num_traits::Zero;
+use paillier_rs::decrypt::paillier_decrypt;
+use paillier_rs::encrypt::paillier_encrypt;
+use paillier_rs::error::PaillierError;
+use paillier_rs::fixed_point::{decode_signed, encode_signed};
+use paillier_rs::keygen::{paillier_keygen, PrivateKey, PublicKey};
+use paillier_rs::multiplication::{
+    Channel, InMemoryChannel, MultiplicationClient, MultiplicationServer, MultiplyRequest, MultiplyResponse,
+};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+fn keys() -> (PublicKey, PrivateKey) {
+    paillier_keygen(128)
+}
+
+fn encrypt_signed(pubkey: &PublicKey, m: i64) -> BigUint {
+    paillier_encrypt(pubkey, &encode_signed(&BigInt::from(m), &pubkey.0))
+}
+
+#[test]
+fn multiplies_batches_in_one_round_trip() {
+    let (pubkey, privkey) = keys();
+    let values = [(6i64, 7i64), (-3, 5), (-4, -9), (0, 123), (1 << 40, 1 << 20)];
+    let pairs: Vec<_> = values
+        .iter()
+        .map(|&(a, b)| (encrypt_signed(&pubkey, a), encrypt_signed(&pubkey, b)))
+        .collect();
+
+    let server = MultiplicationServer::new(&pubkey);
+    let mut channel = InMemoryChannel::new(MultiplicationClient::new(&pubkey, &privkey), StdRng::seed_from_u64(1));
+    let products = server.multiply(&mut channel, &pairs).unwrap();
+
+    assert_eq!(channel.round_trips, 1);
+    for (&(a, b), c) in values.iter().zip(&products) {
+        let m = decode_signed(&paillier_decrypt(&privkey, &pubkey, c), &pubkey.0);
+        assert_eq!(m, BigInt::from(a) * b);
+    }
+}
+
+#[test]
+fn key_holder_only_sees_masked_values() {
+    let (pubkey, privkey) = keys();
+    let pairs = vec![(encrypt_signed(&pubkey, 0), encrypt_signed(&pubkey, 0))];
+    let server = MultiplicationServer::new(&pubkey);
+    let (request, _) = server.mask_with_rng(&pairs, &mut StdRng::seed_from_u64(2)).unwrap();
+    let (ca, cb) = &request.pairs[0];
+    assert_ne!(ca, &pairs[0].0);
+    assert!(!paillier_decrypt(&privkey, &pubkey, ca).is_zero());
+    assert!(!paillier_decrypt(&privkey, &pubkey, cb).is_zero());
+}
+
+/// Drops the last product of every response.
+struct TruncatingChannel<'a>(MultiplicationClient<'a>);
+
+impl Channel for TruncatingChannel<'_> {
+    fn exchange(&mut self, request: &MultiplyRequest) -> Result<MultiplyResponse, PaillierError> {
+        let mut response = self.0.respond(request)?;
+        response.products.pop();
+        Ok(response)
+    }
+}
+
+#[test]
+fn rejects_malformed_messages() {
+    let (pubkey, privkey) = keys();
+    let server = MultiplicationServer::new(&pubkey);
+    let valid = encrypt_signed(&pubkey, 3);
+    let mut channel = TruncatingChannel(MultiplicationClient::new(&pubkey, &privkey));
+    assert_eq!(
+        server.multiply(&mut channel, &[(valid.clone(), valid.clone())]),
+        Err(PaillierError::MalformedMessage)
+    );
+
+    let client = MultiplicationClient::new(&pubkey, &privkey);
+    let bad = MultiplyRequest { pairs: vec![(valid.clone(), BigUint::zero())] };
+    assert_eq!(client.respond(&bad), Err(PaillierError::InvalidCiphertext));
+    assert_eq!(
+        server.mask_with_rng(&[(valid, &pubkey.0 * &pubkey.0)], &mut StdRng::seed_from_u64(3)).map(|_| ()),
+        Err(PaillierError::InvalidCiphertext)
+    );
+}
@@ -0,0 +1,81 @@
+#![cfg(feature = "std")]
+
+use num_bigint::{BigInt, BigUint};
+use paillier_rs::error::PaillierError;
+use paillier_rs::keygen::{paillier_keygen, PrivateKey, PublicKey};
+use paillier_rs::polynomial::{decrypt_scaled, encrypt_powers, evaluate_polynomial, Polynomial};
+
+fn keys() -> (PublicKey, PrivateKey) {
+    paillier_keygen(128)
+}
+
+fn eval_plain(coefficients: &[f64], x: f64) -> f64 {
+    coefficients.iter().rev().fold(0.0, |acc, a| acc * x + a)
+}
+
+#[test]
+fn integer_polynomial_is_exact() {
+    let (pubkey, privkey) = keys();
+    // 3 - 2x + x^3 at scale 1.
+    let poly = Polynomial { coefficients: vec![3.into(), (-2).into(), 0.into(), 1.into()], scale: 1 };
+    assert_eq!(poly.degree(), 3);
+    for x in [-7i64, -1, 0, 2, 11] {
+        let powers = encrypt_powers(&pubkey, x as f64, 3, 1).unwrap();
+        let result = evaluate_polynomial(&poly, &powers, 1, &pubkey).unwrap();
+        assert_eq!(result.scale, BigUint::from(1u32));
+        assert_eq!(decrypt_scaled(&privkey, &pubkey, &result), (3 - 2 * x + x * x * x) as f64);
+    }
+}
+
+#[test]
+fn sigmoid_approximation_tracks_scale() {
+    let (pubkey, privkey) = keys();
+    let coefficients = [0.5, 0.197, 0.0, -0.004];
+    let poly = Polynomial::from_f64(&coefficients, 1_000_000).unwrap();
+    let input_scale = 10_000;
+    for x in [-4.0, -1.25, 0.0, 0.3, 3.5] {
+        let powers = encrypt_powers(&pubkey, x, poly.degree(), input_scale).unwrap();
+        let result = evaluate_polynomial(&poly, &powers, input_scale, &pubkey).unwrap();
+        assert_eq!(result.scale, BigUint::from(1_000_000u64) * BigUint::from(input_scale).pow(3));
+        let decrypted = decrypt_scaled(&privkey, &pubkey, &result);
+        assert!((decrypted - eval_plain(&coefficients, x)).abs() < 1e-6, "x = {}: {}", x, decrypted);
+    }
+}
+
+#[test]
+fn constant_and_trailing_zero_coefficients() {
+    let (pubkey, privkey) = keys();
+    let poly = Polynomial { coefficients: vec![BigInt::from(-5), BigInt::from(0)], scale: 1 };
+    assert_eq!(poly.degree(), 0);
+    let result = evaluate_polynomial(&poly, &[], 100, &pubkey).unwrap();
+    assert_eq!(decrypt_scaled(&privkey, &pubkey, &result), -5.0);
+
+    // Extra powers beyond the degree are ignored.
+    let poly = Polynomial::from_f64(&[0.0, -1.5], 10).unwrap();
+    let powers = encrypt_powers(&pubkey, 2.0, 4, 100).unwrap();
+    let result = evaluate_polynomial(&poly, &powers, 100, &pubkey).unwrap();
+    assert_eq!(decrypt_scaled(&privkey, &pubkey, &result), -3.0);
+}
+
+#[test]
+fn invalid_inputs_are_errors() {
+    let (pubkey, _) = keys();
+    let poly = Polynomial::from_f64(&[1.0, 0.5, 0.25], 100).unwrap();
+    let powers = encrypt_powers(&pubkey, 3.0, 1, 100).unwrap();
+    assert_eq!(
+        evaluate_polynomial(&poly, &powers, 100, &pubkey),
+        Err(PaillierError::MissingPowers { degree: 2, found: 1 })
+    );
+    assert_eq!(
+        evaluate_polynomial(&poly, &[], 100, &pubkey),
+        Err(PaillierError::MissingPowers { degree: 2, found: 0 })
+    );
+
+    for bad in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        assert_eq!(Polynomial::from_f64(&[0.5, bad], 100), Err(PaillierError::NotFinite));
+        assert_eq!(encrypt_powers(&pubkey, bad, 2, 100), Err(PaillierError::NotFinite));
+    }
+    // Finite values that overflow once scaled.
+    assert_eq!(Polynomial::from_f64(&[f64::MAX], 10), Err(PaillierError::NotFinite));
+    assert_eq!(encrypt_powers(&pubkey, f64::MAX, 2, 10), Err(PaillierError::NotFinite));
+}
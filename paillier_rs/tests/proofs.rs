@@ -0,0 +1,98 @@
+#![cfg(feature = "std")]
+
+use num_bigint::{BigUint, RandBigInt};
+use paillier_rs::arithmetic::paillier_scalar_mul;
+use paillier_rs::encrypt::{paillier_encrypt_with_randomness, sample_randomness};
+use paillier_rs::keygen::{paillier_keygen, PublicKey};
+use paillier_rs::proofs::{
+    commit_scalar, prove_plaintext_knowledge, prove_scalar_mul, verify_plaintext_knowledge, verify_scalar_mul,
+    PlaintextKnowledgeProof, ScalarMulProof,
+};
+use paillier_rs::transcript::Transcript;
+use rand::thread_rng;
+
+const LABEL: &[u8] = b"paillier-rs/tests/proofs";
+
+fn transcript() -> Transcript {
+    Transcript::new(LABEL)
+}
+
+/// An encryption of a random plaintext, with its plaintext and randomness.
+fn encryption(pubkey: &PublicKey) -> (BigUint, BigUint, BigUint) {
+    let m = thread_rng().gen_biguint_below(&pubkey.0);
+    let r = sample_randomness(&pubkey.0);
+    (paillier_encrypt_with_randomness(pubkey, &m, &r), m, r)
+}
+
+/// Another unit modulo n, for tampering with responses that must be units.
+fn other_unit(x: &BigUint, n: &BigUint) -> BigUint {
+    let y = (x * 2u32) % n;
+    assert_ne!(&y, x);
+    y
+}
+
+#[test]
+fn plaintext_knowledge() {
+    let (pubkey, _) = paillier_keygen(64);
+    let n = &pubkey.0;
+    let (c, m, r) = encryption(&pubkey);
+    let proof = prove_plaintext_knowledge(&pubkey, &c, &m, &r, &mut transcript(), &mut thread_rng());
+    let verify = |c: &BigUint, proof: &PlaintextKnowledgeProof, mut transcript: Transcript| {
+        verify_plaintext_knowledge(&pubkey, c, proof, &mut transcript)
+    };
+    assert!(verify(&c, &proof, transcript()));
+
+    let (other, _, _) = encryption(&pubkey);
+    assert!(!verify(&other, &proof, transcript()));
+    let tampered = PlaintextKnowledgeProof { z: (&proof.z + 1u32) % n, ..proof.clone() };
+    assert!(!verify(&c, &tampered, transcript()));
+    let tampered = PlaintextKnowledgeProof { w: other_unit(&proof.w, n), ..proof.clone() };
+    assert!(!verify(&c, &tampered, transcript()));
+    let tampered = PlaintextKnowledgeProof { commitment: &proof.commitment + 1u32, ..proof.clone() };
+    assert!(!verify(&c, &tampered, transcript()));
+    assert!(!verify(&c, &proof, Transcript::new(b"another protocol")));
+}
+
+#[test]
+fn scalar_mul() {
+    let (pubkey, _) = paillier_keygen(64);
+    let n = &pubkey.0;
+    let (c, _, _) = encryption(&pubkey);
+    let k = thread_rng().gen_biguint_below(n);
+    let (commitment, s) = commit_scalar(&pubkey, &k, &mut thread_rng());
+    let proof = prove_scalar_mul(&pubkey, &c, &commitment, &k, &s, &mut transcript(), &mut thread_rng());
+    let d = paillier_scalar_mul(&c, &k, &pubkey);
+    let verify = |c: &BigUint, d: &BigUint, proof: &ScalarMulProof, mut transcript: Transcript| {
+        verify_scalar_mul(&pubkey, c, &commitment, d, proof, &mut transcript)
+    };
+    assert!(verify(&c, &d, &proof, transcript()));
+
+    let (other, _, _) = encryption(&pubkey);
+    assert!(!verify(&other, &d, &proof, transcript()));
+    let wrong_d = paillier_scalar_mul(&c, &(&k + 1u32), &pubkey);
+    assert!(!verify(&c, &wrong_d, &proof, transcript()));
+    let tampered = ScalarMulProof { z: (&proof.z + 1u32) % n, ..proof.clone() };
+    assert!(!verify(&c, &d, &tampered, transcript()));
+    let tampered = ScalarMulProof { w1: other_unit(&proof.w1, n), ..proof.clone() };
+    assert!(!verify(&c, &d, &tampered, transcript()));
+    let tampered = ScalarMulProof { w2: other_unit(&proof.w2, n), ..proof.clone() };
+    assert!(!verify(&c, &d, &tampered, transcript()));
+    assert!(!verify(&c, &d, &proof, Transcript::new(b"another protocol")));
+
+    // The proof is bound to the committed scalar.
+    let (other_commitment, _) = commit_scalar(&pubkey, &k, &mut thread_rng());
+    assert!(!verify_scalar_mul(&pubkey, &c, &other_commitment, &d, &proof, &mut transcript()));
+}
+
+#[test]
+fn degenerate_moduli_are_rejected() {
+    let (pubkey, _) = paillier_keygen(64);
+    let (c, m, r) = encryption(&pubkey);
+    let proof = prove_plaintext_knowledge(&pubkey, &c, &m, &r, &mut transcript(), &mut thread_rng());
+    let trivial = PlaintextKnowledgeProof { z: BigUint::from(0u32), w: BigUint::from(1u32), ..proof.clone() };
+    for n in [0u32, 1] {
+        let degenerate = (BigUint::from(n), BigUint::from(n + 1));
+        assert!(!verify_plaintext_knowledge(&degenerate, &c, &proof, &mut transcript()));
+        assert!(!verify_plaintext_knowledge(&degenerate, &c, &trivial, &mut transcript()));
+    }
+}
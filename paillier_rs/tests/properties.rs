@@ -0,0 +1,167 @@
+//! Property-based checks of the homomorphic identities.
+//!
+//! Keys use [`MIN_PRIME_BITS`]-bit primes from a seeded RNG and encryption
+//! randomness comes from the strategies, so a failing case replays exactly
+//! and proptest shrinks it to a minimal counterexample. Set
+//! `PAILLIER_PROPTEST_PRIME_BITS` to run the same properties on larger keys,
+//! and `PROPTEST_CASES` to change the number of cases.
+#![cfg(feature = "std")]
+
+use num_bigint::{BigInt, BigUint};
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+use paillier_rs::arithmetic::{
+    paillier_add, paillier_compare, paillier_difference, paillier_scalar_mul, paillier_subtract,
+};
+use paillier_rs::decrypt::paillier_decrypt;
+use paillier_rs::encrypt::paillier_encrypt_with_randomness;
+use paillier_rs::fixed_point::{decode_signed, encode_signed};
+use paillier_rs::error::PaillierError;
+use paillier_rs::gcd::{extended_gcd_int, modinv};
+use paillier_rs::keygen::{paillier_keygen_with_rng, PrivateKey, PublicKey, MIN_PRIME_BITS};
+use proptest::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::sync::OnceLock;
+
+/// Number of distinct key pairs the properties draw from.
+const KEY_COUNT: usize = 4;
+
+fn prime_bits() -> usize {
+    std::env::var("PAILLIER_PROPTEST_PRIME_BITS")
+        .map(|bits| bits.parse().expect("PAILLIER_PROPTEST_PRIME_BITS must be an integer"))
+        .unwrap_or(MIN_PRIME_BITS)
+}
+
+fn keys() -> &'static [(PublicKey, PrivateKey)] {
+    static KEYS: OnceLock<Vec<(PublicKey, PrivateKey)>> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        (0..KEY_COUNT).map(|_| paillier_keygen_with_rng(prime_bits(), &mut rng)).collect()
+    })
+}
+
+/// Arbitrary value below `bound`, shrinking towards zero.
+fn below(bound: &BigUint) -> impl Strategy<Value = BigUint> {
+    let bound = bound.clone();
+    let digits = bound.iter_u32_digits().len();
+    prop::collection::vec(any::<u32>(), 0..=digits).prop_map(move |d| BigUint::from_slice(&d) % &bound)
+}
+
+/// Encryption randomness in \(\mathbb{Z}_n^*\).
+fn randomness(n: &BigUint) -> impl Strategy<Value = BigUint> {
+    let n = n.clone();
+    below(&n).prop_filter("r must be a unit mod n", move |r| r.gcd(&n).is_one())
+}
+
+/// A key index, two plaintexts and two randomness values.
+fn two_plaintexts() -> impl Strategy<Value = (usize, BigUint, BigUint, BigUint, BigUint)> {
+    (0..KEY_COUNT).prop_flat_map(|i| {
+        let n = &keys()[i].0 .0;
+        (Just(i), below(n), below(n), randomness(n), randomness(n))
+    })
+}
+
+/// A key index, two small signed plaintexts and two randomness values.
+fn two_small_plaintexts() -> impl Strategy<Value = (usize, i64, i64, BigUint, BigUint)> {
+    (0..KEY_COUNT).prop_flat_map(|i| {
+        let n = &keys()[i].0 .0;
+        let small = -(1i64 << 30)..(1i64 << 30);
+        (Just(i), small.clone(), small, randomness(n), randomness(n))
+    })
+}
+
+fn encrypt_signed(pubkey: &PublicKey, m: i64, r: &BigUint) -> BigUint {
+    let m = encode_signed(&BigInt::from(m), &pubkey.0);
+    paillier_encrypt_with_randomness(pubkey, &m, r)
+}
+
+proptest! {
+    #[test]
+    fn decrypt_inverts_encrypt((i, m, _, r, _) in two_plaintexts()) {
+        let (pubkey, privkey) = &keys()[i];
+        let c = paillier_encrypt_with_randomness(pubkey, &m, &r);
+        prop_assert_eq!(paillier_decrypt(privkey, pubkey, &c), m);
+    }
+
+    #[test]
+    fn add_is_plaintext_addition((i, m1, m2, r1, r2) in two_plaintexts()) {
+        let (pubkey, privkey) = &keys()[i];
+        let n = &pubkey.0;
+        let c1 = paillier_encrypt_with_randomness(pubkey, &m1, &r1);
+        let c2 = paillier_encrypt_with_randomness(pubkey, &m2, &r2);
+        let sum = paillier_add(&c1, &c2, pubkey);
+        prop_assert_eq!(paillier_decrypt(privkey, pubkey, &sum), (m1 + m2) % n);
+    }
+
+    #[test]
+    fn scalar_mul_is_plaintext_multiplication((i, m, k, r, _) in two_plaintexts()) {
+        let (pubkey, privkey) = &keys()[i];
+        let n = &pubkey.0;
+        let c = paillier_encrypt_with_randomness(pubkey, &m, &r);
+        let product = paillier_scalar_mul(&c, &k, pubkey);
+        prop_assert_eq!(paillier_decrypt(privkey, pubkey, &product), (m * k) % n);
+    }
+
+    #[test]
+    fn subtract_wraps_mod_n((i, m1, m2, r1, r2) in two_plaintexts()) {
+        let (pubkey, privkey) = &keys()[i];
+        let n = &pubkey.0;
+        let c1 = paillier_encrypt_with_randomness(pubkey, &m1, &r1);
+        let c2 = paillier_encrypt_with_randomness(pubkey, &m2, &r2);
+        let diff = paillier_subtract(&c1, &c2, pubkey);
+        prop_assert_eq!(paillier_decrypt(privkey, pubkey, &diff), (m1 + n - m2) % n);
+    }
+
+    #[test]
+    fn subtract_decodes_to_signed_difference((i, a, b, r1, r2) in two_small_plaintexts()) {
+        let (pubkey, privkey) = &keys()[i];
+        let c1 = encrypt_signed(pubkey, a, &r1);
+        let c2 = encrypt_signed(pubkey, b, &r2);
+        let diff = paillier_decrypt(privkey, pubkey, &paillier_subtract(&c1, &c2, pubkey));
+        prop_assert_eq!(decode_signed(&diff, &pubkey.0), BigInt::from(a - b));
+    }
+
+    #[test]
+    fn difference_and_compare_agree_with_sign((i, a, b, r1, r2) in two_small_plaintexts()) {
+        let (pubkey, privkey) = &keys()[i];
+        let c1 = encrypt_signed(pubkey, a, &r1);
+        let c2 = encrypt_signed(pubkey, b, &r2);
+        prop_assert_eq!(paillier_difference(&c1, &c2, pubkey, privkey), BigInt::from(a - b));
+        // The mask must exceed |a - b| < 2^31.
+        let mask = BigUint::one() << 32;
+        prop_assert_eq!(paillier_compare(&c1, &c2, pubkey, privkey, &mask), a < b);
+    }
+
+    #[test]
+    fn extended_gcd_satisfies_bezout(a in any::<i128>(), b in any::<i128>()) {
+        let (a, b) = (BigInt::from(a), BigInt::from(b));
+        let (g, x, y) = extended_gcd_int(&a, &b);
+        prop_assert_eq!(&a * &x + &b * &y, g.clone());
+        prop_assert_eq!(g.abs(), a.gcd(&b));
+    }
+
+    #[test]
+    fn modinv_inverts_units(a in any::<u128>(), m in 1u128..) {
+        let (a, m) = (BigUint::from(a), BigUint::from(m));
+        match modinv(&a, &m) {
+            Ok(x) => {
+                prop_assert!(x < m);
+                prop_assert_eq!((&a * &x) % &m, BigUint::one() % &m);
+            }
+            Err(e) => {
+                prop_assert_eq!(e, PaillierError::NotInvertible);
+                prop_assert!(!a.gcd(&m).is_one());
+            }
+        }
+    }
+
+    #[test]
+    fn modinv_inverts_phi_mod_n(i in 0..KEY_COUNT) {
+        let (pubkey, privkey) = &keys()[i];
+        let (phi, mu) = privkey;
+        prop_assert_eq!(modinv(phi, &pubkey.0), Ok(mu.clone()));
+        prop_assert_eq!(modinv(&pubkey.0, &pubkey.0), Err(PaillierError::NotInvertible));
+        prop_assert_eq!(modinv(&BigUint::zero(), &pubkey.0), Err(PaillierError::NotInvertible));
+    }
+}
@@ -0,0 +1,155 @@
+#![cfg(feature = "std")]
+
+use num_bigint::{BigInt, BigUint};
+use paillier_rs::decrypt::paillier_decrypt;
+use paillier_rs::encrypt::paillier_encrypt;
+use paillier_rs::error::PaillierError;
+use paillier_rs::fixed_point::{decode_signed, encode_signed};
+use paillier_rs::keygen::paillier_keygen;
+use paillier_rs::rotation::{Checkpoint, Reencryptor, CHECKPOINT_HEADER};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// A path in the temporary directory that does not exist yet.
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("paillier-rs-{}-{}", std::process::id(), name));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn reencryptor_refuses_smaller_modulus() {
+    let (small, small_priv) = paillier_keygen(64);
+    let (large, large_priv) = paillier_keygen(80);
+    assert_eq!(Reencryptor::new(&large, &large_priv, &small).err(), Some(PaillierError::ModulusTooSmall));
+
+    let reencryptor = Reencryptor::new(&small, &small_priv, &large).unwrap();
+    let c = paillier_encrypt(&small, &BigUint::from(1234u32));
+    assert_eq!(paillier_decrypt(&large_priv, &large, &reencryptor.reencrypt(&c).unwrap()), BigUint::from(1234u32));
+}
+
+#[test]
+fn negative_values_keep_their_sign() {
+    let (old, old_priv) = paillier_keygen(64);
+    let (new, new_priv) = paillier_keygen(80);
+    let reencryptor = Reencryptor::new(&old, &old_priv, &new).unwrap();
+    for v in [-1i64, -1234, 0, 1234] {
+        let v = BigInt::from(v);
+        let c = paillier_encrypt(&old, &encode_signed(&v, &old.0));
+        let moved = reencryptor.reencrypt(&c).unwrap();
+        assert_eq!(decode_signed(&paillier_decrypt(&new_priv, &new, &moved), &new.0), v);
+    }
+}
+
+#[test]
+fn invalid_ciphertexts_are_rejected() {
+    let (old, old_priv) = paillier_keygen(64);
+    let (new, _) = paillier_keygen(80);
+    let reencryptor = Reencryptor::new(&old, &old_priv, &new).unwrap();
+    let n_squared = &old.0 * &old.0;
+    for c in [BigUint::from(0u32), old.0.clone(), n_squared.clone(), n_squared + 1u32] {
+        assert_eq!(reencryptor.reencrypt(&c), Err(PaillierError::InvalidCiphertext), "{}", c);
+    }
+    let good = paillier_encrypt(&old, &BigUint::from(5u32));
+    let batch = [(1, good), (2, BigUint::from(0u32))];
+    assert_eq!(reencryptor.reencrypt_batch(&batch), Err(PaillierError::InvalidCiphertext));
+}
+
+#[test]
+fn checkpoint_encoding_round_trip() {
+    let checkpoint = Checkpoint { last_id: -3, rows_done: 1 << 40 };
+    let encoded = checkpoint.encode();
+    assert_eq!(encoded, format!("{}\nlast_id=-3\nrows_done=1099511627776\n", CHECKPOINT_HEADER));
+    assert_eq!(Checkpoint::decode(&encoded), Some(checkpoint));
+    assert_eq!(Checkpoint::decode(&Checkpoint::default().encode()), Some(Checkpoint::default()));
+    // Blank lines, surrounding whitespace and CRLF line ends are tolerated.
+    let loose = format!("{}\r\n\r\n  rows_done=5 \r\nlast_id=9\r\n", CHECKPOINT_HEADER);
+    assert_eq!(Checkpoint::decode(&loose), Some(Checkpoint { last_id: 9, rows_done: 5 }));
+
+    for malformed in [
+        "",
+        "last_id=1\nrows_done=1\n",
+        "paillier-reencrypt-checkpoint v2\nlast_id=1\n",
+        &format!("{}\nlast_id=one\n", CHECKPOINT_HEADER),
+        &format!("{}\nrows_done=-1\n", CHECKPOINT_HEADER),
+        &format!("{}\nlast_id\n", CHECKPOINT_HEADER),
+        &format!("{}\nbatch=3\n", CHECKPOINT_HEADER),
+    ] {
+        assert_eq!(Checkpoint::decode(malformed), None, "{:?}", malformed);
+    }
+}
+
+#[test]
+fn checkpoint_file_round_trip() {
+    let path = temp_path("checkpoint-file");
+    assert_eq!(Checkpoint::load(&path).unwrap(), None);
+
+    let checkpoint = Checkpoint { last_id: 17, rows_done: 12 };
+    checkpoint.save(&path).unwrap();
+    assert_eq!(Checkpoint::load(&path).unwrap(), Some(checkpoint.clone()));
+    assert!(!path.with_extension("tmp").exists());
+    // Saving again replaces the file.
+    let later = Checkpoint { last_id: 30, rows_done: 20 };
+    later.save(&path).unwrap();
+    assert_eq!(Checkpoint::load(&path).unwrap(), Some(later));
+
+    fs::write(&path, "not a checkpoint").unwrap();
+    assert_eq!(Checkpoint::load(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    fs::remove_file(&path).unwrap();
+}
+
+/// Re-encrypts `rows` from the checkpoint on, `batch_size` rows at a time,
+/// saving the checkpoint after each batch and stopping after `max_batches`.
+fn run(
+    reencryptor: &Reencryptor,
+    rows: &mut [(i64, BigUint)],
+    batch_size: usize,
+    max_batches: usize,
+    checkpoint: &mut Checkpoint,
+    path: &std::path::Path,
+) {
+    for _ in 0..max_batches {
+        let pending: Vec<(i64, BigUint)> =
+            rows.iter().filter(|(id, _)| *id > checkpoint.last_id).take(batch_size).cloned().collect();
+        if pending.is_empty() {
+            return;
+        }
+        for (id, c) in reencryptor.reencrypt_batch(&pending).unwrap() {
+            rows.iter_mut().find(|(row, _)| *row == id).unwrap().1 = c;
+        }
+        checkpoint.advance(&pending);
+        checkpoint.save(path).unwrap();
+    }
+}
+
+#[test]
+fn resume_after_partial_batch() {
+    let (old, old_priv) = paillier_keygen(64);
+    let (new, new_priv) = paillier_keygen(80);
+    let reencryptor = Reencryptor::new(&old, &old_priv, &new).unwrap();
+    let path = temp_path("checkpoint-resume");
+    // Ids with gaps, as left by deleted rows.
+    let ids = [1i64, 2, 4, 7, 8, 9, 12];
+    let mut rows: Vec<(i64, BigUint)> =
+        ids.iter().map(|&id| (id, paillier_encrypt(&old, &BigUint::from(id as u64 * 10)))).collect();
+
+    // The first run stops after two batches of three.
+    let mut checkpoint = Checkpoint::default();
+    run(&reencryptor, &mut rows, 3, 2, &mut checkpoint, &path);
+    assert_eq!(checkpoint, Checkpoint { last_id: 9, rows_done: 6 });
+
+    // A fresh process resumes from the saved file and finishes the last,
+    // partial batch.
+    let mut resumed = Checkpoint::load(&path).unwrap().unwrap();
+    run(&reencryptor, &mut rows, 3, usize::MAX, &mut resumed, &path);
+    assert_eq!(resumed, Checkpoint { last_id: 12, rows_done: 7 });
+    assert_eq!(Checkpoint::load(&path).unwrap(), Some(resumed));
+
+    // Every row was moved exactly once: a second re-encryption would have
+    // decrypted a new-key ciphertext with the old key.
+    for (id, c) in &rows {
+        assert_eq!(paillier_decrypt(&new_priv, &new, c), BigUint::from(*id as u64 * 10));
+    }
+    fs::remove_file(&path).unwrap();
+}
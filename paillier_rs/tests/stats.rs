@@ -0,0 +1,103 @@
+#![cfg(feature = "std")]
+
+use num_bigint::{BigInt, BigUint};
+use paillier_rs::encrypt::paillier_encrypt;
+use paillier_rs::error::PaillierError;
+use paillier_rs::fixed_point::{encode_f64, encode_signed};
+use paillier_rs::keygen::{paillier_keygen, PrivateKey, PublicKey};
+use paillier_rs::stats::{decrypt_mean, decrypt_statistics, encrypted_moments, encrypted_sum, Statistics};
+
+fn keys() -> (PublicKey, PrivateKey) {
+    paillier_keygen(64)
+}
+
+fn encrypt_signed(pubkey: &PublicKey, v: i64) -> BigUint {
+    paillier_encrypt(pubkey, &encode_signed(&BigInt::from(v), &pubkey.0))
+}
+
+/// Encryptions of `xs` and of their squares at `scale` and `scale²`.
+fn encrypt_column(pubkey: &PublicKey, xs: &[f64], scale: u64) -> (Vec<BigUint>, Vec<BigUint>) {
+    let n = &pubkey.0;
+    let encrypt = |x: f64, scale| paillier_encrypt(pubkey, &encode_f64(x, scale, n).unwrap());
+    let values = xs.iter().map(|&x| encrypt(x, scale)).collect();
+    let squares = xs.iter().map(|&x| encrypt(x * x, scale * scale)).collect();
+    (values, squares)
+}
+
+#[test]
+fn sum_and_mean_of_signed_values() {
+    let (pubkey, privkey) = keys();
+    let values: Vec<BigUint> = [-7, 3, -12, 2].iter().map(|&v| encrypt_signed(&pubkey, v)).collect();
+    let sum = encrypted_sum(&values, &pubkey);
+    assert_eq!(sum.count, 4);
+    assert_eq!(decrypt_mean(&privkey, &pubkey, &sum, 1), (-14.0, -3.5));
+}
+
+#[test]
+fn mean_at_fixed_point_scale() {
+    let (pubkey, privkey) = keys();
+    let (values, _) = encrypt_column(&pubkey, &[1.25, -0.5, 2.75], 100);
+    let sum = encrypted_sum(&values, &pubkey);
+    assert_eq!(decrypt_mean(&privkey, &pubkey, &sum, 100), (3.5, 3.5 / 3.0));
+    // The same plaintexts read at another scale.
+    assert_eq!(decrypt_mean(&privkey, &pubkey, &sum, 10).0, 35.0);
+}
+
+#[test]
+fn variance_of_fixed_point_column() {
+    let (pubkey, privkey) = keys();
+    let xs = [2.0, -4.0, 4.5, 5.0, 5.5, 7.0, -1.0, 9.0];
+    let (values, squares) = encrypt_column(&pubkey, &xs, 10);
+    let moments = encrypted_moments(&values, &squares, &pubkey).unwrap();
+    let stats = decrypt_statistics(&privkey, &pubkey, &moments, 10);
+
+    let count = xs.len() as f64;
+    let mean = xs.iter().sum::<f64>() / count;
+    let variance = xs.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / count;
+    assert_eq!((stats.count, stats.sum), (8, 28.0));
+    assert!((stats.mean - mean).abs() < 1e-12, "{:?}", stats);
+    assert!((stats.variance - variance).abs() < 1e-12, "{:?} vs {}", stats, variance);
+
+    // A constant column has no variance at all, not a rounding residue.
+    let (values, squares) = encrypt_column(&pubkey, &[-3.3; 5], 10);
+    let moments = encrypted_moments(&values, &squares, &pubkey).unwrap();
+    assert_eq!(decrypt_statistics(&privkey, &pubkey, &moments, 10).variance, 0.0);
+}
+
+#[test]
+fn empty_column_has_nan_mean_and_variance() {
+    let (pubkey, privkey) = keys();
+    let sum = encrypted_sum(&[], &pubkey);
+    assert_eq!((sum.sum.clone(), sum.count), (BigUint::from(1u32), 0));
+    let (total, mean) = decrypt_mean(&privkey, &pubkey, &sum, 100);
+    assert_eq!(total, 0.0);
+    assert!(mean.is_nan());
+
+    let moments = encrypted_moments(&[], &[], &pubkey).unwrap();
+    let Statistics { count, sum, mean, variance } = decrypt_statistics(&privkey, &pubkey, &moments, 100);
+    assert_eq!((count, sum), (0, 0.0));
+    assert!(mean.is_nan() && variance.is_nan());
+}
+
+#[test]
+fn moments_need_a_square_per_value() {
+    let (pubkey, _) = keys();
+    let (values, squares) = encrypt_column(&pubkey, &[1.0, 2.0, 3.0], 1);
+    assert_eq!(
+        encrypted_moments(&values, &squares[..2], &pubkey),
+        Err(PaillierError::LengthMismatch { expected: 3, found: 2 })
+    );
+    assert_eq!(
+        encrypted_moments(&values[..1], &squares, &pubkey),
+        Err(PaillierError::LengthMismatch { expected: 1, found: 3 })
+    );
+}
+
+#[test]
+fn non_finite_values_cannot_be_encoded() {
+    let (pubkey, _) = keys();
+    for x in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, f64::MAX] {
+        assert_eq!(encode_f64(x, 100, &pubkey.0), Err(PaillierError::NotFinite), "{}", x);
+    }
+    assert_eq!(encode_f64(-0.5, 100, &pubkey.0), Ok(&pubkey.0 - 50u32));
+}
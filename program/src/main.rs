@@ -1,28 +1,79 @@
 #![no_main]
 sp1_zkvm::entrypoint!(main);
 use cnn::Conv2D;
+use num_bigint::BigUint;
+use num_traits::One;
+use paillier_rs::arithmetic::{paillier_add_with_backend, paillier_scalar_mul_with_backend};
+use paillier_rs::backend::{BigNumBackend, SoftwareBackend, Sp1Backend};
+use paillier_rs::ciphertext::Ciphertext;
+use paillier_rs::decrypt::paillier_decrypt_with_backend;
+use paillier_rs::keygen::{check_key_pair, PrivateKey, PublicKey};
+use paillier_rs::keyid::KeyId;
+
+/// Reads a big-endian ciphertext from the zkVM input and validates it
+/// against `pubkey`. Execution aborts on an invalid ciphertext, so no proof
+/// can be produced for a computation over malformed input.
+fn read_ciphertext(pubkey: &PublicKey) -> Ciphertext {
+    let bytes = sp1_zkvm::io::read_vec();
+    Ciphertext::from_untrusted_bytes(&bytes, pubkey).expect("Input is not a valid ciphertext.")
+}
+
+/// Computes Enc(m1 + m2), Enc(k * (m1 + m2)) and decrypts the latter. Each
+/// step is wrapped in a cycle tracker so the host can compare backends.
+fn evaluate<B: BigNumBackend>(
+    backend: &B,
+    pubkey: &PublicKey,
+    privkey: &PrivateKey,
+    c1: &Ciphertext,
+    c2: &Ciphertext,
+    k: &BigUint,
+) -> (BigUint, BigUint, BigUint) {
+    println!("cycle-tracker-start: paillier_add");
+    let c_add = paillier_add_with_backend(backend, c1.as_biguint(), c2.as_biguint(), pubkey);
+    println!("cycle-tracker-end: paillier_add");
+
+    println!("cycle-tracker-start: paillier_scalar_mul");
+    let c_scaled = paillier_scalar_mul_with_backend(backend, &c_add, k, pubkey);
+    println!("cycle-tracker-end: paillier_scalar_mul");
+
+    println!("cycle-tracker-start: paillier_decrypt");
+    let m = paillier_decrypt_with_backend(backend, privkey, pubkey, &c_scaled);
+    println!("cycle-tracker-end: paillier_decrypt");
+
+    (c_add, c_scaled, m)
+}
 
 fn main() {
 
-    // let bits = 64;
-    // let (pubkey, privkey) = paillier_keygen(bits);
+    // The host supplies, in order: whether to use the SP1 precompile backend,
+    // the modulus n (big-endian), two ciphertexts under the public key
+    // (n, n + 1), a scalar k, and the private key (λ, μ) so that the guest can
+    // prove a correct decryption. Keys are never generated inside the guest.
+    let use_precompile = sp1_zkvm::io::read::<bool>();
+    let n = BigUint::from_bytes_be(&sp1_zkvm::io::read_vec());
+    let pubkey: PublicKey = (n.clone(), &n + BigUint::one());
 
-    // let a:u32 = sp1_zkvm::io::read::<u32>();
-    // let b:u32 = sp1_zkvm::io::read::<u32>();
+    let c1 = read_ciphertext(&pubkey);
+    let c2 = read_ciphertext(&pubkey);
+    let k = BigUint::from_bytes_be(&sp1_zkvm::io::read_vec());
 
-    // let m1 = a.to_biguint().unwrap();
-    // let m2 = b.to_biguint().unwrap();
-    
-    // let c1 = paillier_encrypt(&pubkey, &m1);
-    // let c2 = paillier_encrypt(&pubkey, &m2);
-    
-    // // Homomorphic addition: should yield m1 + m2.
-    // let c_add = paillier_add(&c1, &c2, &pubkey);
-    // let m_add = paillier_decrypt(&privkey, &pubkey, &c_add);
+    let lambda = BigUint::from_bytes_be(&sp1_zkvm::io::read_vec());
+    let mu = BigUint::from_bytes_be(&sp1_zkvm::io::read_vec());
+    let privkey: PrivateKey = (lambda, mu);
+    check_key_pair(&pubkey, &privkey).expect("Private key does not match the public key.");
 
-    // let result = m_add.to_u32().unwrap();
+    let (c_add, c_scaled, m) = if use_precompile {
+        evaluate(&Sp1Backend, &pubkey, &privkey, &c1, &c2, &k)
+    } else {
+        evaluate(&SoftwareBackend, &pubkey, &privkey, &c1, &c2, &k)
+    };
 
-    // sp1_zkvm::io::commit(&result);
+    // The key ID comes first so a verifier can tell which key the
+    // ciphertexts below belong to.
+    sp1_zkvm::io::commit(&KeyId::of(&pubkey).as_bytes().to_vec());
+    sp1_zkvm::io::commit(&c_add.to_bytes_be());
+    sp1_zkvm::io::commit(&c_scaled.to_bytes_be());
+    sp1_zkvm::io::commit(&m.to_bytes_be());
 
     let height = 8;
     let width = 8;
@@ -0,0 +1,52 @@
+//! Compares guest cycle counts of the Paillier operations with the software
+//! and SP1 precompile backends.
+//!
+//! Run with `cargo run --release --bin cycles`. Only moduli whose square fits
+//! in 256 bits (64-bit primes) can use the `uint256_mulmod` precompile;
+//! larger keys show the software fallback on both rows.
+use paillier_rs::backend::Sp1Backend;
+use script::GuestInputs;
+use sp1_sdk::{include_elf, utils, ProverClient};
+
+const ELF: &[u8] = include_elf!("program");
+
+/// Prime sizes to compare.
+const PRIME_BITS: [usize; 3] = [64, 256, 1024];
+
+/// Cycle trackers emitted by the guest around each operation.
+const TRACKERS: [&str; 3] = ["paillier_add", "paillier_scalar_mul", "paillier_decrypt"];
+
+fn main() {
+    utils::setup_logger();
+    let client = ProverClient::from_env();
+
+    println!(
+        "| {:>10} | {:>10} | {:>14} | {:>20} | {:>18} | {:>14} |",
+        "prime bits", "backend", "paillier_add", "paillier_scalar_mul", "paillier_decrypt", "total"
+    );
+    for bits in PRIME_BITS {
+        let inputs = GuestInputs::generate(bits, 42, 17, 5);
+        let n_sq = &inputs.pubkey.0 * &inputs.pubkey.0;
+        for use_precompile in [false, true] {
+            let (_, report) = client.execute(ELF, &inputs.to_stdin(use_precompile)).run().unwrap();
+            let cycles: Vec<u64> = TRACKERS
+                .iter()
+                .map(|name| report.cycle_tracker.get(*name).copied().unwrap_or_default())
+                .collect();
+            let backend = match (use_precompile, n_sq.bits() <= Sp1Backend::PRECOMPILE_BITS) {
+                (false, _) => "software",
+                (true, true) => "sp1",
+                (true, false) => "sp1 (fb)",
+            };
+            println!(
+                "| {:>10} | {:>10} | {:>14} | {:>20} | {:>18} | {:>14} |",
+                bits,
+                backend,
+                cycles[0],
+                cycles[1],
+                cycles[2],
+                report.total_instruction_count()
+            );
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use num_bigint::BigUint;
+use paillier_rs::encrypt::paillier_encrypt;
+use paillier_rs::keygen::{paillier_keygen, PrivateKey, PublicKey};
+use sp1_sdk::SP1Stdin;
+
+/// Plaintext inputs and keys for one run of the guest program.
+pub struct GuestInputs {
+    pub pubkey: PublicKey,
+    pub privkey: PrivateKey,
+    pub c1: BigUint,
+    pub c2: BigUint,
+    pub k: BigUint,
+}
+
+impl GuestInputs {
+    /// Generates a key with `bits`-bit primes and encrypts `m1` and `m2`.
+    pub fn generate(bits: usize, m1: u32, m2: u32, k: u32) -> Self {
+        let (pubkey, privkey) = paillier_keygen(bits);
+        let c1 = paillier_encrypt(&pubkey, &BigUint::from(m1));
+        let c2 = paillier_encrypt(&pubkey, &BigUint::from(m2));
+        GuestInputs { pubkey, privkey, c1, c2, k: BigUint::from(k) }
+    }
+
+    /// Writes the inputs in the order the guest reads them.
+    pub fn to_stdin(&self, use_precompile: bool) -> SP1Stdin {
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&use_precompile);
+        stdin.write_vec(self.pubkey.0.to_bytes_be());
+        stdin.write_vec(self.c1.to_bytes_be());
+        stdin.write_vec(self.c2.to_bytes_be());
+        stdin.write_vec(self.k.to_bytes_be());
+        stdin.write_vec(self.privkey.0.to_bytes_be());
+        stdin.write_vec(self.privkey.1.to_bytes_be());
+        stdin
+    }
+}
@@ -1,19 +1,21 @@
-use sp1_sdk::{include_elf, utils, ProverClient, SP1ProofWithPublicValues, SP1Stdin};
+use num_bigint::BigUint;
+use paillier_rs::keyid::KeyId;
+use script::GuestInputs;
+use sp1_sdk::{include_elf, utils, ProverClient, SP1ProofWithPublicValues};
 
 /// The ELF we want to execute inside the zkVM.
-const ELF: &[u8] =
+const ELF: &[u8] = include_elf!("program");
 
 fn main() {
     // Setup logging.
     utils::setup_logger();
 
-    // Create an input stream and write '500' to it.
-    let n = 1000u32;
+    // Encrypt 42 and 17 under a fresh key; the guest computes 5 * (42 + 17).
+    let inputs = GuestInputs::generate(64, 42, 17, 5);
 
     // The input stream that the program will read from using `sp1_zkvm::io::read`. Note that the
     // types of the elements in the input stream must match the types being read in the program.
-    let mut stdin = SP1Stdin::new();
-    stdin.write(&n);
+    let stdin = inputs.to_stdin(true);
 
     // Create a `ProverClient` method.
     let client = ProverClient::from_env();
@@ -35,11 +37,16 @@ fn main() {
     //
     // Note that this output is read from values committed to in the program using
     // `sp1_zkvm::io::commit`.
-    let a = proof.public_values.read::<u32>();
-    let b = proof.public_values.read::<u32>();
+    let key_id = KeyId::from_slice(&proof.public_values.read::<Vec<u8>>()).expect("malformed key ID");
+    assert_eq!(key_id, KeyId::of(&inputs.pubkey), "proof is for a different key");
+    let c_add = BigUint::from_bytes_be(&proof.public_values.read::<Vec<u8>>());
+    let c_scaled = BigUint::from_bytes_be(&proof.public_values.read::<Vec<u8>>());
+    let m = BigUint::from_bytes_be(&proof.public_values.read::<Vec<u8>>());
 
-    println!("a: {}", a);
-    println!("b: {}", b);
+    println!("Key ID: {}", key_id);
+    println!("Enc(m1 + m2): {}", c_add);
+    println!("Enc(k * (m1 + m2)): {}", c_scaled);
+    println!("k * (m1 + m2): {}", m);
 
     // Verify proof and public values
     client.verify(&proof, &vk).expect("verification failed");